@@ -0,0 +1,81 @@
+#![feature(test)]
+
+extern crate test;
+
+use orga::merk::MerkStore;
+use orga::store::{Read, Write};
+use tempdir::TempDir;
+use test::Bencher;
+
+fn populated_store(dir: &TempDir, cache_capacity: usize) -> MerkStore {
+    let mut store = MerkStore::new(dir.path()).with_cache_capacity(cache_capacity);
+    for i in 0..65_536u32 {
+        store.put(i.to_be_bytes().to_vec(), vec![0; 8]).unwrap();
+    }
+    store.write(vec![]).unwrap();
+
+    store
+}
+
+#[bench]
+fn merkstore_get_8b_2keys_uncached(b: &mut Bencher) {
+    let dir = TempDir::new("orga-bench").unwrap();
+    let store = populated_store(&dir, 0);
+
+    let mut i: u32 = 0;
+    b.iter(|| {
+        store.get(&(i % 2).to_be_bytes()).unwrap();
+        i += 1;
+    });
+}
+
+#[bench]
+fn merkstore_get_8b_2keys_cached(b: &mut Bencher) {
+    let dir = TempDir::new("orga-bench").unwrap();
+    let store = populated_store(&dir, 10_000);
+
+    let mut i: u32 = 0;
+    b.iter(|| {
+        store.get(&(i % 2).to_be_bytes()).unwrap();
+        i += 1;
+    });
+}
+
+#[bench]
+fn merkstore_get_8b_65536keys_uncached(b: &mut Bencher) {
+    let dir = TempDir::new("orga-bench").unwrap();
+    let store = populated_store(&dir, 0);
+
+    let mut i: u32 = 0;
+    b.iter(|| {
+        store.get(&(i % 65_536).to_be_bytes()).unwrap();
+        i += 1;
+    });
+}
+
+#[bench]
+fn merkstore_get_8b_65536keys_cached(b: &mut Bencher) {
+    let dir = TempDir::new("orga-bench").unwrap();
+    let store = populated_store(&dir, 10_000);
+
+    let mut i: u32 = 0;
+    b.iter(|| {
+        store.get(&(i % 65_536).to_be_bytes()).unwrap();
+        i += 1;
+    });
+}
+
+#[bench]
+fn merkstore_write_8b_256keys(b: &mut Bencher) {
+    let dir = TempDir::new("orga-bench").unwrap();
+    let mut store = populated_store(&dir, 10_000);
+
+    let mut i: u32 = 0;
+    b.iter(|| {
+        let key = (i % 256).to_be_bytes();
+        store.get(&key).unwrap();
+        store.put(key.to_vec(), i.to_be_bytes().to_vec()).unwrap();
+        store.write(vec![]).unwrap();
+        i += 1;
+    });
+}
@@ -0,0 +1,37 @@
+//! Prints the keys which differ between the state of two Merk stores, e.g.
+//! two copies of a node's home directory taken at different heights.
+//!
+//! Usage: `cargo run --example state-diff -- <old home> <new home>`
+
+use orga::merk::MerkStore;
+use orga::store::{diff, Change};
+
+fn main() -> orga::Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 3 {
+        eprintln!("Usage: {} <old home> <new home>", args[0]);
+        std::process::exit(1);
+    }
+
+    let old = MerkStore::open_readonly(&args[1]);
+    let new = MerkStore::open_readonly(&args[2]);
+
+    for change in diff(&old, &new) {
+        match change? {
+            Change::Added { key, value } => {
+                println!("+ {} {}", hex::encode(key), hex::encode(value))
+            }
+            Change::Removed { key, value } => {
+                println!("- {} {}", hex::encode(key), hex::encode(value))
+            }
+            Change::Modified { key, old, new } => println!(
+                "~ {} {} -> {}",
+                hex::encode(key),
+                hex::encode(old),
+                hex::encode(new)
+            ),
+        }
+    }
+
+    Ok(())
+}
@@ -16,6 +16,7 @@ pub fn derive(item: TokenStream) -> TokenStream {
             .map(|name| name.into_token_stream())
             .unwrap_or_else(|| num_to_token(i))
     });
+    let names_access = names.clone();
     let types = struct_fields(&item).map(|field| &field.ty);
     let types_where = struct_fields(&item).map(|field| &field.ty);
 
@@ -43,8 +44,11 @@ pub fn derive(item: TokenStream) -> TokenStream {
             fn describe() -> ::orga::describe::Descriptor {
                 ::orga::describe::Builder::new::<Self>().meta::<u8>()
                 #(
-                    .named_child_from_state::<Self, #types>(
+                    .named_child_with_access::<Self, #types>(
                         stringify!(#names),
+                        |value| value
+                            .downcast_ref::<Self>()
+                            .map(|value| &value.#names_access as &dyn ::std::any::Any),
                     )
                 )*
                 .build()
@@ -52,6 +52,23 @@ fn method_args(method: &ImplItemFn) -> Vec<Type> {
         .collect_vec()
 }
 
+fn method_arg_names(method: &ImplItemFn) -> Vec<String> {
+    method
+        .sig
+        .inputs
+        .iter()
+        .skip(1)
+        .enumerate()
+        .map(|(i, arg)| match arg {
+            FnArg::Typed(PatType { pat, .. }) => match &**pat {
+                Pat::Ident(PatIdent { ident, .. }) => ident.to_string(),
+                _ => format!("arg{}", i),
+            },
+            _ => format!("arg{}", i),
+        })
+        .collect_vec()
+}
+
 fn enum_ident(item: &ItemImpl) -> Ident {
     format_ident!("{}{}", self_ty_ident(&item), "MethodCall")
 }
@@ -195,6 +212,51 @@ fn method_call_impl(tokens: &mut TokenStream2, item: &ItemImpl) {
     })
 }
 
+fn describe_calls_impl(tokens: &mut TokenStream2, item: &ItemImpl) {
+    let Types {
+        encode_trait,
+        describe_calls_trait,
+        method_descriptor_ty,
+        arg_descriptor_ty,
+        ..
+    } = Types::default();
+    let ident = self_ty_ident(&item);
+    let (imp, ty, wher) = item.generics.split_for_impl();
+
+    // methods are described in variant order, so a method's index is its
+    // position in the list
+    let methods = call_methods(&item);
+    let arg_bounds = methods
+        .iter()
+        .flat_map(|method| method_args(method))
+        .map(|ty| quote! { #ty: #encode_trait })
+        .collect_vec();
+    let descriptors = methods.iter().map(|method| {
+        let name = method.sig.ident.to_string();
+        let args = method_args(method);
+        let arg_names = method_arg_names(method);
+        quote! {
+            #method_descriptor_ty::call(
+                #name,
+                vec![#( #arg_descriptor_ty::new::<#args>(#arg_names) ),*],
+            )
+        }
+    });
+
+    let preds = wher
+        .map(|w| w.predicates.iter().collect_vec())
+        .unwrap_or_default();
+    let wher = quote! { where #(#preds,)* #(#arg_bounds,)* };
+
+    tokens.extend(quote! {
+        impl #imp #describe_calls_trait for #ident #ty #wher {
+            fn describe_calls() -> Vec<#method_descriptor_ty> {
+                vec![#( #descriptors ),*]
+            }
+        }
+    })
+}
+
 fn strip_call_attr(item: &mut ItemImpl) {
     for item in item.items.iter_mut() {
         if let ImplItem::Fn(method) = item {
@@ -336,6 +398,7 @@ pub fn call_block(_args: TokenStream, input: TokenStream) -> TokenStream {
     method_call_enum(&mut tokens, &item);
     method_call_impl(&mut tokens, &item);
     call_builder(&mut tokens, &item);
+    describe_calls_impl(&mut tokens, &item);
     strip_call_attr(&mut item);
     tokens.extend(item.into_token_stream());
 
@@ -49,6 +49,23 @@ fn method_args(method: &ImplItemFn) -> Vec<Type> {
         .collect_vec()
 }
 
+fn method_arg_names(method: &ImplItemFn) -> Vec<String> {
+    method
+        .sig
+        .inputs
+        .iter()
+        .skip(1)
+        .enumerate()
+        .map(|(i, arg)| match arg {
+            FnArg::Typed(PatType { pat, .. }) => match &**pat {
+                Pat::Ident(PatIdent { ident, .. }) => ident.to_string(),
+                _ => format!("arg{}", i),
+            },
+            _ => format!("arg{}", i),
+        })
+        .collect_vec()
+}
+
 fn enum_ident(item: &ItemImpl) -> Ident {
     format_ident!("{}{}", self_ty_ident(&item), "MethodQuery")
 }
@@ -206,6 +223,65 @@ fn method_query_impl(tokens: &mut TokenStream2, item: &ItemImpl) {
     })
 }
 
+fn describe_queries_impl(tokens: &mut TokenStream2, item: &ItemImpl) {
+    let Types {
+        encode_trait,
+        decode_trait,
+        error_ty,
+        result_ty,
+        describe_queries_trait,
+        method_descriptor_ty,
+        arg_descriptor_ty,
+        ..
+    } = Types::default();
+    let ident = self_ty_ident(&item);
+    let (imp, ty, wher) = item.generics.split_for_impl();
+
+    let methods = query_methods(&item);
+    let arg_bounds = methods
+        .iter()
+        .flat_map(|method| method_args(method))
+        .map(|ty| quote! { #ty: #encode_trait + #decode_trait })
+        .collect_vec();
+    let descriptors = methods.iter().map(|method| {
+        let method_ident = &method.sig.ident;
+        let name = method_ident.to_string();
+        let args = method_args(method);
+        let arg_names = method_arg_names(method);
+        let arg_vars = args
+            .iter()
+            .enumerate()
+            .map(|(i, _)| format_ident!("var{}", i))
+            .collect_vec();
+        quote! {
+            #method_descriptor_ty::query(
+                #name,
+                vec![#( #arg_descriptor_ty::new::<#args>(#arg_names) ),*],
+                |value, mut args| -> #result_ty<String> {
+                    let value = value.downcast_ref::<Self>().ok_or_else(|| {
+                        #error_ty::Downcast(format!("Expected {}", ::std::any::type_name::<Self>()))
+                    })?;
+                    #( let #arg_vars: #args = #decode_trait::decode(&mut args)?; )*
+                    ::orga::describe::format_value(value.#method_ident(#( #arg_vars ),*))
+                },
+            )
+        }
+    });
+
+    let preds = wher
+        .map(|w| w.predicates.iter().collect_vec())
+        .unwrap_or_default();
+    let wher = quote! { where Self: 'static, #(#preds,)* #(#arg_bounds,)* };
+
+    tokens.extend(quote! {
+        impl #imp #describe_queries_trait for #ident #ty #wher {
+            fn describe_queries() -> Vec<#method_descriptor_ty> {
+                vec![#( #descriptors ),*]
+            }
+        }
+    })
+}
+
 fn strip_query_attr(item: &mut ItemImpl) {
     for item in item.items.iter_mut() {
         if let ImplItem::Fn(method) = item {
@@ -310,6 +386,7 @@ pub fn query_block(_args: TokenStream, input: TokenStream) -> TokenStream {
     let mut tokens = quote! {}.into();
     method_query_enum(&mut tokens, &item);
     method_query_impl(&mut tokens, &item);
+    describe_queries_impl(&mut tokens, &item);
     strip_query_attr(&mut item);
     tokens.extend(item.into_token_stream());
 
@@ -216,6 +216,10 @@ pub struct Types {
     pub field_query_trait: TokenStream,
     pub method_query_trait: TokenStream,
     pub query_item_ty: TokenStream,
+    pub describe_calls_trait: TokenStream,
+    pub describe_queries_trait: TokenStream,
+    pub method_descriptor_ty: TokenStream,
+    pub arg_descriptor_ty: TokenStream,
 }
 
 impl Default for Types {
@@ -252,6 +256,10 @@ impl Default for Types {
             field_query_trait: quote! { ::orga::query::FieldQuery },
             method_query_trait: quote! { ::orga::query::MethodQuery },
             query_item_ty: quote! { ::orga::query::Item },
+            describe_calls_trait: quote! { ::orga::describe::DescribeCalls },
+            describe_queries_trait: quote! { ::orga::describe::DescribeQueries },
+            method_descriptor_ty: quote! { ::orga::describe::MethodDescriptor },
+            arg_descriptor_ty: quote! { ::orga::describe::ArgDescriptor },
         }
     }
 }
@@ -0,0 +1,390 @@
+#[cfg(feature = "merk-full")]
+use crate::merk::cache::DEFAULT_CACHE_CAPACITY;
+#[cfg(feature = "merk-full")]
+use crate::merk::snapshot::SnapshotPolicy;
+#[cfg(feature = "merk-full")]
+use crate::merk::store::DEFAULT_QUERY_HEIGHTS;
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Node-level settings for the app, stored in `app.toml` next to
+/// Tendermint's `config.toml`.
+///
+/// Unlike consensus parameters, these settings only affect the local node and
+/// may differ between nodes. The config is added to the [Context] when the
+/// node starts so the app can read it.
+///
+/// The `snapshots`, `pruning` and `query` sections configure the
+/// `MerkStore`, so they are only available with the `merk-full` feature.
+///
+/// [Context]: crate::context::Context
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig {
+    /// Stops the node after committing this height, or never if 0. Takes
+    /// precedence over the `ORGA_STOP_HEIGHT` environment variable.
+    pub halt_height: u64,
+    #[cfg(feature = "merk-full")]
+    pub snapshots: SnapshotConfig,
+    #[cfg(feature = "merk-full")]
+    pub pruning: PruningConfig,
+    #[cfg(feature = "merk-full")]
+    pub query: QueryConfig,
+    pub log: LogConfig,
+    pub upgrade: UpgradeConfig,
+}
+
+/// Settings for creating state sync snapshots.
+#[cfg(feature = "merk-full")]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SnapshotConfig {
+    /// Whether snapshots are created automatically when committing.
+    pub enabled: bool,
+    /// Creates a snapshot at every multiple of this height, or never if 0.
+    pub interval: u64,
+    /// Specific heights to create snapshots at, which are never pruned.
+    pub heights: Vec<u64>,
+}
+
+/// Settings for removing data the node no longer needs.
+#[cfg(feature = "merk-full")]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PruningConfig {
+    /// The number of interval snapshots to keep on disk.
+    pub keep_snapshots: u64,
+}
+
+/// Settings for serving queries.
+#[cfg(feature = "merk-full")]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QueryConfig {
+    /// The number of recent heights kept in memory which can be queried.
+    pub retain_heights: usize,
+    /// The maximum number of committed values kept in the store's read cache.
+    pub cache_capacity: usize,
+}
+
+/// Settings for the node's log output. The node does not install a logger
+/// itself; apps using the `logger` feature can install one with these
+/// settings with [LogConfig::init].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// The log filter, in the same format as `RUST_LOG` (e.g.
+    /// `info,orga=debug`). `RUST_LOG` takes precedence when set.
+    pub level: String,
+    /// Whether Tendermint's logs are printed.
+    pub tendermint: bool,
+}
+
+/// Settings for the upgrade supervisor, which switches the node's binary when
+/// the network version changes.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UpgradeConfig {
+    /// How long a binary must run after switching to it before it is
+    /// considered to have started successfully, in seconds.
+    pub start_timeout_seconds: u64,
+    /// The SHA-256 hash of the binary for each network version, both
+    /// hex-encoded. Binaries without a configured hash are never run.
+    pub binaries: BTreeMap<String, String>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            halt_height: 0,
+            #[cfg(feature = "merk-full")]
+            snapshots: Default::default(),
+            #[cfg(feature = "merk-full")]
+            pruning: Default::default(),
+            #[cfg(feature = "merk-full")]
+            query: Default::default(),
+            log: Default::default(),
+            upgrade: Default::default(),
+        }
+    }
+}
+
+#[cfg(feature = "merk-full")]
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        let policy = SnapshotPolicy::default();
+        Self {
+            enabled: policy.enabled,
+            interval: policy.interval,
+            heights: policy.heights,
+        }
+    }
+}
+
+#[cfg(feature = "merk-full")]
+impl Default for PruningConfig {
+    fn default() -> Self {
+        Self {
+            keep_snapshots: SnapshotPolicy::default().keep_recent,
+        }
+    }
+}
+
+#[cfg(feature = "merk-full")]
+impl Default for QueryConfig {
+    fn default() -> Self {
+        Self {
+            retain_heights: DEFAULT_QUERY_HEIGHTS,
+            cache_capacity: DEFAULT_CACHE_CAPACITY,
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            tendermint: false,
+        }
+    }
+}
+
+impl Default for UpgradeConfig {
+    fn default() -> Self {
+        Self {
+            start_timeout_seconds: 60,
+            binaries: BTreeMap::new(),
+        }
+    }
+}
+
+impl FromStr for AppConfig {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let config: Self = toml_edit::de::from_str(s).map_err(|e| Error::Config(e.to_string()))?;
+        config.validate()?;
+
+        Ok(config)
+    }
+}
+
+impl AppConfig {
+    /// Loads the config from the given path, writing the default config there
+    /// first if the file does not exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            let config = Self::default();
+            config.write(path)?;
+            return Ok(config);
+        }
+
+        std::fs::read_to_string(path)?
+            .parse()
+            .map_err(|e| Error::Config(format!("Invalid {}: {}", path.display(), e)))
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        toml_edit::ser::to_string_pretty(self).map_err(|e| Error::Config(e.to_string()))
+    }
+
+    /// Checks that the settings are consistent and can be used by the node.
+    pub fn validate(&self) -> Result<()> {
+        #[cfg(feature = "merk-full")]
+        self.validate_store()?;
+
+        for directive in self.log.level.split(',') {
+            if let Some((_, level)) = directive.split_once('=') {
+                if log::LevelFilter::from_str(level).is_err() {
+                    return Err(Error::Config(format!("Invalid log level: {}", directive)));
+                }
+            }
+        }
+
+        for (version, hash) in self.upgrade.binaries.iter() {
+            if version.is_empty() || hex::decode(version).is_err() {
+                return Err(Error::Config(format!(
+                    "Invalid upgrade version: {}",
+                    version
+                )));
+            }
+            if !matches!(hex::decode(hash), Ok(bytes) if bytes.len() == 32) {
+                return Err(Error::Config(format!(
+                    "Invalid SHA-256 hash for version {}: {}",
+                    version, hash
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "merk-full")]
+    fn validate_store(&self) -> Result<()> {
+        if self.snapshots.interval > 0 && self.pruning.keep_snapshots == 0 {
+            return Err(Error::Config(
+                "pruning.keep_snapshots must be at least 1 when snapshots.interval is set"
+                    .to_string(),
+            ));
+        }
+
+        if self.query.retain_heights == 0 {
+            return Err(Error::Config(
+                "query.retain_heights must be at least 1".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Overrides a setting, e.g. from a command-line flag. The key is the
+    /// setting's dotted path (e.g. `snapshots.interval`) and the value is
+    /// parsed as TOML, or used as a string if it is not valid TOML.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let mut doc: toml_edit::Document = self
+            .to_toml()?
+            .parse()
+            .map_err(|e: toml_edit::TomlError| Error::Config(e.to_string()))?;
+
+        let value = value
+            .parse::<toml_edit::Value>()
+            .unwrap_or_else(|_| value.into());
+
+        let mut path = key.split('.').peekable();
+        let mut table = doc.as_table_mut();
+        while let Some(part) = path.next() {
+            if path.peek().is_none() {
+                table.insert(part, toml_edit::value(value));
+                break;
+            }
+            table = table
+                .get_mut(part)
+                .and_then(|item| item.as_table_mut())
+                .ok_or_else(|| Error::Config(format!("Unknown setting: {}", key)))?;
+        }
+
+        *self = doc
+            .to_string()
+            .parse()
+            .map_err(|e: Error| Error::Config(format!("Invalid value for {}: {}", key, e)))?;
+
+        Ok(())
+    }
+
+    /// Applies overrides given as `key=value` strings, as accepted by
+    /// [AppConfig::set].
+    pub fn apply_overrides<T: AsRef<str>>(&mut self, overrides: &[T]) -> Result<()> {
+        for item in overrides {
+            let (key, value) = item.as_ref().split_once('=').ok_or_else(|| {
+                Error::Config(format!("Expected key=value, got {}", item.as_ref()))
+            })?;
+            self.set(key.trim(), value.trim())?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "merk-full")]
+    /// The snapshot policy described by the `snapshots` and `pruning`
+    /// settings.
+    pub fn snapshot_policy(&self) -> SnapshotPolicy {
+        SnapshotPolicy {
+            enabled: self.snapshots.enabled,
+            interval: self.snapshots.interval,
+            keep_recent: self.pruning.keep_snapshots,
+            heights: self.snapshots.heights.clone(),
+        }
+    }
+
+    #[cfg(feature = "merk-full")]
+    pub fn set_snapshot_policy(&mut self, policy: SnapshotPolicy) {
+        self.snapshots = SnapshotConfig {
+            enabled: policy.enabled,
+            interval: policy.interval,
+            heights: policy.heights,
+        };
+        self.pruning.keep_snapshots = policy.keep_recent;
+    }
+
+    /// The height to stop the node at, if any.
+    pub fn halt_height(&self) -> Option<u64> {
+        (self.halt_height > 0).then_some(self.halt_height)
+    }
+}
+
+impl LogConfig {
+    /// Initializes the global logger with the configured filter, unless a
+    /// logger has already been set, e.g. with `node.app_config().log.init()`.
+    #[cfg(feature = "logger")]
+    pub fn init(&self) {
+        let filter = std::env::var("RUST_LOG").unwrap_or_else(|_| self.level.clone());
+        let _ = pretty_env_logger::formatted_builder()
+            .parse_filters(&filter)
+            .try_init();
+    }
+}
+
+#[cfg(all(test, feature = "merk-full"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_and_override() -> Result<()> {
+        let home = tempdir::TempDir::new("orga-app-config").unwrap();
+        let path = home.path().join("app.toml");
+
+        let config = AppConfig::load(&path)?;
+        assert_eq!(config, AppConfig::default());
+        assert!(path.exists());
+
+        std::fs::write(
+            &path,
+            r#"
+            halt_height = 100
+
+            [snapshots]
+            interval = 500
+
+            [query]
+            retain_heights = 5
+            "#,
+        )?;
+        let mut config = AppConfig::load(&path)?;
+        assert_eq!(config.halt_height(), Some(100));
+        assert_eq!(config.snapshot_policy().interval, 500);
+        assert_eq!(config.query.retain_heights, 5);
+        assert!(config.set("upgrade.binaries.02", "abcd").is_err());
+        config.set("upgrade.binaries.02", &"ab".repeat(32))?;
+        assert_eq!(config.upgrade.binaries["02"], "ab".repeat(32));
+        assert_eq!(config.log, LogConfig::default());
+
+        config.apply_overrides(&[
+            "halt_height=0",
+            "log.tendermint=true",
+            "log.level=debug,orga=trace",
+        ])?;
+        assert_eq!(config.halt_height(), None);
+        assert!(config.log.tendermint);
+        assert_eq!(config.log.level, "debug,orga=trace");
+
+        assert!(config.set("query.retain_heights", "0").is_err());
+        assert!(config.set("foo.bar", "1").is_err());
+        assert!(config.set("log.level", "orga=loud").is_err());
+        assert!(config.set("log.tendermint", "maybe").is_err());
+        assert_eq!(config.query.retain_heights, 5);
+
+        std::fs::write(&path, "[snapshots]\nfoo = 1\n")?;
+        assert!(AppConfig::load(&path).is_err());
+
+        Ok(())
+    }
+}
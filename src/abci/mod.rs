@@ -4,9 +4,17 @@ use crate::state::State;
 
 use crate::Result;
 #[cfg(feature = "abci")]
+mod config;
+#[cfg(feature = "abci")]
+pub use config::*;
+#[cfg(feature = "abci")]
 mod node;
 #[cfg(feature = "abci")]
 pub use node::*;
+#[cfg(feature = "abci")]
+mod supervisor;
+#[cfg(feature = "abci")]
+pub use supervisor::*;
 
 pub mod prost;
 
@@ -16,6 +24,7 @@ pub use tendermint_proto::v0_34::abci as messages;
 #[cfg(feature = "abci")]
 mod server {
     use super::*;
+    #[cfg(feature = "merk-full")]
     use crate::merk::MerkStore;
     use crate::store::{BufStore, BufStoreMap, MapStore, Read, Shared, Write, KV};
     use crate::Error;
@@ -30,9 +39,13 @@ mod server {
 
     /// Top-level struct for running an ABCI application. Maintains an ABCI server,
     /// mempool, and handles committing data to the store.
-    pub struct ABCIStateMachine<A: Application> {
+    ///
+    /// The state machine can be backed by any [`ABCIStore`](trait.ABCIStore.html),
+    /// e.g. a `MerkStore` for a full node or a [`MemStore`](struct.MemStore.html)
+    /// for tests.
+    pub struct ABCIStateMachine<A: Application<S>, S: ABCIStore> {
         app: Option<A>,
-        store: Option<Shared<MerkStore>>,
+        store: Option<Shared<S>>,
         receiver: Receiver<(Request, SyncSender<Response>)>,
         sender: SyncSender<(Request, SyncSender<Response>)>,
         mempool_state: Option<BufStoreMap>,
@@ -40,17 +53,18 @@ mod server {
         height: u64,
         skip_init_chain: bool,
         header: Option<Header>,
+        stop_height: Option<u64>,
         shutdown: Arc<RwLock<Option<Error>>>,
         shutdown_notifier: Arc<RwLock<bool>>,
     }
 
-    impl<A: Application> ABCIStateMachine<A> {
+    impl<A: Application<S>, S: ABCIStore> ABCIStateMachine<A, S> {
         /// Constructs an `ABCIStateMachine` from the given app (a set of handlers
         /// for transactions and blocks), and store (a key/value store to persist
         /// the state data).
         pub fn new(
             app: A,
-            store: MerkStore,
+            store: S,
             skip_init_chain: bool,
             shutdown: Arc<RwLock<Option<Error>>>,
             shutdown_notifier: Arc<RwLock<bool>>,
@@ -66,11 +80,20 @@ mod server {
                 height: 0,
                 skip_init_chain,
                 header: None,
+                stop_height: env_stop_height(),
                 shutdown,
                 shutdown_notifier,
             }
         }
 
+        /// Sets the height after which the state machine stops, overriding
+        /// the `ORGA_STOP_HEIGHT` environment variable.
+        #[must_use]
+        pub fn stop_height(mut self, height: Option<u64>) -> Self {
+            self.stop_height = height;
+            self
+        }
+
         /// Handles a single incoming ABCI request.
         ///
         /// Some messages, such as `info`, `flush`, and `echo` are automatically
@@ -166,13 +189,8 @@ mod server {
                     Ok(Res::InitChain(res_init_chain))
                 }
                 Req::BeginBlock(req) => {
-                    if let Some(stop_height_str) = env::var_os("ORGA_STOP_HEIGHT") {
-                        let stop_height: i64 = stop_height_str
-                            .into_string()
-                            .unwrap()
-                            .parse()
-                            .expect("Invalid ORGA_STOP_HEIGHT value");
-                        if req.header.as_ref().unwrap().height > stop_height {
+                    if let Some(stop_height) = self.stop_height {
+                        if req.header.as_ref().unwrap().height as u64 > stop_height {
                             return Err(Error::ABCI(format!(
                                 "Reached stop height ({})",
                                 stop_height
@@ -358,14 +376,6 @@ mod server {
         /// Creates a TCP server for the ABCI protocol and begins handling the
         /// incoming connections.
         pub fn listen<SA: ToSocketAddrs>(mut self, addr: SA) -> Result<Arc<RwLock<bool>>> {
-            if let Some(stop_height_str) = env::var_os("ORGA_STOP_HEIGHT") {
-                let _stop_height: u64 = stop_height_str
-                    .into_string()
-                    .unwrap()
-                    .parse()
-                    .expect("Invalid ORGA_STOP_HEIGHT value");
-            }
-
             let server = abci2::Server::listen(addr)?;
 
             // TODO: keep workers in struct
@@ -406,12 +416,7 @@ mod server {
                 cb.send(res).unwrap();
 
                 if is_commit {
-                    if let Some(stop_height_str) = env::var_os("ORGA_STOP_HEIGHT") {
-                        let stop_height: u64 = stop_height_str
-                            .into_string()
-                            .unwrap()
-                            .parse()
-                            .expect("Invalid ORGA_STOP_HEIGHT value");
+                    if let Some(stop_height) = self.stop_height {
                         if self.height >= stop_height {
                             let mut shutdown = self.shutdown_notifier.write().unwrap();
                             *shutdown = true;
@@ -437,6 +442,17 @@ mod server {
         }
     }
 
+    /// Reads the stop height from the `ORGA_STOP_HEIGHT` environment variable.
+    fn env_stop_height() -> Option<u64> {
+        env::var_os("ORGA_STOP_HEIGHT").map(|stop_height_str| {
+            stop_height_str
+                .into_string()
+                .unwrap()
+                .parse()
+                .expect("Invalid ORGA_STOP_HEIGHT value")
+        })
+    }
+
     struct Worker {
         #[allow(dead_code)]
         thread: std::thread::JoinHandle<()>, // TODO: keep handle to connection or socket so we can close it
@@ -479,7 +495,14 @@ mod server {
         }
     }
 
-    pub type WrappedMerk = Shared<BufStore<Shared<BufStore<Shared<MerkStore>>>>>;
+    /// The store passed to [`Application`](trait.Application.html) handlers: the
+    /// backing store, wrapped in a buffer of the pending block's writes and a
+    /// buffer of the current request's writes.
+    pub type WrappedStore<S> = Shared<BufStore<Shared<BufStore<Shared<S>>>>>;
+
+    #[cfg(feature = "merk-full")]
+    pub type WrappedMerk = WrappedStore<MerkStore>;
+
     /// An interface for handling ABCI requests.
     ///
     /// All methods have a default implemenation which returns an empty response.
@@ -487,10 +510,10 @@ mod server {
     /// Only exposes the core state machine requests since messages like Echo and
     /// Info are automatically handled within
     /// [`ABCIStateMachine`](struct.ABCIStateMachine.html).
-    pub trait Application {
+    pub trait Application<S: ABCIStore> {
         fn init_chain(
             &self,
-            _store: WrappedMerk,
+            _store: WrappedStore<S>,
             _req: RequestInitChain,
         ) -> Result<ResponseInitChain> {
             Ok(Default::default())
@@ -498,7 +521,7 @@ mod server {
 
         fn begin_block(
             &self,
-            _store: WrappedMerk,
+            _store: WrappedStore<S>,
             _req: RequestBeginBlock,
         ) -> Result<ResponseBeginBlock> {
             Ok(Default::default())
@@ -506,7 +529,7 @@ mod server {
 
         fn deliver_tx(
             &self,
-            _store: WrappedMerk,
+            _store: WrappedStore<S>,
             _req: RequestDeliverTx,
         ) -> Result<ResponseDeliverTx> {
             Ok(Default::default())
@@ -514,17 +537,21 @@ mod server {
 
         fn end_block(
             &self,
-            _store: WrappedMerk,
+            _store: WrappedStore<S>,
             _req: RequestEndBlock,
         ) -> Result<ResponseEndBlock> {
             Ok(Default::default())
         }
 
-        fn check_tx(&self, _store: WrappedMerk, _req: RequestCheckTx) -> Result<ResponseCheckTx> {
+        fn check_tx(
+            &self,
+            _store: WrappedStore<S>,
+            _req: RequestCheckTx,
+        ) -> Result<ResponseCheckTx> {
             Ok(Default::default())
         }
 
-        fn query(&self, _store: Shared<MerkStore>, _req: RequestQuery) -> Result<ResponseQuery> {
+        fn query(&self, _store: Shared<S>, _req: RequestQuery) -> Result<ResponseQuery> {
             Ok(Default::default())
         }
     }
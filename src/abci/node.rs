@@ -1,14 +1,23 @@
-use super::{ABCIStateMachine, ABCIStore, AbciQuery, App, Application, WrappedMerk};
+use super::{
+    ABCIStateMachine, ABCIStore, AbciQuery, App, AppConfig, Application, MemStore, WrappedStore,
+};
 use crate::call::Call;
 use crate::context::Context;
 use crate::encoding::Decode;
+#[cfg(feature = "merk-full")]
+use crate::merk::batchproof::{prove_batch, BatchQuery};
+#[cfg(feature = "merk-full")]
 use crate::merk::memsnapshot::MemSnapshot;
+#[cfg(feature = "merk-full")]
+use crate::merk::snapshot::SnapshotPolicy;
+#[cfg(feature = "merk-full")]
 use crate::merk::{MerkStore, ProofBuilder};
+#[cfg(feature = "merk-full")]
 use crate::migrate::Migrate;
 use crate::plugins::{ABCICall, ABCIPlugin};
 use crate::query::Query;
 use crate::state::State;
-use crate::store::{BackingStore, Read, Shared, Store, Write};
+use crate::store::{BackingStore, Read, ReadWrite, Shared, Store, Write};
 use crate::tendermint::Child as TendermintChild;
 use crate::tendermint::Tendermint;
 use crate::{Error, Result};
@@ -21,6 +30,11 @@ use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use tendermint_proto::v0_34::abci::*;
 
+/// The exit code of a node process which stopped because the network version
+/// changed or the stop height was reached. The version the network is running
+/// is written to the `network_version` file in the node's home directory.
+pub const UPGRADE_EXIT_CODE: i32 = 138;
+
 pub struct Child {
     tm_child: TendermintChild,
     abci_shutdown_handle: Arc<RwLock<Option<Error>>>,
@@ -68,8 +82,59 @@ impl Child {
     }
 }
 
-pub struct Node<A> {
+/// A store which a [Node] can open as the backing store of its state machine.
+pub trait NodeStore: ABCIStore + Sized + 'static {
+    /// Opens the store, using the given directory for any persisted data.
+    fn open(path: &Path) -> Result<Self>;
+
+    /// Applies the node's app config to the store, for stores which support
+    /// its settings.
+    fn configure(&mut self, _config: &AppConfig) {}
+
+    /// Creates a state sync snapshot of the committed state, returning its
+    /// height. Stores which do not support snapshots return an error.
+    fn create_snapshot(&mut self) -> Result<u64> {
+        Err(Error::Store("Store does not support snapshots".into()))
+    }
+}
+
+#[cfg(feature = "merk-full")]
+impl NodeStore for MerkStore {
+    fn open(path: &Path) -> Result<Self> {
+        Ok(MerkStore::new(path))
+    }
+
+    fn configure(&mut self, config: &AppConfig) {
+        self.set_snapshot_policy(config.snapshot_policy());
+        self.set_query_heights(config.query.retain_heights);
+        self.set_cache_capacity(config.query.cache_capacity);
+    }
+
+    fn create_snapshot(&mut self) -> Result<u64> {
+        MerkStore::create_snapshot(self)
+    }
+}
+
+impl NodeStore for MemStore {
+    fn open(_path: &Path) -> Result<Self> {
+        Ok(MemStore::new())
+    }
+}
+
+/// The store a [Node] runs on unless another is chosen with
+/// [Node::with_store]: a [MerkStore] when the `merk-full` feature is enabled.
+#[cfg(feature = "merk-full")]
+pub type DefaultNodeStore = MerkStore;
+
+/// Without the `merk-full` feature there is no persistent store to fall back
+/// on, so the store type of a [Node] has to be given explicitly, e.g.
+/// `Node<App, MemStore>`. This type cannot be used as a store.
+#[cfg(not(feature = "merk-full"))]
+pub enum DefaultNodeStore {}
+
+pub struct Node<A, S = DefaultNodeStore> {
     _app: PhantomData<A>,
+    _store: PhantomData<S>,
     tm_home: PathBuf,
     merk_home: PathBuf,
     home: PathBuf,
@@ -81,6 +146,7 @@ pub struct Node<A> {
     logs: bool,
     skip_init_chain: bool,
     flags: Vec<String>,
+    app_config: AppConfig,
 }
 
 impl Node<()> {
@@ -93,6 +159,7 @@ impl Node<()> {
         .join(format!(".{}", name).as_str())
     }
 
+    #[cfg(feature = "merk-full")]
     pub fn height<P: AsRef<Path>>(home: P) -> Result<u64> {
         let home = home.as_ref();
 
@@ -111,7 +178,7 @@ pub struct DefaultConfig {
     pub timeout_commit: Option<String>,
 }
 
-impl<A: App> Node<A> {
+impl<A: App, S: NodeStore> Node<A, S> {
     pub async fn new<P: AsRef<Path>>(
         home: P,
         chain_id: Option<&str>,
@@ -178,6 +245,9 @@ impl<A: App> Node<A> {
             .expect("Failed to modify genesis chain ID");
         }
 
+        let app_config =
+            AppConfig::load(tm_home.join("config/app.toml")).expect("Failed to load app config");
+
         let abci_port: u16 = if cfg_path.exists() {
             let toml = read_toml();
             let abci_laddr = toml["proxy_app"]
@@ -196,6 +266,7 @@ impl<A: App> Node<A> {
 
         Node {
             _app: PhantomData,
+            _store: PhantomData,
             merk_home,
             tm_home,
             home,
@@ -207,10 +278,155 @@ impl<A: App> Node<A> {
             stderr: Stdio::null(),
             logs: false,
             flags: vec![],
+            app_config,
         }
     }
+}
+
+#[cfg(feature = "merk-full")]
+impl<A: App> Node<A, MerkStore> {
+    // TODO: remove when we don't require compat migrations
+    pub fn migrate(self, version: Vec<u8>, compat_mode: bool, repair: bool) -> Self
+    where
+        ABCIPlugin<A>: Migrate,
+    {
+        let merk_store = crate::merk::MerkStore::new(&self.merk_home);
+        if let Some(store_ver) = merk_store.merk().get_aux(b"consensus_version").unwrap() {
+            if store_ver == version {
+                log::info!("Node has already migrated");
+                return self;
+            }
+        }
+
+        let genesis: serde_json::Value =
+            std::fs::read_to_string(self.tm_home.join("config/genesis.json"))
+                .unwrap()
+                .parse()
+                .unwrap();
+        let chain_id = genesis["chain_id"].as_str().unwrap();
+        Context::add(crate::plugins::ChainId(chain_id.to_string()));
+
+        log::info!("Migrating store data... (This might take a while)");
+        let store = Shared::new(merk_store);
+        let mut store = Store::new(BackingStore::Merk(store));
+        let bytes = store.get(&[]).unwrap().unwrap();
+
+        orga::set_compat_mode(compat_mode);
+        let mut app =
+            ABCIPlugin::<A>::migrate(store.clone(), store.clone(), &mut bytes.as_slice()).unwrap();
+        orga::set_compat_mode(false);
+
+        app.attach(store.clone()).unwrap();
+
+        let mut bytes = vec![];
+        app.flush(&mut bytes).unwrap();
+        store.put(vec![], bytes).unwrap();
+        store
+            .put(
+                crate::upgrade::VERSION_KEY.to_vec(),
+                [vec![version.len() as u8], version.clone()].concat(),
+            )
+            .unwrap();
+        if let BackingStore::Merk(merk_store) = store.into_backing_store().into_inner() {
+            let mut store = merk_store.into_inner();
+            store
+                .write(vec![(b"consensus_version".to_vec(), Some(version))])
+                .unwrap();
+
+            if repair {
+                store.into_merk().repair().unwrap();
+            }
+        } else {
+            unreachable!();
+        }
+
+        self
+    }
+
+    pub fn init_from_store(self, source: impl AsRef<Path>, height: Option<u64>) -> Self {
+        MerkStore::init_from(source, &self.merk_home, height).unwrap();
+
+        self
+    }
+
+    /// Exports the state sync snapshot at the given height to an archive file.
+    #[must_use]
+    pub fn export_snapshot(self, height: u64, path: impl AsRef<Path>) -> Self {
+        let store = MerkStore::new(&self.merk_home);
+        let manifest = store
+            .export_snapshot(height, path)
+            .expect("Failed to export snapshot");
+        log::info!(
+            "Exported snapshot at height {} ({} chunks)",
+            manifest.height,
+            manifest.chunks
+        );
+
+        self
+    }
+
+    /// Initializes the node's store from a snapshot archive, allowing it to
+    /// start without state syncing from peers. The archive is verified against
+    /// the trusted `height` and `app_hash`, which must come from a verified
+    /// block header rather than from the archive's source.
+    ///
+    /// This only restores the app state; see [MerkStore::import_snapshot] for
+    /// the Tendermint data the node also needs before it can start.
+    #[must_use]
+    pub fn import_snapshot(self, path: impl AsRef<Path>, height: u64, app_hash: &[u8]) -> Self {
+        let store = MerkStore::import_snapshot(path, &self.merk_home, height, app_hash)
+            .expect("Failed to import snapshot");
+        log::info!("Imported snapshot at height {}", store.height().unwrap());
+
+        self
+    }
+}
+
+impl<A: App, S: NodeStore> Node<A, S> {
+    /// Creates a state sync snapshot of the stored state at its current height,
+    /// regardless of the snapshot policy, and returns its height. The store is
+    /// opened the same way [Node::run] opens it, so this must not be called
+    /// while the node is running.
+    pub fn create_snapshot(&self) -> Result<u64> {
+        let mut store = S::open(&self.merk_home)?;
+        store.configure(&self.app_config);
+        let height = store.create_snapshot()?;
+        log::info!("Created snapshot at height {}", height);
+
+        Ok(height)
+    }
+
+    /// Runs the node's state machine on a different kind of backing store,
+    /// e.g. a [MemStore] for tests.
+    #[must_use]
+    pub fn with_store<S2: NodeStore>(self) -> Node<A, S2> {
+        Node {
+            _app: PhantomData,
+            _store: PhantomData,
+            tm_home: self.tm_home,
+            merk_home: self.merk_home,
+            home: self.home,
+            abci_port: self.abci_port,
+            genesis_bytes: self.genesis_bytes,
+            p2p_persistent_peers: self.p2p_persistent_peers,
+            stdout: self.stdout,
+            stderr: self.stderr,
+            logs: self.logs,
+            skip_init_chain: self.skip_init_chain,
+            flags: self.flags,
+            app_config: self.app_config,
+        }
+    }
+
+    /// The node's app config, loaded from `app.toml` in Tendermint's config
+    /// directory.
+    pub fn app_config(&self) -> &AppConfig {
+        &self.app_config
+    }
 
     pub async fn run(self) -> Result<Child> {
+        Context::add(self.app_config.clone());
+
         let tm_home = self.tm_home.clone();
         let abci_port = self.abci_port;
         let stdout = self.stdout;
@@ -221,7 +437,7 @@ impl<A: App> Node<A> {
         let mut tm_process = Tendermint::new(&tm_home)
             .stdout(stdout)
             .stderr(stderr)
-            .logs(self.logs)
+            .logs(self.logs || self.app_config.log.tendermint)
             .flags(self.flags)
             .proxy_app(format!("tcp://0.0.0.0:{}", abci_port).as_str());
 
@@ -248,15 +464,25 @@ impl<A: App> Node<A> {
 
         std::thread::spawn(move || {
             let app = InternalApp::<ABCIPlugin<A>>::new();
-            let store = MerkStore::new(self.merk_home.clone());
-            let res = ABCIStateMachine::new(
+            let mut store = match S::open(&self.merk_home) {
+                Ok(store) => store,
+                Err(e) => {
+                    *shutdown.write().unwrap() = Some(e);
+                    return;
+                }
+            };
+            store.configure(&self.app_config);
+            let mut state_machine = ABCIStateMachine::new(
                 app,
                 store,
                 self.skip_init_chain,
                 shutdown.clone(),
                 shutdown_notifier,
-            )
-            .listen(format!("127.0.0.1:{}", self.abci_port));
+            );
+            if let Some(height) = self.app_config.halt_height() {
+                state_machine = state_machine.stop_height(Some(height));
+            }
+            let res = state_machine.listen(format!("127.0.0.1:{}", self.abci_port));
             let mut shutdown = shutdown.write().unwrap();
 
             log::info!("[Temp] Response from upgrade: {:?}", res);
@@ -282,12 +508,12 @@ impl<A: App> Node<A> {
                     )
                     .unwrap();
 
-                    std::process::exit(138);
+                    std::process::exit(UPGRADE_EXIT_CODE);
                 }
                 Err(crate::Error::ABCI(msg)) if msg.starts_with("Reached stop height ") => {
                     *shutdown = Some(crate::Error::ABCI(msg));
 
-                    std::process::exit(138);
+                    std::process::exit(UPGRADE_EXIT_CODE);
                 }
                 Err(e) => {
                     *shutdown = Some(e);
@@ -315,74 +541,29 @@ impl<A: App> Node<A> {
         self
     }
 
-    // TODO: remove when we don't require compat migrations
-    pub fn migrate(self, version: Vec<u8>, compat_mode: bool, repair: bool) -> Self
-    where
-        ABCIPlugin<A>: Migrate,
-    {
-        let merk_store = crate::merk::MerkStore::new(&self.merk_home);
-        if let Some(store_ver) = merk_store.merk().get_aux(b"consensus_version").unwrap() {
-            if store_ver == version {
-                log::info!("Node has already migrated");
-                return self;
-            }
-        }
-
-        let genesis: serde_json::Value =
-            std::fs::read_to_string(self.tm_home.join("config/genesis.json"))
-                .unwrap()
-                .parse()
-                .unwrap();
-        let chain_id = genesis["chain_id"].as_str().unwrap();
-        Context::add(crate::plugins::ChainId(chain_id.to_string()));
-
-        log::info!("Migrating store data... (This might take a while)");
-        let store = Shared::new(merk_store);
-        let mut store = Store::new(BackingStore::Merk(store));
-        let bytes = store.get(&[]).unwrap().unwrap();
-
-        orga::set_compat_mode(compat_mode);
-        let mut app =
-            ABCIPlugin::<A>::migrate(store.clone(), store.clone(), &mut bytes.as_slice()).unwrap();
-        orga::set_compat_mode(false);
-
-        app.attach(store.clone()).unwrap();
-
-        let mut bytes = vec![];
-        app.flush(&mut bytes).unwrap();
-        store.put(vec![], bytes).unwrap();
-        store
-            .put(
-                crate::upgrade::VERSION_KEY.to_vec(),
-                [vec![version.len() as u8], version.clone()].concat(),
-            )
-            .unwrap();
-        if let BackingStore::Merk(merk_store) = store.into_backing_store().into_inner() {
-            let mut store = merk_store.into_inner();
-            store
-                .write(vec![(b"consensus_version".to_vec(), Some(version))])
-                .unwrap();
-
-            if repair {
-                store.into_merk().repair().unwrap();
-            }
-        } else {
-            unreachable!();
-        }
+    pub fn skip_init_chain(mut self) -> Self {
+        self.skip_init_chain = true;
 
         self
     }
 
-    pub fn skip_init_chain(mut self) -> Self {
-        self.skip_init_chain = true;
+    /// Sets the policy for creating and pruning state sync snapshots,
+    /// overriding the `snapshots` and `pruning` settings of the app config.
+    #[cfg(feature = "merk-full")]
+    #[must_use]
+    pub fn snapshot_policy(mut self, policy: SnapshotPolicy) -> Self {
+        self.app_config.set_snapshot_policy(policy);
 
         self
     }
 
-    pub fn init_from_store(self, source: impl AsRef<Path>, height: Option<u64>) -> Self {
-        MerkStore::init_from(source, &self.merk_home, height).unwrap();
+    /// Overrides settings of the app config with `key=value` strings, e.g.
+    /// passed as command-line flags. The overrides are not written to
+    /// `app.toml`.
+    pub fn app_config_overrides<T: AsRef<str>>(mut self, overrides: &[T]) -> Result<Self> {
+        self.app_config.apply_overrides(overrides)?;
 
-        self
+        Ok(self)
     }
 
     #[must_use]
@@ -430,8 +611,13 @@ impl<A: App> Node<A> {
 }
 
 impl<A: App> InternalApp<ABCIPlugin<A>> {
-    fn run<T, F: FnOnce(&mut ABCIPlugin<A>) -> T>(&self, store: WrappedMerk, op: F) -> Result<T> {
-        let mut store = Store::new(store.into());
+    fn run<S, T, F>(&self, store: WrappedStore<S>, op: F) -> Result<T>
+    where
+        S: ABCIStore + 'static,
+        F: FnOnce(&mut ABCIPlugin<A>) -> T,
+    {
+        let store: Box<dyn ReadWrite> = Box::new(store);
+        let mut store = Store::new(BackingStore::Other(Shared::new(store)));
         let state_bytes = match store.get(&[])? {
             Some(inner) => inner,
             None => {
@@ -455,8 +641,12 @@ impl<A: App> InternalApp<ABCIPlugin<A>> {
     }
 }
 
-impl<A: App> Application for InternalApp<ABCIPlugin<A>> {
-    fn init_chain(&self, store: WrappedMerk, req: RequestInitChain) -> Result<ResponseInitChain> {
+impl<A: App, S: QueryStore> Application<S> for InternalApp<ABCIPlugin<A>> {
+    fn init_chain(
+        &self,
+        store: WrappedStore<S>,
+        req: RequestInitChain,
+    ) -> Result<ResponseInitChain> {
         let mut updates = self.run(store, move |state| -> Result<_> {
             state.call(req.into())?;
             Ok(state
@@ -474,7 +664,7 @@ impl<A: App> Application for InternalApp<ABCIPlugin<A>> {
 
     fn begin_block(
         &self,
-        store: WrappedMerk,
+        store: WrappedStore<S>,
         req: RequestBeginBlock,
     ) -> Result<ResponseBeginBlock> {
         let (events, _logs) = self.run(store, move |state| -> Result<_> {
@@ -488,7 +678,7 @@ impl<A: App> Application for InternalApp<ABCIPlugin<A>> {
         Ok(ResponseBeginBlock { events })
     }
 
-    fn end_block(&self, store: WrappedMerk, req: RequestEndBlock) -> Result<ResponseEndBlock> {
+    fn end_block(&self, store: WrappedStore<S>, req: RequestEndBlock) -> Result<ResponseEndBlock> {
         let (mut updates, events, _logs) = self.run(store, move |state| -> Result<_> {
             state.call(req.into())?;
             Ok((
@@ -518,7 +708,11 @@ impl<A: App> Application for InternalApp<ABCIPlugin<A>> {
         Ok(res)
     }
 
-    fn deliver_tx(&self, store: WrappedMerk, req: RequestDeliverTx) -> Result<ResponseDeliverTx> {
+    fn deliver_tx(
+        &self,
+        store: WrappedStore<S>,
+        req: RequestDeliverTx,
+    ) -> Result<ResponseDeliverTx> {
         let run_res = self.run(store, move |state| -> Result<_> {
             let inner_call = Decode::decode(req.tx.to_vec().as_slice())?;
             let res = state.call(ABCICall::DeliverTx(inner_call));
@@ -556,7 +750,7 @@ impl<A: App> Application for InternalApp<ABCIPlugin<A>> {
         Ok(deliver_tx_res)
     }
 
-    fn check_tx(&self, store: WrappedMerk, req: RequestCheckTx) -> Result<ResponseCheckTx> {
+    fn check_tx(&self, store: WrappedStore<S>, req: RequestCheckTx) -> Result<ResponseCheckTx> {
         let run_res = self.run(store, move |state| -> Result<_> {
             let inner_call = Decode::decode(req.tx.to_vec().as_slice())?;
             let res = state.call(ABCICall::CheckTx(inner_call));
@@ -595,14 +789,69 @@ impl<A: App> Application for InternalApp<ABCIPlugin<A>> {
         Ok(check_tx_res)
     }
 
-    fn query(&self, merk_store: Shared<MerkStore>, req: RequestQuery) -> Result<ResponseQuery> {
-        let create_state = |store| {
-            let store = Store::new(store);
-            let state_bytes = store
-                .get(&[])?
-                .ok_or_else(|| crate::Error::Query("Store is empty".to_string()))?;
-            ABCIPlugin::<A>::load(store, &mut state_bytes.as_slice())
-        };
+    fn query(&self, store: Shared<S>, req: RequestQuery) -> Result<ResponseQuery> {
+        S::query_state::<A>(store, req)
+    }
+}
+
+/// Loads the app state from the root key of the given store.
+fn load_state<A: App>(store: BackingStore) -> Result<ABCIPlugin<A>> {
+    let store = Store::new(store);
+    let state_bytes = store
+        .get(&[])?
+        .ok_or_else(|| crate::Error::Query("Store is empty".to_string()))?;
+    ABCIPlugin::<A>::load(store, &mut state_bytes.as_slice())
+}
+
+/// Handles ABCI queries against the committed state in a backing store.
+///
+/// Stores without support for proofs can only serve queries with a path, which
+/// are handled by the app's [AbciQuery] implementation.
+pub trait QueryStore: ABCIStore + Sized + 'static {
+    fn query_state<A: App>(store: Shared<Self>, req: RequestQuery) -> Result<ResponseQuery>;
+}
+
+impl<S: ABCIStore + 'static> QueryStore for S {
+    default fn query_state<A: App>(
+        store: Shared<Self>,
+        req: RequestQuery,
+    ) -> Result<ResponseQuery> {
+        if req.path.is_empty() {
+            return Err(crate::Error::Query(
+                "Store does not support proof queries".to_string(),
+            ));
+        }
+
+        let height = store.borrow().height()?;
+        if req.height != 0 && req.height as u64 != height {
+            return Err(crate::Error::Query(format!(
+                "Cannot query for height {}",
+                req.height
+            )));
+        }
+
+        let store: Box<dyn ReadWrite> = Box::new(store);
+        let state = load_state::<A>(BackingStore::Other(Shared::new(store)))?;
+        let mut res = state.abci_query(&req)?;
+        res.height = height.try_into()?;
+
+        Ok(res)
+    }
+}
+
+#[cfg(feature = "merk-full")]
+impl QueryStore for MerkStore {
+    fn query_state<A: App>(merk_store: Shared<Self>, req: RequestQuery) -> Result<ResponseQuery> {
+        if req.path == "/snapshots" {
+            let store = merk_store.borrow();
+            let snapshots = store.snapshot_info()?;
+            return Ok(ResponseQuery {
+                code: 0,
+                height: store.height()?.try_into()?,
+                value: serde_json::to_vec(&snapshots)?.into(),
+                ..Default::default()
+            });
+        }
 
         let (height, snapshot) = {
             let merk_store_ref = merk_store.borrow();
@@ -619,9 +868,26 @@ impl<A: App> Application for InternalApp<ABCIPlugin<A>> {
 
         let mss = Shared::new(MemSnapshot::new(snapshot, merk_store));
 
+        if req.path == "/batch_proof" {
+            let query = BatchQuery::decode(&*req.data)?;
+            let proof_bytes = prove_batch(&*mss.borrow(), &query)?;
+            let root_hash = mss.borrow().use_snapshot(|ss| ss.root_hash());
+
+            let mut value = vec![];
+            value.extend(root_hash);
+            value.extend(proof_bytes);
+
+            return Ok(ResponseQuery {
+                code: 0,
+                height: height.try_into()?,
+                value: value.into(),
+                ..Default::default()
+            });
+        }
+
         if !req.path.is_empty() {
             let store = BackingStore::MemSnapshot(mss);
-            let state = create_state(store)?;
+            let state = load_state::<A>(store)?;
             let mut res = state.abci_query(&req)?;
             res.height = height.try_into().unwrap();
             drop(state);
@@ -631,7 +897,7 @@ impl<A: App> Application for InternalApp<ABCIPlugin<A>> {
 
         let query = Decode::decode(&*req.data)?;
         let store = BackingStore::ProofBuilderMemSnapshot(ProofBuilder::new(mss));
-        let state = create_state(store.clone())?;
+        let state = load_state::<A>(store.clone())?;
         state.query(query)?;
         drop(state);
 
@@ -676,10 +942,13 @@ impl<A: App> InternalApp<ABCIPlugin<A>> {
 mod tests {
     use crate::{
         abci::{BeginBlock, Node},
-        client::{wallet::Unsigned, AppClient},
         coins::Symbol,
         context::Context,
         plugins::{ChainId, ConvertSdkTx, DefaultPlugins, PaidCall},
+    };
+    #[cfg(feature = "merk-verify")]
+    use crate::{
+        client::{wallet::Unsigned, AppClient},
         tendermint::client::HttpClient,
     };
 
@@ -708,7 +977,20 @@ mod tests {
         }
     }
 
+    impl AbciQuery for App {
+        fn abci_query(&self, req: &RequestQuery) -> Result<ResponseQuery> {
+            use crate::encoding::Encode;
+
+            Ok(ResponseQuery {
+                value: self.count.encode()?.into(),
+                height: req.height,
+                ..Default::default()
+            })
+        }
+    }
+
     // TODO: dedupe w/ tendermint::client tests
+    #[cfg(feature = "merk-full")]
     pub async fn spawn_node() {
         pretty_env_logger::init();
 
@@ -748,6 +1030,7 @@ mod tests {
         }
     }
 
+    #[cfg(all(feature = "merk-verify", feature = "merk-full"))]
     #[ignore]
     #[tokio::test]
     #[serial_test::serial]
@@ -765,4 +1048,55 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    #[serial_test::serial]
+    fn state_machine_on_mem_store() -> Result<()> {
+        use tendermint_proto::v0_34::abci::request::Value as Req;
+        use tendermint_proto::v0_34::abci::response::Value as Res;
+        use tendermint_proto::v0_34::types::Header;
+
+        let app = InternalApp::<ABCIPlugin<App>>::new();
+        let mut state_machine = ABCIStateMachine::new(
+            app,
+            MemStore::new(),
+            false,
+            Default::default(),
+            Default::default(),
+        );
+        let mut run = |value| state_machine.run(Request { value: Some(value) });
+
+        run(Req::InitChain(Default::default()))?;
+        for height in 1..=2 {
+            let header = Header {
+                height,
+                ..Default::default()
+            };
+            run(Req::BeginBlock(RequestBeginBlock {
+                header: Some(header),
+                ..Default::default()
+            }))?;
+            run(Req::EndBlock(RequestEndBlock { height }))?;
+            run(Req::Commit(Default::default()))?;
+        }
+
+        let res = run(Req::Query(RequestQuery {
+            path: "count".to_string(),
+            ..Default::default()
+        }))?;
+        let Res::Query(res) = res else {
+            unreachable!();
+        };
+        assert_eq!(res.code, 0);
+        assert_eq!(res.height, 2);
+        assert_eq!(u32::decode(&*res.value)?, 2);
+
+        let res = run(Req::Query(Default::default()))?;
+        let Res::Query(res) = res else {
+            unreachable!();
+        };
+        assert_eq!(res.code, 1);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,395 @@
+use super::{AppConfig, UpgradeConfig, UPGRADE_EXIT_CODE};
+use crate::{Error, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
+use std::time::{Duration, Instant};
+
+/// Runs the node as a child process, switching to the binary for the network's
+/// version whenever the node exits because the network has upgraded.
+///
+/// Binaries are kept in the node home at `bin/<version>/<name>`, where the
+/// version is hex-encoded, and must match the SHA-256 hashes configured in the
+/// `upgrade` section of `app.toml` before they are run. The supervisor's own
+/// executable is used for its own version if no binary is installed for it.
+///
+/// If a binary exits with an error shortly after switching to it, the
+/// supervisor rolls back to the last binary which started successfully.
+pub struct Supervisor {
+    home: PathBuf,
+    version: Vec<u8>,
+    name: OsString,
+    args: Vec<OsString>,
+    config: UpgradeConfig,
+}
+
+impl Supervisor {
+    /// Creates a supervisor for the node in `home`, where `version` is the
+    /// network version the running binary supports.
+    pub fn new<P: AsRef<Path>>(home: P, version: &[u8]) -> Result<Self> {
+        let home = home.as_ref().to_path_buf();
+        let config_path = home.join("tendermint/config/app.toml");
+        let config = if config_path.exists() {
+            AppConfig::load(config_path)?.upgrade
+        } else {
+            Default::default()
+        };
+
+        let exe = std::env::current_exe()?;
+        let name = exe
+            .file_name()
+            .ok_or_else(|| Error::App("Could not resolve executable name".to_string()))?
+            .to_os_string();
+
+        Ok(Self {
+            home,
+            version: version.to_vec(),
+            name,
+            args: vec![],
+            config,
+        })
+    }
+
+    /// Sets the arguments the node binary is run with, e.g. the subcommand
+    /// which starts the node.
+    #[must_use]
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args = args
+            .into_iter()
+            .map(|a| a.as_ref().to_os_string())
+            .collect();
+
+        self
+    }
+
+    /// Sets the file name binaries are installed as, which defaults to the
+    /// name of the running executable.
+    #[must_use]
+    pub fn binary_name<S: AsRef<OsStr>>(mut self, name: S) -> Self {
+        self.name = name.as_ref().to_os_string();
+
+        self
+    }
+
+    #[must_use]
+    pub fn with_config(mut self, config: UpgradeConfig) -> Self {
+        self.config = config;
+
+        self
+    }
+
+    /// The path the binary for the given version is installed at.
+    pub fn bin_path(&self, version: &[u8]) -> PathBuf {
+        self.home
+            .join("bin")
+            .join(hex::encode(version))
+            .join(&self.name)
+    }
+
+    /// Verifies the binary at `source` against the configured hash for the
+    /// given version, then copies it into the node home.
+    pub fn install<P: AsRef<Path>>(&self, version: &[u8], source: P) -> Result<PathBuf> {
+        self.verify(version, source.as_ref())?;
+
+        let path = self.bin_path(version);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::copy(source, &path)?;
+        set_executable(&path)?;
+        log::info!(
+            "Installed binary for version {} at {}",
+            hex::encode(version),
+            path.display()
+        );
+
+        Ok(path)
+    }
+
+    /// Checks that the file at `path` matches the configured hash for the
+    /// given version.
+    pub fn verify(&self, version: &[u8], path: &Path) -> Result<()> {
+        let version = hex::encode(version);
+        let expected = self.config.binaries.get(&version).ok_or_else(|| {
+            Error::App(format!("No binary hash configured for version {}", version))
+        })?;
+
+        let mut file = std::fs::File::open(path)?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut file, &mut hasher)?;
+        let actual = hex::encode(hasher.finalize());
+
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(Error::App(format!(
+                "Binary for version {} has hash {}, expected {}",
+                version, actual, expected
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// The version the network is running, as written by the node when it
+    /// stops because of a version mismatch.
+    pub fn network_version(&self) -> Result<Option<Vec<u8>>> {
+        read_version(self.home.join("network_version"))
+    }
+
+    /// The version of the last binary which started successfully, or the
+    /// supervisor's own version if none has been run yet.
+    pub fn current_version(&self) -> Result<Vec<u8>> {
+        Ok(read_version(self.current_path())?.unwrap_or_else(|| self.version.clone()))
+    }
+
+    fn current_path(&self) -> PathBuf {
+        self.home.join("bin/current")
+    }
+
+    fn set_current_version(&self, version: &[u8]) -> Result<()> {
+        std::fs::create_dir_all(self.home.join("bin"))?;
+        std::fs::write(self.current_path(), format!("{}\n", hex::encode(version)))?;
+        Ok(())
+    }
+
+    /// Resolves and verifies the binary to run for the given version.
+    fn binary(&self, version: &[u8]) -> Result<PathBuf> {
+        let path = self.bin_path(version);
+        if path.exists() {
+            self.verify(version, &path)?;
+            return Ok(path);
+        }
+
+        if version == self.version.as_slice() {
+            return Ok(std::env::current_exe()?);
+        }
+
+        Err(Error::App(format!(
+            "No binary installed for version {} (expected at {})",
+            hex::encode(version),
+            path.display()
+        )))
+    }
+
+    /// Runs the node until it exits for a reason other than an upgrade.
+    ///
+    /// Returns successfully if the node exits successfully or stops at its
+    /// stop height, and returns an error if it fails or the network upgrades
+    /// to a version which could not be started.
+    pub fn run(&self) -> Result<()> {
+        let mut failed = HashSet::new();
+
+        loop {
+            let current = self.current_version()?;
+            let target = match self.network_version()? {
+                Some(version) if !failed.contains(&version) => version,
+                _ => current.clone(),
+            };
+            let switching = target != current;
+
+            let path = match self.binary(&target) {
+                Ok(path) => path,
+                Err(e) if switching => {
+                    log::error!("Cannot switch to version {}: {}", hex::encode(&target), e);
+                    failed.insert(target);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            log::info!(
+                "Starting node version {} ({})",
+                hex::encode(&target),
+                path.display()
+            );
+            let mut child = Command::new(&path).args(&self.args).spawn()?;
+
+            let status = match self.wait_for_start(&mut child)? {
+                Some(status) if switching && !status_is_upgrade(&status) => {
+                    log::error!(
+                        "Node version {} failed to start ({}), rolling back to version {}",
+                        hex::encode(&target),
+                        status,
+                        hex::encode(&current)
+                    );
+                    failed.insert(target);
+                    continue;
+                }
+                Some(status) => status,
+                None => {
+                    self.set_current_version(&target)?;
+                    child.wait()?
+                }
+            };
+
+            if !status_is_upgrade(&status) {
+                if status.success() {
+                    return Ok(());
+                }
+                return Err(Error::App(format!(
+                    "Node version {} exited with {}",
+                    hex::encode(&target),
+                    status
+                )));
+            }
+
+            self.set_current_version(&target)?;
+            match self.network_version()? {
+                Some(version) if version == target => {
+                    log::info!("Node reached stop height");
+                    return Ok(());
+                }
+                Some(version) if failed.contains(&version) => {
+                    return Err(Error::App(format!(
+                        "Network upgraded to version {}, which failed to start",
+                        hex::encode(version)
+                    )));
+                }
+                Some(version) => {
+                    log::info!("Network upgraded to version {}", hex::encode(version));
+                }
+                None => {
+                    log::info!("Node reached stop height");
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Waits for the start timeout to pass, returning the child's exit status
+    /// if it exits before then.
+    fn wait_for_start(&self, child: &mut Child) -> Result<Option<ExitStatus>> {
+        let timeout = Duration::from_secs(self.config.start_timeout_seconds);
+        let start = Instant::now();
+        loop {
+            if let Some(status) = child.try_wait()? {
+                return Ok(Some(status));
+            }
+            if start.elapsed() >= timeout {
+                return Ok(None);
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+fn status_is_upgrade(status: &ExitStatus) -> bool {
+    status.code() == Some(UPGRADE_EXIT_CODE)
+}
+
+fn read_version<P: AsRef<Path>>(path: P) -> Result<Option<Vec<u8>>> {
+    let path = path.as_ref();
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let version = std::fs::read_to_string(path)?;
+    let version = hex::decode(version.trim())
+        .map_err(|_| Error::App(format!("Invalid version in {}", path.display())))?;
+
+    Ok(Some(version))
+}
+
+#[cfg(unix)]
+fn set_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut perms = std::fs::metadata(path)?.permissions();
+    perms.set_mode(perms.mode() | 0o111);
+    std::fs::set_permissions(path, perms)?;
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    fn supervisor(
+        home: &TempDir,
+        start_timeout_seconds: u64,
+        scripts: &[(u8, &str)],
+    ) -> Supervisor {
+        let mut config = UpgradeConfig {
+            start_timeout_seconds,
+            ..Default::default()
+        };
+        let sources = home.path().join("src");
+        std::fs::create_dir_all(&sources).unwrap();
+        for (version, script) in scripts {
+            let script = format!("#!/bin/sh\n{}\n", script);
+            let hash = hex::encode(Sha256::digest(script.as_bytes()));
+            config.binaries.insert(hex::encode([*version]), hash);
+            std::fs::write(sources.join(hex::encode([*version])), script).unwrap();
+        }
+
+        let supervisor = Supervisor::new(home.path(), &[1])
+            .unwrap()
+            .binary_name("node")
+            .args([home.path()])
+            .with_config(config);
+        for (version, _) in scripts {
+            supervisor
+                .install(&[*version], sources.join(hex::encode([*version])))
+                .unwrap();
+        }
+
+        supervisor
+    }
+
+    #[test]
+    fn verify() {
+        let home = TempDir::new("orga-supervisor").unwrap();
+        let supervisor = supervisor(&home, 0, &[(1, "exit 0")]);
+
+        let path = supervisor.bin_path(&[1]);
+        assert!(supervisor.verify(&[1], &path).is_ok());
+        assert!(supervisor.verify(&[2], &path).is_err());
+
+        std::fs::write(&path, "#!/bin/sh\nexit 1\n").unwrap();
+        assert!(supervisor.verify(&[1], &path).is_err());
+        assert!(supervisor.run().is_err());
+    }
+
+    #[test]
+    fn upgrade() {
+        let home = TempDir::new("orga-supervisor").unwrap();
+        let supervisor = supervisor(
+            &home,
+            0,
+            &[
+                (1, "printf '02\\n' > \"$1/network_version\"; exit 138"),
+                (2, "sleep 1; exit 0"),
+            ],
+        );
+
+        supervisor.run().unwrap();
+        assert_eq!(supervisor.current_version().unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn rollback() {
+        let home = TempDir::new("orga-supervisor").unwrap();
+        let supervisor = supervisor(
+            &home,
+            30,
+            &[
+                (1, "printf '02\\n' > \"$1/network_version\"; exit 138"),
+                (2, "exit 1"),
+            ],
+        );
+
+        let err = supervisor.run().unwrap_err();
+        assert!(err.to_string().contains("failed to start"));
+        assert_eq!(supervisor.current_version().unwrap(), vec![1]);
+    }
+}
@@ -0,0 +1,615 @@
+//! A command-line interface generated from an app's `#[call]` and `#[query]`
+//! methods and its state descriptor.
+//!
+//! Each field of the app with methods becomes a subcommand, e.g. `mychain
+//! call staking delegate <validator> <amount>` or `mychain query staking
+//! delegations <address>`. Arguments are parsed with the type's `FromStr`
+//! implementation, or as JSON. Raw state can be browsed by path with `mychain
+//! state staking.validators`.
+
+use std::any::{Any, TypeId};
+use std::ffi::OsString;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+use clap::{value_parser, Arg, ArgMatches, Command};
+
+use crate::abci::App;
+use crate::call::{Call, PREFIX_OFFSET};
+use crate::client::wallet::{
+    remote::Endpoint, HdWallet, Keystore, RemoteWallet, SimpleWallet, Unsigned, Wallet,
+};
+use crate::client::{exec, AppClient};
+use crate::coins::{Address, Symbol};
+use crate::describe::{Children, Describe, Descriptor, KeyOp, MethodDescriptor};
+use crate::encoding::Decode;
+use crate::plugins::{ABCIPlugin, ConvertSdkTx, DefaultPlugins, PaidCall, SignerCall};
+use crate::query::Query;
+use crate::state::State;
+use crate::store::Read;
+use crate::tendermint::client::HttpClient;
+use crate::{Error, Result};
+
+const DEFAULT_NODE: &str = "http://localhost:26657";
+const DEFAULT_STATE_LIMIT: &str = "100";
+
+/// The environment variable read for the keystore password before prompting
+/// for it.
+pub const KEYSTORE_PASSWORD_VAR: &str = "ORGA_KEYSTORE_PASSWORD";
+
+/// A command-line client for an app of type `T`, using `S` as the symbol of
+/// the app's fee token.
+pub struct Cli<T: Call, S> {
+    name: String,
+    payer: fn(&T) -> T::Call,
+    _symbol: PhantomData<S>,
+}
+
+impl<T: Call + Describe + 'static, S> Cli<T, S> {
+    /// Creates a CLI for a binary named `name`, which pays for calls with the
+    /// call built by `payer`, e.g. `|app| build_call!(app.accounts.take_as_funding(MIN_FEE.into()))`.
+    pub fn new(name: &str, payer: fn(&T) -> T::Call) -> Self {
+        Self {
+            name: name.to_string(),
+            payer,
+            _symbol: PhantomData,
+        }
+    }
+
+    /// Builds the command, with `call` and `query` subcommands for each field
+    /// of the app with `#[call]` or `#[query]` methods.
+    pub fn command(&self) -> Command {
+        let desc = T::describe();
+
+        let mut command = Command::new(self.name.clone())
+            .subcommand_required(true)
+            .arg(
+                Arg::new("node")
+                    .long("node")
+                    .global(true)
+                    .default_value(DEFAULT_NODE)
+                    .help("RPC address of the node to connect to"),
+            )
+            .arg(
+                Arg::new("wallet")
+                    .long("wallet")
+                    .global(true)
+                    .value_parser(value_parser!(PathBuf))
+                    .help("Directory of the key to sign calls with [default: ~/.orga-wallet]"),
+            )
+            .arg(
+                Arg::new("keystore")
+                    .long("keystore")
+                    .global(true)
+                    .value_parser(value_parser!(PathBuf))
+                    .requires("account")
+                    .conflicts_with("wallet")
+                    .help("Keystore file holding the account to sign calls with"),
+            )
+            .arg(
+                Arg::new("account")
+                    .long("account")
+                    .global(true)
+                    .requires("keystore")
+                    .help("Name of the keystore account to sign calls with"),
+            )
+            .arg(
+                Arg::new("signer")
+                    .long("signer")
+                    .global(true)
+                    .conflicts_with_all(["wallet", "keystore"])
+                    .help("Remote signer to sign calls with, e.g. unix:/path/to/socket"),
+            )
+            .subcommand(
+                Command::new("state")
+                    .about("Lists the raw state entries under a path, e.g. accounts.balances")
+                    .arg(Arg::new("path").default_value(""))
+                    .arg(
+                        Arg::new("limit")
+                            .long("limit")
+                            .value_parser(value_parser!(usize))
+                            .default_value(DEFAULT_STATE_LIMIT)
+                            .help("Maximum number of entries to list"),
+                    ),
+            );
+
+        let calls = method_command(Command::new("call"), &desc, MethodKind::Call);
+        if let Some(calls) = calls {
+            command = command.subcommand(calls.about("Signs and broadcasts a call"));
+        }
+        let queries = method_command(Command::new("query"), &desc, MethodKind::Query);
+        if let Some(queries) = queries {
+            command = command.subcommand(queries.about("Runs a query against the app's state"));
+        }
+
+        command
+    }
+}
+
+impl<T, S> Cli<T, S>
+where
+    T: App
+        + Call
+        + State
+        + Query
+        + Default
+        + Describe
+        + ConvertSdkTx<Output = PaidCall<T::Call>>
+        + 'static,
+    S: Symbol,
+{
+    /// Runs the CLI with the process's arguments, exiting on invalid
+    /// arguments.
+    pub async fn run(&self) -> Result<()> {
+        self.run_from(std::env::args_os()).await
+    }
+
+    /// Runs the CLI with the given arguments, where the first argument is the
+    /// binary name. Exits the process on invalid arguments, or after printing
+    /// help.
+    pub async fn run_from<I, A>(&self, args: I) -> Result<()>
+    where
+        I: IntoIterator<Item = A>,
+        A: Into<OsString> + Clone,
+    {
+        let matches = self.command().get_matches_from(args);
+
+        let node = matches.get_one::<String>("node").unwrap();
+        let client = AppClient::<T, T, _, S, _>::new(HttpClient::new(node)?, Unsigned);
+
+        match matches.subcommand() {
+            Some(("state", matches)) => {
+                let path = matches.get_one::<String>("path").unwrap();
+                let limit = *matches.get_one::<usize>("limit").unwrap();
+                for (path, value) in self.state(&client, path, limit).await? {
+                    println!("{} = {}", path, value);
+                }
+            }
+            Some(("query", matches)) => {
+                let desc = T::describe();
+                let (fields, method, args) = resolve_method(&desc, matches, MethodKind::Query)?;
+                let args = method.parse_args(&args)?;
+                println!("{}", self.query(&client, &fields, method, &args).await?);
+            }
+            Some(("call", call_matches)) => {
+                let desc = T::describe();
+                let (fields, method, args) = resolve_method(&desc, call_matches, MethodKind::Call)?;
+                let call = call_bytes(&desc, &fields, method, &args)?;
+                let call = T::Call::decode(call.as_slice())
+                    .map_err(|_| Error::App(format!("{} cannot be called", fields.join("."))))?;
+
+                let client = client.with_wallet(wallet(&matches)?);
+                let res = client.call(self.payer, move |_| call).await?;
+                if res.height > 0 {
+                    println!("Transaction {} included at height {}", res.hash, res.height);
+                } else {
+                    println!("Transaction {} broadcast", res.hash);
+                }
+            }
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    /// Reads up to `limit` raw entries under the state path, returning each
+    /// entry's resolved key path and formatted value.
+    async fn state<Tr, W>(
+        &self,
+        client: &AppClient<T, T, Tr, S, W>,
+        path: &str,
+        limit: usize,
+    ) -> Result<Vec<(String, String)>>
+    where
+        Tr: exec::Transport<ABCIPlugin<DefaultPlugins<S, T>>>,
+        W: Wallet,
+    {
+        let root = ABCIPlugin::<DefaultPlugins<S, T>>::describe();
+        let (app_prefix, app_desc) = root
+            .find_type(TypeId::of::<T>())
+            .ok_or_else(|| Error::App("App not found in state descriptor".into()))?;
+        let (path_prefix, _) = app_desc.resolve_path(path)?;
+        let prefix = [app_prefix.as_slice(), path_prefix.as_slice()].concat();
+
+        let entries = client
+            .query_store(|store| {
+                let mut entries = vec![];
+                let mut next = store.get_next_inclusive(&prefix)?;
+                while let Some((key, value)) = next {
+                    if entries.len() >= limit || !key.starts_with(&prefix) {
+                        break;
+                    }
+                    next = store.get_next(&key)?;
+                    entries.push((key, value));
+                }
+                Ok(entries)
+            })
+            .await?;
+
+        Ok(entries
+            .into_iter()
+            .map(|(key, value)| {
+                let (path, desc) = app_desc.resolve_key(&key[app_prefix.len()..]);
+                let value = desc
+                    .and_then(|desc| desc.format_bytes(&mut value.as_slice()).ok())
+                    .unwrap_or_else(|| hex::encode(&value));
+                (path, value)
+            })
+            .collect())
+    }
+
+    async fn query<Tr, W>(
+        &self,
+        client: &AppClient<T, T, Tr, S, W>,
+        fields: &[&str],
+        method: &MethodDescriptor,
+        args: &[u8],
+    ) -> Result<String>
+    where
+        Tr: exec::Transport<ABCIPlugin<DefaultPlugins<S, T>>>,
+        W: Wallet,
+    {
+        let desc = T::describe();
+        client
+            .query(|app| run_query(&desc, &app, fields, method, args))
+            .await
+    }
+}
+
+#[derive(Clone, Copy)]
+enum MethodKind {
+    Call,
+    Query,
+}
+
+impl MethodKind {
+    fn methods(self, desc: &Descriptor) -> &[MethodDescriptor] {
+        match self {
+            MethodKind::Call => &desc.calls,
+            MethodKind::Query => &desc.queries,
+        }
+    }
+}
+
+/// Adds subcommands to `command` for the type's methods and for its fields
+/// which have methods, returning `None` if there are none.
+fn method_command(mut command: Command, desc: &Descriptor, kind: MethodKind) -> Option<Command> {
+    let methods = kind.methods(desc);
+    let mut empty = methods.is_empty();
+
+    for method in methods {
+        let mut method_command = Command::new(method.name.clone());
+        for (i, arg) in method.args.iter().enumerate() {
+            method_command = method_command.arg(
+                Arg::new(format!("arg{}", i))
+                    .value_name(arg.name.clone())
+                    .help(arg.type_name.clone())
+                    .required(true),
+            );
+        }
+        command = command.subcommand(method_command);
+    }
+
+    if let Children::Named(children) = desc.children() {
+        for child in children {
+            // methods take precedence over fields with the same name
+            if methods.iter().any(|method| method.name == child.name) {
+                continue;
+            }
+            if let Some(child_command) =
+                method_command(Command::new(child.name.clone()), &child.desc, kind)
+            {
+                command = command.subcommand(child_command);
+                empty = false;
+            }
+        }
+    }
+
+    (!empty).then(|| command.subcommand_required(true))
+}
+
+/// Follows the matched subcommands to a method, returning the path of fields
+/// to it, the method, and its unparsed arguments.
+fn resolve_method<'a>(
+    desc: &'a Descriptor,
+    matches: &'a ArgMatches,
+    kind: MethodKind,
+) -> Result<(Vec<&'a str>, &'a MethodDescriptor, Vec<String>)> {
+    let mut fields = vec![];
+    let mut desc = desc;
+    let mut matches = matches;
+
+    loop {
+        let (name, sub_matches) = matches
+            .subcommand()
+            .ok_or_else(|| Error::App("Expected a method".into()))?;
+
+        if let Some(method) = kind.methods(desc).iter().find(|m| m.name == name) {
+            let args = (0..method.args.len())
+                .map(|i| {
+                    sub_matches
+                        .get_one::<String>(&format!("arg{}", i))
+                        .cloned()
+                        .unwrap_or_default()
+                })
+                .collect();
+            return Ok((fields, method, args));
+        }
+
+        let child = desc
+            .named_child(name)
+            .ok_or_else(|| Error::App(format!("No field named {}", name)))?;
+        fields.push(child.name.as_str());
+        desc = &child.desc;
+        matches = sub_matches;
+    }
+}
+
+/// Encodes a call to the method on the field at `fields`, where each field
+/// call is prefixed by the field's store key, and the method call by its
+/// index.
+fn call_bytes(
+    desc: &Descriptor,
+    fields: &[&str],
+    method: &MethodDescriptor,
+    args: &[String],
+) -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    let mut desc = desc;
+
+    for field in fields {
+        let child = desc
+            .named_child(field)
+            .ok_or_else(|| Error::App(format!("No field named {}", field)))?;
+        match child.store_key {
+            KeyOp::Append(ref prefix) => bytes.extend_from_slice(prefix),
+            KeyOp::Absolute(_) => {
+                return Err(Error::App(format!("{} cannot be called", field)));
+            }
+        }
+        desc = &child.desc;
+    }
+
+    let index = desc
+        .calls
+        .iter()
+        .position(|call| call.name == method.name)
+        .ok_or_else(|| Error::App(format!("No call method named {}", method.name)))?;
+    bytes.push(index as u8 + PREFIX_OFFSET);
+    bytes.extend(method.parse_args(args)?);
+
+    Ok(bytes)
+}
+
+/// Runs the query method on the field of `app` at `fields`.
+fn run_query(
+    desc: &Descriptor,
+    app: &dyn Any,
+    fields: &[&str],
+    method: &MethodDescriptor,
+    args: &[u8],
+) -> Result<String> {
+    let mut desc = desc;
+    let mut value = app;
+
+    for field in fields {
+        let child = desc
+            .named_child(field)
+            .ok_or_else(|| Error::App(format!("No field named {}", field)))?;
+        value = child
+            .access(value)
+            .ok_or_else(|| Error::App(format!("{} cannot be queried", field)))?;
+        desc = &child.desc;
+    }
+
+    method.run_query(value, args)
+}
+
+/// Builds the wallet selected by the command-line options.
+fn wallet(matches: &ArgMatches) -> Result<CliWallet> {
+    if let Some(endpoint) = matches.get_one::<String>("signer") {
+        let endpoint: Endpoint = endpoint.parse()?;
+        return Ok(CliWallet::Remote(RemoteWallet::new(endpoint)));
+    }
+
+    if let Some(path) = matches.get_one::<PathBuf>("keystore") {
+        let account = matches.get_one::<String>("account").unwrap();
+        let password = match std::env::var(KEYSTORE_PASSWORD_VAR) {
+            Ok(password) => password,
+            Err(_) => rpassword::prompt_password(format!("Password for account {}: ", account))?,
+        };
+        let wallet = Keystore::open(path)?.unlock(account, &password)?;
+        return Ok(CliWallet::Hd(wallet));
+    }
+
+    let path = match matches.get_one::<PathBuf>("wallet") {
+        Some(path) => path.clone(),
+        None => home::home_dir()
+            .ok_or_else(|| Error::App("No home directory set".into()))?
+            .join(".orga-wallet"),
+    };
+    Ok(CliWallet::Simple(SimpleWallet::open(path)?))
+}
+
+/// The wallet selected on the command line.
+#[derive(Clone)]
+enum CliWallet {
+    Simple(SimpleWallet),
+    Hd(HdWallet),
+    Remote(RemoteWallet),
+}
+
+impl Wallet for CliWallet {
+    fn sign(&self, call_bytes: &[u8]) -> Result<SignerCall> {
+        match self {
+            CliWallet::Simple(wallet) => wallet.sign(call_bytes),
+            CliWallet::Hd(wallet) => wallet.sign(call_bytes),
+            CliWallet::Remote(wallet) => wallet.sign(call_bytes),
+        }
+    }
+
+    fn sign_with_description(&self, call_bytes: &[u8], description: &str) -> Result<SignerCall> {
+        match self {
+            CliWallet::Simple(wallet) => wallet.sign_with_description(call_bytes, description),
+            CliWallet::Hd(wallet) => wallet.sign_with_description(call_bytes, description),
+            CliWallet::Remote(wallet) => wallet.sign_with_description(call_bytes, description),
+        }
+    }
+
+    fn address(&self) -> Result<Option<Address>> {
+        match self {
+            CliWallet::Simple(wallet) => Wallet::address(wallet),
+            CliWallet::Hd(wallet) => Wallet::address(wallet),
+            CliWallet::Remote(wallet) => wallet.address(),
+        }
+    }
+
+    fn nonce_hint(&self) -> Result<Option<u64>> {
+        match self {
+            CliWallet::Simple(wallet) => wallet.nonce_hint(),
+            CliWallet::Hd(wallet) => wallet.nonce_hint(),
+            CliWallet::Remote(wallet) => wallet.nonce_hint(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::call::build_call;
+    use crate::collections::Map;
+    use crate::encoding::Encode;
+    use crate::orga;
+
+    #[orga]
+    pub struct Counter {
+        pub count: u64,
+        pub by_key: Map<u32, u64>,
+    }
+
+    #[orga]
+    impl Counter {
+        #[call]
+        pub fn increment(&mut self, n: u64) -> Result<()> {
+            self.count += n;
+            Ok(())
+        }
+
+        #[call]
+        pub fn set(&mut self, key: u32, value: u64) -> Result<()> {
+            self.by_key.insert(key, value)
+        }
+
+        #[query]
+        pub fn get(&self, key: u32) -> Result<Option<u64>> {
+            Ok(self.by_key.get(key)?.map(|v| *v))
+        }
+    }
+
+    #[orga]
+    pub struct Counters {
+        pub total: u64,
+        #[call]
+        pub a: Counter,
+        #[call]
+        #[state(prefix(7))]
+        pub b: Counter,
+    }
+
+    #[orga]
+    impl Counters {
+        #[call]
+        pub fn reset(&mut self) -> Result<()> {
+            self.total = 0;
+            Ok(())
+        }
+    }
+
+    fn cli() -> Cli<Counters, ()> {
+        Cli::new("counters", |app| build_call!(app.reset()))
+    }
+
+    fn resolve<'a>(
+        desc: &'a Descriptor,
+        matches: &'a ArgMatches,
+    ) -> (Vec<&'a str>, &'a MethodDescriptor, Vec<String>) {
+        let (kind, matches) = match matches.subcommand().unwrap() {
+            ("call", matches) => (MethodKind::Call, matches),
+            ("query", matches) => (MethodKind::Query, matches),
+            _ => unreachable!(),
+        };
+        resolve_method(desc, matches, kind).unwrap()
+    }
+
+    #[test]
+    fn command() {
+        cli().command().debug_assert();
+
+        let command = cli().command();
+        let call = command.find_subcommand("call").unwrap();
+        let names: Vec<_> = call.get_subcommands().map(|c| c.get_name()).collect();
+        assert_eq!(names, vec!["reset", "a", "b"]);
+
+        let query = command.find_subcommand("query").unwrap();
+        let names: Vec<_> = query.get_subcommands().map(|c| c.get_name()).collect();
+        assert_eq!(names, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn call() -> Result<()> {
+        let desc = Counters::describe();
+        let app = Counters::default();
+
+        let matches = cli()
+            .command()
+            .try_get_matches_from(["counters", "call", "b", "set", "3", "12"])
+            .unwrap();
+        let (fields, method, args) = resolve(&desc, &matches);
+        assert_eq!(fields, vec!["b"]);
+        assert_eq!(args, vec!["3", "12"]);
+
+        let bytes = call_bytes(&desc, &fields, method, &args)?;
+        assert_eq!(bytes, build_call!(app.b.set(3, 12)).encode()?);
+        assert!(<Counters as Call>::Call::decode(bytes.as_slice()).is_ok());
+
+        let matches = cli()
+            .command()
+            .try_get_matches_from(["counters", "call", "reset"])
+            .unwrap();
+        let (fields, method, args) = resolve(&desc, &matches);
+        let bytes = call_bytes(&desc, &fields, method, &args)?;
+        assert_eq!(bytes, build_call!(app.reset()).encode()?);
+
+        assert!(cli()
+            .command()
+            .try_get_matches_from(["counters", "call", "a", "increment"])
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn query() -> Result<()> {
+        let desc = Counters::describe();
+        let mut app = Counters::default();
+        app.b.by_key.insert(3, 12)?;
+
+        let matches = cli()
+            .command()
+            .try_get_matches_from(["counters", "query", "b", "get", "3"])
+            .unwrap();
+        let (fields, method, args) = resolve(&desc, &matches);
+        let args = method.parse_args(&args)?;
+        assert_eq!(
+            run_query(&desc, &app, &fields, method, &args)?,
+            "Some(\n    12,\n)"
+        );
+
+        let args = method.parse_args(&["4"])?;
+        assert_eq!(run_query(&desc, &app, &fields, method, &args)?, "None");
+
+        assert!(method.parse_args(&["foo"]).is_err());
+
+        Ok(())
+    }
+}
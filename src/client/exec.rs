@@ -1,6 +1,7 @@
 use std::{any::TypeId, collections::HashSet};
 
 use super::trace::{take_trace, tracing_guard};
+use super::TxResponse;
 use crate::{
     abci::App,
     call::Call,
@@ -30,7 +31,7 @@ pub enum StepResult<T: Query, U> {
 pub trait Transport<T: Query + Call>: Send + Sync {
     async fn query(&self, query: T::Query) -> Result<Store>;
 
-    async fn call(&self, call: T::Call) -> Result<()>;
+    async fn call(&self, call: T::Call) -> Result<TxResponse>;
 }
 
 impl<T: Transport<U>, U: Query + Call> Transport<U> for &mut T {
@@ -38,7 +39,7 @@ impl<T: Transport<U>, U: Query + Call> Transport<U> for &mut T {
         (**self).query(query).await
     }
 
-    async fn call(&self, call: <U as Call>::Call) -> Result<()> {
+    async fn call(&self, call: <U as Call>::Call) -> Result<TxResponse> {
         (**self).call(call).await
     }
 }
@@ -79,6 +80,43 @@ where
     }
 }
 
+/// Like [execute], but runs `query_fn` against the raw store rather than the
+/// loaded app, fetching keys from the node as they are read.
+pub async fn execute_raw<T, U>(
+    store: Store,
+    client: &impl Transport<ABCIPlugin<QueryPlugin<T>>>,
+    mut query_fn: impl FnMut(Store) -> Result<U>,
+) -> Result<(U, Store)>
+where
+    T: App + State + Query + Call + Describe,
+    T::Query: Send + Sync,
+    T::Call: Send + Sync,
+{
+    let mut store = store;
+
+    let mut queries = HashSet::new();
+
+    loop {
+        let query = match raw_step::<T, U>(store.clone(), &mut query_fn)? {
+            StepResult::Done(value) => return Ok((value, store)),
+            StepResult::FetchKey(key) => QueryPluginQuery::RawKey(key),
+            StepResult::FetchNext(key) => QueryPluginQuery::RawNext(key),
+            StepResult::FetchPrev(key) => QueryPluginQuery::RawPrev(key),
+            StepResult::FetchQuery(query) => QueryPluginQuery::Query(query),
+        };
+
+        let query_bytes = query.encode()?;
+        if queries.contains(&query_bytes) {
+            return Err(Error::Client("Execution did not advance".into()));
+        }
+        queries.insert(query_bytes);
+
+        let res = client.query(query).await?;
+
+        store = join_store(store, res)?;
+    }
+}
+
 pub mod sync {
     use std::collections::HashSet;
 
@@ -87,7 +125,7 @@ pub mod sync {
     pub trait Transport<T: Query + Call>: Send + Sync {
         fn query_sync(&self, query: T::Query) -> Result<Store>;
 
-        fn call_sync(&self, call: T::Call) -> Result<()>;
+        fn call_sync(&self, call: T::Call) -> Result<TxResponse>;
     }
 
     impl<T: Transport<U>, U: Query + Call> Transport<U> for &mut T {
@@ -95,7 +133,7 @@ pub mod sync {
             (**self).query_sync(query)
         }
 
-        fn call_sync(&self, call: <U as Call>::Call) -> Result<()> {
+        fn call_sync(&self, call: <U as Call>::Call) -> Result<TxResponse> {
             (**self).call_sync(call)
         }
     }
@@ -200,6 +238,28 @@ where
     Ok(fallback_res)
 }
 
+fn raw_step<T: Query, U>(
+    store: Store,
+    mut query_fn: impl FnMut(Store) -> Result<U>,
+) -> Result<StepResult<T, U>> {
+    // an empty store has no proof yet, so start by fetching the root key
+    match store.get(&[]) {
+        Err(Error::StoreErr(store::Error::GetUnknown(_))) | Ok(None) => {
+            return Ok(StepResult::FetchKey(vec![]))
+        }
+        Err(err) => return Err(err),
+        Ok(Some(_)) => {}
+    }
+
+    match query_fn(store) {
+        Err(Error::StoreErr(store::Error::GetUnknown(key))) => Ok(StepResult::FetchKey(key)),
+        Err(Error::StoreErr(store::Error::GetNextUnknown(key))) => Ok(StepResult::FetchNext(key)),
+        Err(Error::StoreErr(store::Error::GetPrevUnknown(key))) => Ok(StepResult::FetchPrev(key)),
+        Err(other_err) => Err(other_err),
+        Ok(value) => Ok(StepResult::Done(value)),
+    }
+}
+
 pub fn join_store(dst: Store, src: Store) -> Result<Store> {
     let dst = dst.into_backing_store().into_inner();
     let src = src.into_backing_store().into_inner();
@@ -263,9 +323,13 @@ impl Descriptor {
                 Err(Error::Client("No matching child".to_string()))
             }
             Children::Dynamic(child) => {
+                let child_key = child_key
+                    .strip_prefix(child.store_prefix())
+                    .ok_or_else(|| Error::Client("No matching child".to_string()))?;
                 let consumed = child.key_desc().encoding_bytes_subslice(child_key)?;
                 out_bytes = child.apply_query_bytes(out_bytes);
                 out_bytes.extend_from_slice(consumed);
+                self_store_key.extend_from_slice(child.store_prefix());
                 self_store_key.extend_from_slice(consumed);
                 child.value_desc().resolve_by_type_id(
                     target_type_id,
@@ -12,6 +12,7 @@ use crate::{
 };
 
 use super::exec::{sync::Transport as SyncTransport, Transport};
+use super::TxResponse;
 
 #[derive(Default)]
 pub struct MockClient<T> {
@@ -97,8 +98,10 @@ impl<T: App + State + Query + Call> SyncTransport<ABCIPlugin<QueryPlugin<T>>>
         ))))
     }
 
-    fn call_sync(&self, call: <ABCIPlugin<QueryPlugin<T>> as Call>::Call) -> Result<()> {
-        self.calls.lock().unwrap().push(call.encode()?);
+    fn call_sync(&self, call: <ABCIPlugin<QueryPlugin<T>> as Call>::Call) -> Result<TxResponse> {
+        let call_bytes = call.encode()?;
+        let res = TxResponse::local(&call_bytes);
+        self.calls.lock().unwrap().push(call_bytes);
 
         let root_bytes = self.store.get(&[])?.unwrap_or_default();
         let mut app =
@@ -110,7 +113,7 @@ impl<T: App + State + Query + Call> SyncTransport<ABCIPlugin<QueryPlugin<T>>>
         app.flush(&mut out)?;
         self.store.clone().put(vec![], out)?;
 
-        Ok(())
+        Ok(res)
     }
 }
 
@@ -121,7 +124,7 @@ impl<T: App + State + Query + Call> Transport<ABCIPlugin<QueryPlugin<T>>>
         self.query_sync(query)
     }
 
-    async fn call(&self, call: <ABCIPlugin<QueryPlugin<T>> as Call>::Call) -> Result<()> {
+    async fn call(&self, call: <ABCIPlugin<QueryPlugin<T>> as Call>::Call) -> Result<TxResponse> {
         self.call_sync(call)
     }
 }
@@ -1,24 +1,28 @@
-use crate::call::Call;
+use crate::call::{Call, CallBuilder};
 use crate::describe::Describe;
 use crate::encoding::{Decode, Encode};
 
 use crate::abci::App;
+use crate::coins::Address;
 use crate::plugins::{sdk_compat, ABCICall, ABCIPlugin, ConvertSdkTx};
 use crate::plugins::{PaidCall, PayableCall};
 use crate::query::Query;
 use crate::state::State;
 use crate::store::Store;
 
-use crate::Result;
+use crate::{Error, Result};
 
 use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
 
 pub mod exec;
 pub mod mock;
 pub mod trace;
+pub mod tx;
 pub mod wallet;
 
 pub use exec::Transport;
+pub use tx::{FromEvent, TxEvent, TxResponse};
 pub use wallet::Wallet;
 
 pub trait Client<T: Query + Call>: Send + Sync {
@@ -28,14 +32,55 @@ pub trait Client<T: Query + Call>: Send + Sync {
         &self,
         payer: impl FnOnce(&T) -> T::Call,
         payee: impl FnOnce(&T) -> T::Call,
-    ) -> Result<()>;
+    ) -> Result<TxResponse>;
 }
 
+/// The number of times [AppClient] re-syncs its nonce and retries a call
+/// which was rejected for having an invalid nonce.
+pub const DEFAULT_NONCE_RETRIES: usize = 2;
+
+/// Converts the encoding of a sub-client's call into the encoding of the
+/// equivalent call on the root app.
+type WrapCall = Arc<dyn Fn(&[u8]) -> Result<Vec<u8>> + Send + Sync>;
+
 pub struct AppClient<T, U, Transport, Symbol, Wallet> {
     _pd: PhantomData<Symbol>,
     transport: Transport,
     wallet: Wallet,
     sub: fn(T) -> U,
+    wrap_call: WrapCall,
+    last_nonce: Mutex<Option<u64>>,
+    nonce_retries: usize,
+}
+
+/// Creates a sub-client for a field of the app, e.g.
+/// `sub_client!(client, app.staking)`, which can both query and call the
+/// field. Calls built on the sub-client are wrapped into calls on the root app.
+#[macro_export]
+macro_rules! sub_client {
+    ($client:expr, $app:ident $(. $field:ident)+) => {
+        $client.sub_with_call(
+            |$app| $app $(.$field)+,
+            |builder, call| {
+                let call = ::std::cell::Cell::new(Some(call));
+                $crate::__wrap_call!(builder, call, $($field).+)
+            },
+        )
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __wrap_call {
+    ($builder:expr, $call:ident, $field:ident) => {
+        $builder.build_call::<{ stringify!($field) }, _>(|_| $call.take().unwrap(), ())
+    };
+    ($builder:expr, $call:ident, $field:ident $(. $rest:ident)+) => {
+        $builder.build_call::<{ stringify!($field) }, _>(
+            |builder| $crate::__wrap_call!(builder, $call, $($rest).+),
+            (),
+        )
+    };
 }
 
 pub mod sync {
@@ -63,11 +108,10 @@ pub mod sync {
 
         fn call_sync(
             &self,
-            _payer: impl FnOnce(&U) -> U::Call,
-            _payee: impl FnOnce(&U) -> U::Call,
-        ) -> Result<()> {
-            todo!()
-            // self.call(payer, payee)
+            payer: impl FnOnce(&U) -> U::Call,
+            payee: impl FnOnce(&U) -> U::Call,
+        ) -> Result<TxResponse> {
+            AppClient::call_sync(self, payer, payee)
         }
     }
 }
@@ -84,6 +128,36 @@ impl<T, U, Transport, Symbol, Wallet> AppClient<T, U, Transport, Symbol, Wallet>
             transport: client,
             wallet,
             sub: Into::into,
+            // the root client's calls are already root calls
+            wrap_call: Arc::new(|bytes: &[u8]| Ok(bytes.to_vec())),
+            last_nonce: Mutex::new(None),
+            nonce_retries: DEFAULT_NONCE_RETRIES,
+        }
+    }
+
+    /// Sets how many times a call rejected for having an invalid nonce is
+    /// retried with a nonce re-synced from the chain.
+    #[must_use]
+    pub fn with_nonce_retries(mut self, retries: usize) -> Self {
+        self.nonce_retries = retries;
+        self
+    }
+
+    /// Returns the nonce to sign the next call with, given the signer's nonce
+    /// in the queried state. Nonces of calls sent by this client which have
+    /// not been committed yet are accounted for, so calls can be sent back to
+    /// back.
+    fn next_nonce(&self, state_nonce: u64) -> u64 {
+        let last_nonce = self.last_nonce.lock().unwrap();
+        state_nonce.max(last_nonce.unwrap_or_default()) + 1
+    }
+
+    fn record_nonce(&self, nonce: Option<u64>, res: &Result<TxResponse>) {
+        let mut last_nonce = self.last_nonce.lock().unwrap();
+        match res {
+            Ok(_) => *last_nonce = nonce.max(*last_nonce),
+            Err(err) if err.is_retryable() => *last_nonce = None,
+            Err(_) => {}
         }
     }
 
@@ -93,9 +167,15 @@ impl<T, U, Transport, Symbol, Wallet> AppClient<T, U, Transport, Symbol, Wallet>
             transport: self.transport,
             wallet,
             sub: self.sub,
+            wrap_call: self.wrap_call,
+            last_nonce: Mutex::new(None),
+            nonce_retries: self.nonce_retries,
         }
     }
 
+    /// Creates a client for part of the app which can only be queried. Use
+    /// [sub_client](crate::sub_client) to create a sub-client which can also
+    /// be called.
     #[allow(clippy::should_implement_trait)]
     pub fn sub<U2>(self, sub: fn(T) -> U2) -> AppClient<T, U2, Transport, Symbol, Wallet> {
         AppClient {
@@ -103,8 +183,51 @@ impl<T, U, Transport, Symbol, Wallet> AppClient<T, U, Transport, Symbol, Wallet>
             transport: self.transport,
             wallet: self.wallet,
             sub,
+            wrap_call: Arc::new(|_: &[u8]| {
+                Err(Error::Client(
+                    "Sub-client cannot be called, create it with sub_client!".into(),
+                ))
+            }),
+            last_nonce: self.last_nonce,
+            nonce_retries: self.nonce_retries,
+        }
+    }
+
+    /// Creates a client for part of the app, where `wrap` converts calls on
+    /// the part of the app into calls on the root app. Usually created with
+    /// the [sub_client](crate::sub_client) macro.
+    pub fn sub_with_call<U2>(
+        self,
+        sub: fn(T) -> U2,
+        wrap: fn(CallBuilder<T>, U2::Call) -> T::Call,
+    ) -> AppClient<T, U2, Transport, Symbol, Wallet>
+    where
+        T: Call + 'static,
+        U2: Call + 'static,
+    {
+        AppClient {
+            _pd: PhantomData,
+            transport: self.transport,
+            wallet: self.wallet,
+            sub,
+            wrap_call: Arc::new(move |bytes: &[u8]| {
+                let call = U2::Call::decode(bytes)?;
+                Ok(wrap(CallBuilder::new(), call).encode()?)
+            }),
+            last_nonce: self.last_nonce,
+            nonce_retries: self.nonce_retries,
         }
     }
+
+    /// Converts a call built on this client into a call on the root app.
+    fn wrap_call(&self, call: U::Call) -> Result<T::Call>
+    where
+        T: Call,
+        U: Call,
+    {
+        let bytes = (self.wrap_call)(call.encode()?.as_slice())?;
+        Ok(T::Call::decode(bytes.as_slice())?)
+    }
 }
 
 impl<T, U, Transport, Symbol, Wallet> AppClient<T, U, Transport, Symbol, Wallet>
@@ -114,47 +237,94 @@ where
     Wallet: wallet::Wallet + Clone,
     Symbol: crate::coins::Symbol,
 {
-    // TODO: support subclients
-    // TODO: return object with result data (e.g. txid)
+    /// Signs and broadcasts a call, returning the result of the transaction
+    /// once it has been processed. Calls built on a sub-client are wrapped
+    /// into calls on the root app.
+    ///
+    /// If the call is rejected for having an invalid nonce, e.g. because
+    /// another client signed with the same key, the nonce is re-synced from
+    /// the chain and the call is retried.
     pub async fn call(
         &self,
-        payer: impl FnOnce(&U) -> T::Call,
-        payee: impl FnOnce(&U) -> T::Call,
-    ) -> Result<()> {
+        payer: impl FnOnce(&U) -> U::Call,
+        payee: impl FnOnce(&U) -> U::Call,
+    ) -> Result<TxResponse>
+    where
+        U: Call,
+    {
         let (chain_id, store) = exec::execute(Store::default(), &self.transport, |app| {
             Ok(app.inner.inner.borrow().inner.inner.chain_id.to_vec())
         })
         .await?;
-        let (nonce, store) = match self.wallet.address()? {
-            None => (None, store),
-            Some(addr) => {
-                exec::execute(store, &self.transport, |app| {
-                    Ok(Some(
-                        app.inner.inner.borrow_mut().inner.inner.inner.nonce(addr)? + 1,
-                    ))
-                })
-                .await?
-            }
-        };
+        let (mut nonce, store) = self.fetch_nonce(store).await?;
 
         let app = self.query_with_store(store, Ok).await?;
 
-        let payer_call = payer(&app);
-        let payer_call_bytes = payer_call.encode()?;
-        let payer = <T as Call>::Call::decode(payer_call_bytes.as_slice())?;
+        let payer = self.wrap_call(payer(&app))?;
+        let paid = self.wrap_call(payee(&app))?;
+        let call_bytes = PayableCall::Paid(PaidCall { payer, paid }).encode()?;
+
+        let mut retries = 0;
+        loop {
+            let call = crate::plugins::NonceCall {
+                nonce,
+                inner_call: PayableCall::decode(call_bytes.as_slice())?,
+            };
+            let description = format!("{:?}", call);
+            let call = [chain_id.as_slice(), call.encode()?.as_slice()].concat();
+            let call = self.wallet.sign_with_description(&call, &description)?;
+            let call = ABCICall::DeliverTx(sdk_compat::Call::Native(call));
+
+            let res = self.transport.call(call).await;
+            self.record_nonce(nonce, &res);
+            match res {
+                Err(err) if err.is_retryable() && retries < self.nonce_retries => {
+                    retries += 1;
+                    nonce = self.fetch_nonce(Store::default()).await?.0;
+                }
+                res => return res,
+            }
+        }
+    }
 
-        let paid = payee(&app);
-        let call = PayableCall::Paid(PaidCall { payer, paid });
-        let call = crate::plugins::NonceCall {
-            nonce,
-            inner_call: call,
-        };
-        let call = [chain_id, call.encode()?].concat();
-        let call = self.wallet.sign(&call)?;
-        let call = ABCICall::DeliverTx(sdk_compat::Call::Native(call));
-        self.transport.call(call).await?;
+    /// Returns the last nonce the account used on chain, or 0 if it has not
+    /// signed any calls.
+    pub async fn nonce(&self, address: Address) -> Result<u64> {
+        let (nonce, _) = exec::execute(Store::default(), &self.transport, |app| {
+            app.inner
+                .inner
+                .borrow_mut()
+                .inner
+                .inner
+                .inner
+                .nonce(address)
+        })
+        .await?;
+        Ok(nonce)
+    }
 
-        Ok(())
+    /// Runs a query encoded by the generated TypeScript bindings (see
+    /// [typescript](crate::describe::typescript)), returning its result
+    /// formatted for display.
+    pub async fn query_bytes(&self, query: &[u8]) -> Result<String>
+    where
+        U: Describe + 'static,
+    {
+        let desc = U::describe();
+        self.query(|app| desc.run_query_bytes(&app, query)).await
+    }
+
+    async fn fetch_nonce(&self, store: Store) -> Result<(Option<u64>, Store)> {
+        match self.wallet.address()? {
+            None => Ok((None, store)),
+            Some(addr) => {
+                let (state_nonce, store) = exec::execute(store, &self.transport, |app| {
+                    app.inner.inner.borrow_mut().inner.inner.inner.nonce(addr)
+                })
+                .await?;
+                Ok((Some(self.next_nonce(state_nonce)), store))
+            }
+        }
     }
 
     pub async fn query_root<U2, F2: FnMut(ABCIPlugin<DefaultPlugins<Symbol, T>>) -> Result<U2>>(
@@ -165,6 +335,13 @@ where
         Ok(res)
     }
 
+    /// Runs `op` against the app's raw key/value store, e.g. to iterate over
+    /// entries without loading typed state.
+    pub async fn query_store<U2, F2: FnMut(Store) -> Result<U2>>(&self, op: F2) -> Result<U2> {
+        let (res, _) = exec::execute_raw(Store::default(), &self.transport, op).await?;
+        Ok(res)
+    }
+
     pub async fn query<U2, F2: FnMut(U) -> Result<U2>>(&self, op: F2) -> Result<U2> {
         self.query_with_store(Store::default(), op).await
     }
@@ -199,43 +376,61 @@ where
     Wallet: wallet::Wallet + Clone,
     Symbol: crate::coins::Symbol,
 {
-    // TODO: support subclients
-    // TODO: return object with result data (e.g. txid)
+    /// Signs and broadcasts a call, returning the result of the transaction
+    /// once it has been processed. Calls rejected for having an invalid nonce
+    /// are retried like in [AppClient::call].
     pub fn call_sync(
         &self,
-        payer: impl FnOnce(&U) -> T::Call,
-        payee: impl FnOnce(&U) -> T::Call,
-    ) -> Result<()> {
+        payer: impl FnOnce(&U) -> U::Call,
+        payee: impl FnOnce(&U) -> U::Call,
+    ) -> Result<TxResponse>
+    where
+        U: Call,
+    {
         let (chain_id, store) = exec::sync::execute(Store::default(), &self.transport, |app| {
             Ok(app.inner.inner.borrow().inner.inner.chain_id.to_vec())
         })?;
-        let (nonce, store) = match self.wallet.address()? {
-            None => (None, store),
-            Some(addr) => exec::sync::execute(store, &self.transport, |app| {
-                Ok(Some(
-                    app.inner.inner.borrow_mut().inner.inner.inner.nonce(addr)? + 1,
-                ))
-            })?,
-        };
+        let (mut nonce, store) = self.fetch_nonce_sync(store)?;
 
         let app = self.query_with_store_sync(store, Ok)?;
 
-        let payer_call = payer(&app);
-        let payer_call_bytes = payer_call.encode()?;
-        let payer = <T as Call>::Call::decode(payer_call_bytes.as_slice())?;
-
-        let paid = payee(&app);
-        let call = PayableCall::Paid(PaidCall { payer, paid });
-        let call = crate::plugins::NonceCall {
-            nonce,
-            inner_call: call,
-        };
-        let call = [chain_id, call.encode()?].concat();
-        let call = self.wallet.sign(&call)?;
-        let call = ABCICall::DeliverTx(sdk_compat::Call::Native(call));
-        self.transport.call_sync(call)?;
+        let payer = self.wrap_call(payer(&app))?;
+        let paid = self.wrap_call(payee(&app))?;
+        let call_bytes = PayableCall::Paid(PaidCall { payer, paid }).encode()?;
+
+        let mut retries = 0;
+        loop {
+            let call = crate::plugins::NonceCall {
+                nonce,
+                inner_call: PayableCall::decode(call_bytes.as_slice())?,
+            };
+            let description = format!("{:?}", call);
+            let call = [chain_id.as_slice(), call.encode()?.as_slice()].concat();
+            let call = self.wallet.sign_with_description(&call, &description)?;
+            let call = ABCICall::DeliverTx(sdk_compat::Call::Native(call));
+
+            let res = self.transport.call_sync(call);
+            self.record_nonce(nonce, &res);
+            match res {
+                Err(err) if err.is_retryable() && retries < self.nonce_retries => {
+                    retries += 1;
+                    nonce = self.fetch_nonce_sync(Store::default())?.0;
+                }
+                res => return res,
+            }
+        }
+    }
 
-        Ok(())
+    fn fetch_nonce_sync(&self, store: Store) -> Result<(Option<u64>, Store)> {
+        match self.wallet.address()? {
+            None => Ok((None, store)),
+            Some(addr) => {
+                let (state_nonce, store) = exec::sync::execute(store, &self.transport, |app| {
+                    app.inner.inner.borrow_mut().inner.inner.inner.nonce(addr)
+                })?;
+                Ok((Some(self.next_nonce(state_nonce)), store))
+            }
+        }
     }
 
     pub fn query_root_sync<U2, F2: FnMut(ABCIPlugin<DefaultPlugins<Symbol, T>>) -> Result<U2>>(
@@ -481,10 +676,18 @@ mod tests {
                 .await?;
             assert_eq!(value, 3);
 
-            client.call(
-                |app| build_call!(app.bar.inc_b(4)),
-                |app| build_call!(app.signed_method(DerivedKey::address_for(b"alice").unwrap())),
-            ).await?;
+            let tx =
+                client
+                    .call(
+                        |app| build_call!(app.bar.inc_b(4)),
+                        |app| {
+                            build_call!(
+                                app.signed_method(DerivedKey::address_for(b"alice").unwrap())
+                            )
+                        },
+                    )
+                    .await?;
+            assert_eq!(tx.hash.len(), 64);
         }
 
         {
@@ -506,18 +709,20 @@ mod tests {
             DerivedKey::new(b"alice").unwrap(),
         );
 
-        let bar_client = client.sub(|app| app.bar);
+        let bar_client = crate::sub_client!(client, app.bar);
 
         let bar_b = bar_client.query(|bar| Ok(bar.b)).await?;
         assert_eq!(bar_b, 8);
 
-        // TODO
-        // bar_client
-        //     .call(
-        //         |bar| build_call!(bar.inc_b(4)),
-        //         |bar| build_call!(bar.inc_b(4)),
-        //     )
-        //     .await?;
+        bar_client
+            .call(
+                |bar| build_call!(bar.inc_b(4)),
+                |bar| build_call!(bar.inc_b(4)),
+            )
+            .await?;
+
+        let bar_b = bar_client.query(|bar| Ok(bar.b)).await?;
+        assert_eq!(bar_b, 16);
 
         Ok(())
     }
@@ -580,23 +785,38 @@ mod tests {
     #[test]
     fn sub_sync() -> Result<()> {
         let mut mock_client = setup()?;
-        let client = AppClient::<Foo, Foo, _, _, _>::new(
-            &mut mock_client,
-            DerivedKey::new(b"alice").unwrap(),
-        );
 
-        let bar_client = client.sub(|app| app.bar);
+        {
+            let client = AppClient::<Foo, Foo, _, _, _>::new(
+                &mut mock_client,
+                DerivedKey::new(b"alice").unwrap(),
+            );
 
-        let bar_b = bar_client.query_sync(|bar| Ok(bar.b))?;
-        assert_eq!(bar_b, 8);
+            let bar_client = crate::sub_client!(client, app.bar);
+
+            let bar_b = bar_client.query_sync(|bar| Ok(bar.b))?;
+            assert_eq!(bar_b, 8);
 
-        // TODO
-        // bar_client
-        //     .call(
-        //         |bar| build_call!(bar.inc_b(4)),
-        //         |bar| build_call!(bar.inc_b(4)),
-        //     )
-        //     .await?;
+            bar_client.call_sync(
+                |bar| build_call!(bar.inc_b(4)),
+                |bar| build_call!(bar.inc_b(4)),
+            )?;
+
+            let bar_b = bar_client.query_sync(|bar| Ok(bar.b))?;
+            assert_eq!(bar_b, 16);
+        }
+
+        {
+            // sub-clients created with `sub` can only be queried
+            let client =
+                AppClient::<Foo, Foo, _, _, _>::new(&mut mock_client, Unsigned).sub(|app| app.bar);
+            assert!(client
+                .call_sync(
+                    |bar| build_call!(bar.inc_b(4)),
+                    |bar| build_call!(bar.inc_b(4)),
+                )
+                .is_err());
+        }
 
         Ok(())
     }
@@ -610,11 +830,203 @@ mod tests {
             DerivedKey::new(b"alice").unwrap(),
         );
 
-        fn do_query(client: impl Client<Foo>) {
+        fn do_query(client: &impl Client<Foo>) {
             let bar_b = client.query_sync(|app| Ok(app.bar.b)).unwrap();
             assert_eq!(bar_b, 8);
         }
 
-        do_query(client);
+        fn do_call(client: impl Client<Bar>) {
+            client
+                .call_sync(
+                    |bar| build_call!(bar.inc_b(4)),
+                    |bar| build_call!(bar.inc_b(4)),
+                )
+                .unwrap();
+            let bar_b = client.query_sync(|bar| Ok(bar.b)).unwrap();
+            assert_eq!(bar_b, 16);
+        }
+
+        do_query(&client);
+        do_call(crate::sub_client!(client, app.bar));
+    }
+
+    /// A transport which rejects the first calls it receives for having an
+    /// invalid nonce, then passes calls through to the mock app.
+    struct RejectNonces {
+        inner: MockClient<App>,
+        rejections: Mutex<usize>,
+        calls: Mutex<usize>,
+    }
+
+    impl RejectNonces {
+        fn new(rejections: usize) -> Result<Self> {
+            Ok(Self {
+                inner: setup()?,
+                rejections: Mutex::new(rejections),
+                calls: Mutex::new(0),
+            })
+        }
+
+        fn reject(&self) -> Result<()> {
+            *self.calls.lock().unwrap() += 1;
+            let mut rejections = self.rejections.lock().unwrap();
+            if *rejections > 0 {
+                *rejections -= 1;
+                return Err(Error::CheckTx {
+                    code: 1,
+                    codespace: String::new(),
+                    log: "Nonce is not valid. Expected 1-1000, got 6".into(),
+                });
+            }
+            Ok(())
+        }
+    }
+
+    impl sync::Transport<App> for RejectNonces {
+        fn query_sync(&self, query: <App as Query>::Query) -> Result<Store> {
+            self.inner.query_sync(query)
+        }
+
+        fn call_sync(&self, call: <App as Call>::Call) -> Result<TxResponse> {
+            self.reject()?;
+            self.inner.call_sync(call)
+        }
+    }
+
+    impl Transport<App> for RejectNonces {
+        async fn query(&self, query: <App as Query>::Query) -> Result<Store> {
+            self.inner.query_sync(query)
+        }
+
+        async fn call(&self, call: <App as Call>::Call) -> Result<TxResponse> {
+            self.reject()?;
+            self.inner.call_sync(call)
+        }
+    }
+
+    fn state_nonce(client: &AppClient<Foo, Foo, RejectNonces, Simp, DerivedKey>) -> Result<u64> {
+        let address = DerivedKey::address_for(b"alice")?;
+        client.query_root_sync(|app| {
+            app.inner
+                .inner
+                .borrow_mut()
+                .inner
+                .inner
+                .inner
+                .nonce(address)
+        })
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn nonce_tracking() {
+        let client = AppClient::<Foo, Foo, _, Simp, _>::new((), Unsigned);
+        assert_eq!(client.next_nonce(0), 1);
+
+        // calls which haven't been committed yet are accounted for
+        client.record_nonce(Some(1), &Ok(TxResponse::default()));
+        assert_eq!(client.next_nonce(0), 2);
+        client.record_nonce(Some(2), &Ok(TxResponse::default()));
+        assert_eq!(client.next_nonce(1), 3);
+
+        // the chain's nonce is used once it has caught up
+        assert_eq!(client.next_nonce(5), 6);
+
+        // other errors don't affect the nonce
+        client.record_nonce(Some(3), &Err(Error::App("failed".into())));
+        assert_eq!(client.next_nonce(0), 3);
+
+        // the nonce is re-synced after a call is rejected for its nonce
+        let err = Error::Nonce("Nonce is not valid. Expected 4-1003, got 3".into());
+        client.record_nonce(Some(3), &Err(err));
+        assert_eq!(client.next_nonce(0), 1);
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn nonce_back_to_back() -> Result<()> {
+        let mut mock_client = setup()?;
+        let client = AppClient::<Foo, Foo, _, _, _>::new(
+            &mut mock_client,
+            DerivedKey::new(b"alice").unwrap(),
+        );
+
+        for nonce in 1..=3 {
+            client.call_sync(
+                |app| build_call!(app.bar.inc_b(4)),
+                |app| build_call!(app.signed_method(DerivedKey::address_for(b"alice").unwrap())),
+            )?;
+            assert_eq!(*client.last_nonce.lock().unwrap(), Some(nonce));
+        }
+
+        let bar_b = client.query_sync(|app| Ok(app.bar.b))?;
+        assert_eq!(bar_b, 20);
+
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn nonce_retry_sync() -> Result<()> {
+        let client = AppClient::<Foo, Foo, _, Simp, _>::new(
+            RejectNonces::new(1)?,
+            DerivedKey::new(b"alice").unwrap(),
+        );
+        // a stale nonce, e.g. from before another client signed with this key
+        *client.last_nonce.lock().unwrap() = Some(5);
+
+        client.call_sync(
+            |app| build_call!(app.bar.inc_b(4)),
+            |app| build_call!(app.signed_method(DerivedKey::address_for(b"alice").unwrap())),
+        )?;
+        assert_eq!(*client.transport.calls.lock().unwrap(), 2);
+        assert_eq!(*client.last_nonce.lock().unwrap(), Some(1));
+        assert_eq!(state_nonce(&client)?, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn nonce_retry_limit() -> Result<()> {
+        let client = AppClient::<Foo, Foo, _, Simp, _>::new(
+            RejectNonces::new(5)?,
+            DerivedKey::new(b"alice").unwrap(),
+        )
+        .with_nonce_retries(2);
+
+        let err = client
+            .call_sync(
+                |app| build_call!(app.bar.inc_b(4)),
+                |app| build_call!(app.signed_method(DerivedKey::address_for(b"alice").unwrap())),
+            )
+            .unwrap_err();
+        assert!(err.is_retryable());
+        assert_eq!(*client.transport.calls.lock().unwrap(), 3);
+        assert_eq!(state_nonce(&client)?, 0);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn nonce_retry() -> Result<()> {
+        let client = AppClient::<Foo, Foo, _, Simp, _>::new(
+            RejectNonces::new(2)?,
+            DerivedKey::new(b"alice").unwrap(),
+        );
+        *client.last_nonce.lock().unwrap() = Some(5);
+
+        client
+            .call(
+                |app| build_call!(app.bar.inc_b(4)),
+                |app| build_call!(app.signed_method(DerivedKey::address_for(b"alice").unwrap())),
+            )
+            .await?;
+        assert_eq!(*client.transport.calls.lock().unwrap(), 3);
+        assert_eq!(*client.last_nonce.lock().unwrap(), Some(1));
+
+        Ok(())
     }
 }
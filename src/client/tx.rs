@@ -0,0 +1,136 @@
+use crate::{Error, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// The result of broadcasting a transaction, returned by
+/// [AppClient::call](super::AppClient::call).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct TxResponse {
+    /// The uppercase hex SHA-256 hash of the transaction bytes, as used by
+    /// Tendermint to identify transactions.
+    pub hash: String,
+    /// The height of the block the transaction was included in, or 0 if it
+    /// has not been included in a block.
+    pub height: u64,
+    pub check_code: u32,
+    pub deliver_code: u32,
+    pub codespace: String,
+    pub log: String,
+    pub gas_wanted: i64,
+    pub gas_used: i64,
+    pub events: Vec<TxEvent>,
+}
+
+/// An event emitted while processing a transaction.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct TxEvent {
+    pub kind: String,
+    pub attributes: Vec<(String, String)>,
+}
+
+impl TxResponse {
+    /// Creates a response for a transaction which has not been broadcast to a
+    /// network, e.g. one executed by a mock client.
+    pub fn local(tx: &[u8]) -> Self {
+        Self {
+            hash: tx_hash(tx),
+            ..Default::default()
+        }
+    }
+
+    /// Converts a response for a transaction which failed in `DeliverTx` into
+    /// an [Error::DeliverTx].
+    pub fn into_result(self) -> Result<Self> {
+        if self.deliver_code != 0 {
+            return Err(Error::DeliverTx {
+                code: self.deliver_code,
+                codespace: self.codespace,
+                log: self.log,
+                hash: self.hash,
+                height: self.height,
+            });
+        }
+
+        Ok(self)
+    }
+
+    /// Returns the events of the given kind, e.g. `"transfer"`.
+    pub fn events_of<'a>(&'a self, kind: &'a str) -> impl Iterator<Item = &'a TxEvent> + 'a {
+        self.events.iter().filter(move |event| event.kind == kind)
+    }
+
+    /// Decodes all the events of type `E` emitted by the transaction.
+    pub fn typed_events<E: FromEvent>(&self) -> Result<Vec<E>> {
+        self.events_of(E::KIND).map(E::from_event).collect()
+    }
+}
+
+/// A type which can be decoded from the events of a given kind emitted by an
+/// app, e.g. a transfer with its sender, recipient and amount.
+pub trait FromEvent: Sized {
+    const KIND: &'static str;
+
+    fn from_event(event: &TxEvent) -> Result<Self>;
+}
+
+impl TxEvent {
+    /// Returns the value of the first attribute with the given key.
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns the value of the attribute with the given key, or an error if
+    /// the event does not have one. Useful when implementing [FromEvent].
+    pub fn require(&self, key: &str) -> Result<&str> {
+        self.attribute(key).ok_or_else(|| {
+            Error::Client(format!("Event {} is missing attribute {}", self.kind, key))
+        })
+    }
+}
+
+/// Computes the hash Tendermint uses to identify a transaction.
+pub fn tx_hash(tx: &[u8]) -> String {
+    hex::encode_upper(Sha256::digest(tx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn into_result() {
+        let res = TxResponse {
+            height: 5,
+            log: "ok".into(),
+            ..TxResponse::local(b"tx")
+        };
+        assert_eq!(res.clone().into_result().unwrap(), res);
+
+        let res = TxResponse {
+            height: 5,
+            deliver_code: 3,
+            codespace: "app".into(),
+            log: "insufficient funds".into(),
+            ..TxResponse::local(b"tx")
+        };
+        match res.into_result().unwrap_err() {
+            Error::DeliverTx {
+                code,
+                codespace,
+                log,
+                hash,
+                height,
+            } => {
+                assert_eq!(code, 3);
+                assert_eq!(codespace, "app");
+                assert_eq!(log, "insufficient funds");
+                assert_eq!(hash, tx_hash(b"tx"));
+                assert_eq!(height, 5);
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
@@ -8,9 +8,27 @@ use crate::{
     Result,
 };
 
+#[cfg(feature = "keystore")]
+pub mod hd;
+#[cfg(feature = "keystore")]
+pub mod keystore;
+pub mod remote;
+
+#[cfg(feature = "keystore")]
+pub use hd::{CoinType, HdWallet};
+#[cfg(feature = "keystore")]
+pub use keystore::Keystore;
+pub use remote::RemoteWallet;
+
 pub trait Wallet: Clone + Send + Sync {
     fn sign(&self, call_bytes: &[u8]) -> Result<SignerCall>;
 
+    /// Signs the call, with a human-readable description of it for wallets
+    /// which show calls to the user before signing.
+    fn sign_with_description(&self, call_bytes: &[u8], _description: &str) -> Result<SignerCall> {
+        self.sign(call_bytes)
+    }
+
     fn address(&self) -> Result<Option<Address>>;
 
     fn nonce_hint(&self) -> Result<Option<u64>> {
@@ -0,0 +1,274 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use bip39::Mnemonic;
+use hmac::{Hmac, Mac};
+use secp256k1::{PublicKey, Scalar, Secp256k1, SecretKey};
+use serde::{Deserialize, Serialize};
+use sha2::Sha512;
+
+use super::Wallet;
+use crate::{
+    coins::Address,
+    plugins::{SigType, SignerCall},
+    Error, Result,
+};
+
+const HARDENED: u32 = 1 << 31;
+
+/// The BIP-44 coin types supported for key derivation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CoinType {
+    /// Coin type 118, used by Cosmos SDK chains and Keplr.
+    Cosmos,
+    /// Coin type 60, used by Ethereum wallets such as MetaMask.
+    Ethereum,
+}
+
+impl CoinType {
+    pub fn index(&self) -> u32 {
+        match self {
+            CoinType::Cosmos => 118,
+            CoinType::Ethereum => 60,
+        }
+    }
+
+    /// Returns the standard BIP-44 path for the address at `index`, i.e.
+    /// `m/44'/<coin type>'/0'/0/<index>`.
+    pub fn path(&self, index: u32) -> DerivationPath {
+        DerivationPath(vec![
+            44 | HARDENED,
+            self.index() | HARDENED,
+            HARDENED,
+            0,
+            index,
+        ])
+    }
+}
+
+/// A BIP-32 derivation path, e.g. `m/44'/118'/0'/0/0`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DerivationPath(Vec<u32>);
+
+impl FromStr for DerivationPath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::Signer(format!("Invalid derivation path: {}", s));
+
+        let mut parts = s.split('/');
+        if parts.next() != Some("m") {
+            return Err(invalid());
+        }
+
+        parts
+            .map(|part| {
+                let (index, hardened) = match part.strip_suffix('\'') {
+                    Some(index) => (index, true),
+                    None => (part, false),
+                };
+                let index: u32 = index.parse().map_err(|_| invalid())?;
+                if index >= HARDENED {
+                    return Err(invalid());
+                }
+
+                Ok(if hardened { index | HARDENED } else { index })
+            })
+            .collect::<Result<_>>()
+            .map(DerivationPath)
+    }
+}
+
+impl Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+        for index in self.0.iter() {
+            if index & HARDENED != 0 {
+                write!(f, "/{}'", index & !HARDENED)?;
+            } else {
+                write!(f, "/{}", index)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Generates a new random 24-word BIP-39 mnemonic.
+pub fn generate_mnemonic() -> Result<String> {
+    use rand::RngCore;
+    let mut entropy = [0; 32];
+    rand::thread_rng().fill_bytes(&mut entropy);
+
+    let mnemonic =
+        Mnemonic::from_entropy(&entropy).map_err(|err| Error::Signer(err.to_string()))?;
+
+    Ok(mnemonic.to_string())
+}
+
+/// A wallet holding a key derived from a BIP-39 mnemonic.
+///
+/// Calls are always signed with the native signature type, so the wallet's
+/// address is derived from its public key the same way for every coin type.
+/// For keys derived on the Ethereum coin type, [HdWallet::eth_address] returns
+/// the address used when signing with an Ethereum wallet instead.
+#[derive(Clone, Debug)]
+pub struct HdWallet {
+    privkey: SecretKey,
+    pubkey: PublicKey,
+    path: DerivationPath,
+}
+
+impl HdWallet {
+    /// Derives the key at `path` from the mnemonic, with an optional BIP-39
+    /// passphrase (an empty string if unused).
+    pub fn from_mnemonic(phrase: &str, passphrase: &str, path: &DerivationPath) -> Result<Self> {
+        let mnemonic =
+            Mnemonic::parse_normalized(phrase).map_err(|err| Error::Signer(err.to_string()))?;
+        let seed = mnemonic.to_seed_normalized(passphrase);
+
+        Self::from_seed(&seed, path)
+    }
+
+    /// Derives the key at `path` from a BIP-32 seed.
+    pub fn from_seed(seed: &[u8], path: &DerivationPath) -> Result<Self> {
+        let secp = Secp256k1::new();
+
+        let (mut privkey, mut chain_code) = split(hmac_sha512(b"Bitcoin seed", &[seed])?)?;
+        for index in path.0.iter() {
+            let index_bytes = index.to_be_bytes();
+            let hash = if index & HARDENED != 0 {
+                hmac_sha512(&chain_code, &[&[0], &privkey.secret_bytes(), &index_bytes])?
+            } else {
+                let pubkey = PublicKey::from_secret_key(&secp, &privkey).serialize();
+                hmac_sha512(&chain_code, &[&pubkey, &index_bytes])?
+            };
+
+            let (tweak, child_chain_code) = split(hash)?;
+            privkey = tweak.add_tweak(&Scalar::from(privkey))?;
+            chain_code = child_chain_code;
+        }
+
+        let pubkey = PublicKey::from_secret_key(&secp, &privkey);
+
+        Ok(Self {
+            privkey,
+            pubkey,
+            path: path.clone(),
+        })
+    }
+
+    pub fn path(&self) -> &DerivationPath {
+        &self.path
+    }
+
+    pub fn privkey(&self) -> &SecretKey {
+        &self.privkey
+    }
+
+    pub fn pubkey(&self) -> PublicKey {
+        self.pubkey
+    }
+
+    pub fn address(&self) -> Address {
+        Address::from_pubkey(self.pubkey.serialize())
+    }
+
+    pub fn eth_address(&self) -> Address {
+        let mut eth_pubkey = [0; 64];
+        eth_pubkey.copy_from_slice(&self.pubkey.serialize_uncompressed()[1..]);
+        Address::from_pubkey_eth(eth_pubkey)
+    }
+}
+
+impl Wallet for HdWallet {
+    fn sign(&self, call_bytes: &[u8]) -> Result<SignerCall> {
+        use secp256k1::hashes::sha256;
+        let secp = Secp256k1::new();
+        let msg = secp256k1::Message::from_hashed_data::<sha256::Hash>(call_bytes);
+        let sig = secp.sign_ecdsa(&msg, &self.privkey).serialize_compact();
+
+        Ok(SignerCall {
+            call_bytes: call_bytes.to_vec(),
+            signature: Some(sig),
+            pubkey: Some(self.pubkey.serialize()),
+            sigtype: SigType::Native,
+        })
+    }
+
+    fn address(&self) -> Result<Option<Address>> {
+        Ok(Some(self.address()))
+    }
+}
+
+fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> Result<[u8; 64]> {
+    let mut mac =
+        Hmac::<Sha512>::new_from_slice(key).map_err(|err| Error::Signer(err.to_string()))?;
+    for bytes in data {
+        mac.update(bytes);
+    }
+
+    let mut hash = [0; 64];
+    hash.copy_from_slice(&mac.finalize().into_bytes());
+
+    Ok(hash)
+}
+
+/// Splits an HMAC output into a private key and chain code.
+fn split(hash: [u8; 64]) -> Result<(SecretKey, [u8; 32])> {
+    let privkey = SecretKey::from_slice(&hash[..32])?;
+    let mut chain_code = [0; 32];
+    chain_code.copy_from_slice(&hash[32..]);
+
+    Ok((privkey, chain_code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn derivation_paths() -> Result<()> {
+        let path: DerivationPath = "m/44'/118'/0'/0/7".parse()?;
+        assert_eq!(path, CoinType::Cosmos.path(7));
+        assert_eq!(path.to_string(), "m/44'/118'/0'/0/7");
+        assert!("44'/118'".parse::<DerivationPath>().is_err());
+        assert!("m/foo".parse::<DerivationPath>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn bip32_test_vector() -> Result<()> {
+        // test vector 1 from BIP-32
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let wallet = HdWallet::from_seed(&seed, &"m/0'/1/2'/2/1000000000".parse()?)?;
+        assert_eq!(
+            hex::encode(wallet.privkey().secret_bytes()),
+            "471b76e389e528d6de6d816857e012c5455051cad6660850e58372a6c3e6e7c8"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn mnemonic_accounts() -> Result<()> {
+        let eth = HdWallet::from_mnemonic(MNEMONIC, "", &CoinType::Ethereum.path(0))?;
+        assert_eq!(
+            hex::encode(eth.eth_address().bytes()),
+            "9858effd232b4033e47d90003d41ec34ecaeda94"
+        );
+
+        let cosmos = HdWallet::from_mnemonic(MNEMONIC, "", &CoinType::Cosmos.path(0))?;
+        assert_ne!(cosmos.address(), eth.address());
+        assert!(HdWallet::from_mnemonic("abandon abandon", "", &CoinType::Cosmos.path(0)).is_err());
+
+        let phrase = generate_mnemonic()?;
+        assert_eq!(phrase.split(' ').count(), 24);
+        HdWallet::from_mnemonic(&phrase, "", &CoinType::Cosmos.path(0))?;
+
+        Ok(())
+    }
+}
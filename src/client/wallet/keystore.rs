@@ -0,0 +1,297 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use super::hd::{CoinType, DerivationPath, HdWallet};
+use crate::{coins::Address, Error, Result};
+
+const KEYSTORE_VERSION: u32 = 1;
+const KDF_ROUNDS: u32 = 600_000;
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+
+/// A file holding named accounts, each with a BIP-39 mnemonic encrypted by a
+/// password.
+///
+/// Mnemonics are encrypted with ChaCha20-Poly1305 using a key derived from
+/// the password with PBKDF2-HMAC-SHA256, with a random salt and nonce per
+/// account. Addresses are stored in plaintext so accounts can be listed
+/// without a password.
+pub struct Keystore {
+    path: PathBuf,
+    file: KeystoreFile,
+    kdf_rounds: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeystoreFile {
+    version: u32,
+    accounts: BTreeMap<String, Account>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Account {
+    address: String,
+    path: String,
+    kdf_rounds: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl Keystore {
+    /// Opens the keystore file at `path`, or starts an empty keystore which
+    /// will be written to `path` once an account is added.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = if path.exists() {
+            let file: KeystoreFile = serde_json::from_slice(&std::fs::read(&path)?)?;
+            if file.version != KEYSTORE_VERSION {
+                return Err(Error::Signer(format!(
+                    "Unsupported keystore version {}",
+                    file.version
+                )));
+            }
+            file
+        } else {
+            KeystoreFile {
+                version: KEYSTORE_VERSION,
+                accounts: BTreeMap::new(),
+            }
+        };
+
+        Ok(Self {
+            path,
+            file,
+            kdf_rounds: KDF_ROUNDS,
+        })
+    }
+
+    /// Sets the number of PBKDF2 rounds used when encrypting new accounts.
+    /// Existing accounts keep the number of rounds they were encrypted with.
+    #[must_use]
+    pub fn with_kdf_rounds(mut self, rounds: u32) -> Self {
+        self.kdf_rounds = rounds;
+        self
+    }
+
+    /// Lists the names of the accounts in the keystore.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.file.accounts.keys().map(String::as_str)
+    }
+
+    pub fn address(&self, name: &str) -> Result<Address> {
+        self.account(name)?
+            .address
+            .parse()
+            .map_err(|_| Error::Signer(format!("Invalid address for account {}", name)))
+    }
+
+    /// Adds an account for the key at the standard path of `coin_type` and
+    /// `index`, encrypting the mnemonic with `password`, and returns its
+    /// address.
+    pub fn add(
+        &mut self,
+        name: &str,
+        mnemonic: &str,
+        coin_type: CoinType,
+        index: u32,
+        password: &str,
+    ) -> Result<Address> {
+        self.add_with_path(name, mnemonic, &coin_type.path(index), password)
+    }
+
+    /// Adds an account for the key at a custom derivation path.
+    pub fn add_with_path(
+        &mut self,
+        name: &str,
+        mnemonic: &str,
+        path: &DerivationPath,
+        password: &str,
+    ) -> Result<Address> {
+        if self.file.accounts.contains_key(name) {
+            return Err(Error::Signer(format!("Account {} already exists", name)));
+        }
+
+        let wallet = HdWallet::from_mnemonic(mnemonic, "", path)?;
+        let address = wallet.address();
+
+        let mut salt = [0; SALT_LENGTH];
+        let mut nonce = [0; NONCE_LENGTH];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let cipher = cipher(password, &salt, self.kdf_rounds);
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), mnemonic.as_bytes())
+            .map_err(|_| Error::Signer("Failed to encrypt mnemonic".into()))?;
+
+        self.file.accounts.insert(
+            name.to_string(),
+            Account {
+                address: address.to_string(),
+                path: path.to_string(),
+                kdf_rounds: self.kdf_rounds,
+                salt: hex::encode(salt),
+                nonce: hex::encode(nonce),
+                ciphertext: hex::encode(ciphertext),
+            },
+        );
+        self.save()?;
+
+        Ok(address)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<()> {
+        self.account(name)?;
+        self.file.accounts.remove(name);
+        self.save()
+    }
+
+    /// Decrypts the account's mnemonic and derives its wallet. Errors if the
+    /// password is incorrect.
+    pub fn unlock(&self, name: &str, password: &str) -> Result<HdWallet> {
+        let account = self.account(name)?;
+        let corrupted = || Error::Signer("Corrupted keystore account".into());
+        let decode = |value: &str| hex::decode(value).map_err(|_| corrupted());
+
+        let salt = decode(&account.salt)?;
+        let nonce = decode(&account.nonce)?;
+        if salt.len() != SALT_LENGTH || nonce.len() != NONCE_LENGTH {
+            return Err(corrupted());
+        }
+
+        let cipher = cipher(password, &salt, account.kdf_rounds);
+        let mnemonic = cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                decode(&account.ciphertext)?.as_slice(),
+            )
+            .map_err(|_| Error::Signer("Incorrect keystore password".into()))?;
+        let mnemonic = String::from_utf8(mnemonic).map_err(|_| corrupted())?;
+
+        HdWallet::from_mnemonic(&mnemonic, "", &account.path.parse()?)
+    }
+
+    fn account(&self, name: &str) -> Result<&Account> {
+        self.file
+            .accounts
+            .get(name)
+            .ok_or_else(|| Error::Signer(format!("No account named {}", name)))
+    }
+
+    /// Writes the keystore to a temporary file, created readable only by the
+    /// owner, then replaces the keystore file so it is never partially
+    /// written.
+    fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // a file left over from an interrupted save may have other
+        // permissions, so always create a new one
+        let tmp_path = self.path.with_extension("tmp");
+        if let Err(err) = std::fs::remove_file(&tmp_path) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                return Err(err.into());
+            }
+        }
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&tmp_path)?;
+        file.write_all(&serde_json::to_vec_pretty(&self.file)?)?;
+        file.sync_all()?;
+        drop(file);
+        std::fs::rename(tmp_path, &self.path)?;
+
+        Ok(())
+    }
+}
+
+fn cipher(password: &str, salt: &[u8], rounds: u32) -> ChaCha20Poly1305 {
+    let mut key = [0; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, rounds, &mut key);
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::wallet::hd::generate_mnemonic;
+    use tempdir::TempDir;
+
+    #[test]
+    fn keystore_accounts() -> Result<()> {
+        let dir = TempDir::new("orga-keystore").unwrap();
+        let path = dir.path().join("keys.json");
+        let mnemonic = generate_mnemonic()?;
+
+        let mut keystore = Keystore::open(&path)?.with_kdf_rounds(1_000);
+        let alice = keystore.add("alice", &mnemonic, CoinType::Cosmos, 0, "hunter2")?;
+        let bob = keystore.add("bob", &mnemonic, CoinType::Ethereum, 0, "hunter3")?;
+        assert_ne!(alice, bob);
+        assert!(keystore
+            .add("alice", &mnemonic, CoinType::Cosmos, 1, "hunter2")
+            .is_err());
+
+        let keystore = Keystore::open(&path)?;
+        assert_eq!(keystore.names().collect::<Vec<_>>(), vec!["alice", "bob"]);
+        assert_eq!(keystore.address("bob")?, bob);
+        assert_eq!(keystore.unlock("alice", "hunter2")?.address(), alice);
+        assert!(keystore.unlock("alice", "hunter3").is_err());
+        assert!(!std::fs::read_to_string(&path)?.contains(mnemonic.as_str()));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path)?.permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let mut keystore = keystore;
+        keystore.remove("alice")?;
+        assert!(Keystore::open(&path)?.unlock("alice", "hunter2").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn keystore_corrupted_account() -> Result<()> {
+        let dir = TempDir::new("orga-keystore").unwrap();
+        let path = dir.path().join("keys.json");
+        let mnemonic = generate_mnemonic()?;
+
+        let mut keystore = Keystore::open(&path)?.with_kdf_rounds(1_000);
+        keystore.add("alice", &mnemonic, CoinType::Cosmos, 0, "hunter2")?;
+
+        let corrupt = |field: &str, value: &str| -> Result<Keystore> {
+            let mut json: serde_json::Value = serde_json::from_slice(&std::fs::read(&path)?)?;
+            json["accounts"]["alice"][field] = value.into();
+            std::fs::write(&path, serde_json::to_vec(&json)?)?;
+            Keystore::open(&path)
+        };
+
+        for (field, value) in [("nonce", "00"), ("salt", ""), ("nonce", "zz")] {
+            let keystore = corrupt(field, value)?;
+            match keystore.unlock("alice", "hunter2") {
+                Err(Error::Signer(msg)) => assert_eq!(msg, "Corrupted keystore account"),
+                Err(err) => panic!("unexpected error: {}", err),
+                Ok(_) => panic!("unlocked corrupted account"),
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,634 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use super::Wallet;
+use crate::{
+    call::Call,
+    coins::Address,
+    encoding::Decode,
+    plugins::{NonceCall, PaidCall, PayableCall, SigType, SignerCall},
+    Error, Result,
+};
+
+/// How long a [SignerServer] waits for a client to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The maximum length of a request line accepted by a [SignerServer].
+const MAX_REQUEST_LENGTH: usize = 1 << 20;
+
+/// The address of a [SignerServer], written as `unix:<path>` or
+/// `tcp:<host>:<port>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Endpoint {
+    Unix(PathBuf),
+    Tcp(SocketAddr),
+}
+
+impl FromStr for Endpoint {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            Ok(Endpoint::Unix(path.into()))
+        } else if let Some(addr) = s.strip_prefix("tcp:") {
+            addr.parse()
+                .map(Endpoint::Tcp)
+                .map_err(|_| Error::Signer(format!("Invalid signer address: {}", addr)))
+        } else {
+            Err(Error::Signer(format!("Invalid signer endpoint: {}", s)))
+        }
+    }
+}
+
+/// A request sent to a [SignerServer], encoded as a line of JSON.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    Address,
+    Sign {
+        call_bytes: String,
+        description: String,
+    },
+}
+
+/// A response from a [SignerServer], encoded as a line of JSON.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    Address { address: Option<String> },
+    Signature { signature: String, pubkey: String },
+    Error { message: String },
+}
+
+/// A wallet which forwards signing requests to a [SignerServer] in another
+/// process, so the process building calls never holds private keys.
+#[derive(Clone, Debug)]
+pub struct RemoteWallet {
+    endpoint: Endpoint,
+    timeout: Option<Duration>,
+}
+
+impl RemoteWallet {
+    pub fn new(endpoint: Endpoint) -> Self {
+        Self {
+            endpoint,
+            // long enough for an operator to approve the call
+            timeout: Some(Duration::from_secs(300)),
+        }
+    }
+
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn request(&self, req: &Request) -> Result<Response> {
+        let res = match &self.endpoint {
+            Endpoint::Tcp(addr) => {
+                let stream = TcpStream::connect(addr)?;
+                stream.set_read_timeout(self.timeout)?;
+                roundtrip(stream, req)?
+            }
+            #[cfg(unix)]
+            Endpoint::Unix(path) => {
+                let stream = std::os::unix::net::UnixStream::connect(path)?;
+                stream.set_read_timeout(self.timeout)?;
+                roundtrip(stream, req)?
+            }
+            #[cfg(not(unix))]
+            Endpoint::Unix(_) => {
+                return Err(Error::Signer(
+                    "Unix sockets are not supported on this platform".into(),
+                ))
+            }
+        };
+
+        match res {
+            Response::Error { message } => Err(Error::Signer(message)),
+            res => Ok(res),
+        }
+    }
+}
+
+impl Wallet for RemoteWallet {
+    fn sign(&self, call_bytes: &[u8]) -> Result<SignerCall> {
+        self.sign_with_description(call_bytes, "")
+    }
+
+    fn sign_with_description(&self, call_bytes: &[u8], description: &str) -> Result<SignerCall> {
+        let req = Request::Sign {
+            call_bytes: hex::encode(call_bytes),
+            description: description.to_string(),
+        };
+        let (signature, pubkey) = match self.request(&req)? {
+            Response::Signature { signature, pubkey } => (signature, pubkey),
+            _ => return Err(Error::Signer("Unexpected response from signer".into())),
+        };
+
+        Ok(SignerCall {
+            call_bytes: call_bytes.to_vec(),
+            signature: Some(decode_array(&signature)?),
+            pubkey: Some(decode_array(&pubkey)?),
+            sigtype: SigType::Native,
+        })
+    }
+
+    fn address(&self) -> Result<Option<Address>> {
+        match self.request(&Request::Address)? {
+            Response::Address { address: None } => Ok(None),
+            Response::Address {
+                address: Some(address),
+            } => address
+                .parse()
+                .map(Some)
+                .map_err(|_| Error::Signer("Invalid address from signer".into())),
+            _ => Err(Error::Signer("Unexpected response from signer".into())),
+        }
+    }
+}
+
+fn roundtrip<S: Read + Write>(mut stream: S, req: &Request) -> Result<Response> {
+    let mut line = serde_json::to_vec(req)?;
+    line.push(b'\n');
+    stream.write_all(&line)?;
+    stream.flush()?;
+
+    let mut res = String::new();
+    BufReader::new(stream).read_line(&mut res)?;
+
+    Ok(serde_json::from_str(&res)?)
+}
+
+fn decode_array<const N: usize>(value: &str) -> Result<[u8; N]> {
+    hex::decode(value)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| Error::Signer("Malformed signature from signer".into()))
+}
+
+/// A request to sign a call, as seen by a [Policy].
+#[derive(Clone, Debug)]
+pub struct SignRequest {
+    pub call_bytes: Vec<u8>,
+    /// The description provided by the client, which should not be trusted.
+    pub description: String,
+    /// The descriptions of the calls decoded from the call bytes by the
+    /// signer, if the policy has a decoder.
+    pub calls: Option<Vec<String>>,
+}
+
+pub type DecodeFn = Box<dyn Fn(&[u8]) -> Result<Vec<String>> + Send + Sync>;
+pub type SpendFn = Box<dyn Fn(&SignRequest) -> Result<u64> + Send + Sync>;
+pub type ApproveFn = Box<dyn Fn(&SignRequest) -> bool + Send + Sync>;
+
+struct SpendLimit {
+    amount: u64,
+    window: Duration,
+    spend: SpendFn,
+    history: Mutex<VecDeque<(Instant, u64)>>,
+}
+
+/// The rules a [SignerServer] applies before signing a call.
+///
+/// The default policy signs nothing: calls must be permitted with
+/// [Policy::allow_all], [Policy::allow_calls] or an approval function, and are
+/// then subject to the policy's spend limit.
+#[derive(Default)]
+pub struct Policy {
+    allow_all: bool,
+    decode: Option<DecodeFn>,
+    allowed_calls: Option<Vec<String>>,
+    spend_limit: Option<SpendLimit>,
+    approve: Option<ApproveFn>,
+}
+
+impl Policy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Permits every call, unless it is rejected by another rule.
+    #[must_use]
+    pub fn allow_all(mut self) -> Self {
+        self.allow_all = true;
+        self
+    }
+
+    /// Decodes call bytes built by [AppClient](crate::client::AppClient) for
+    /// an app with call type `T` on the given chain, describing the payer and
+    /// paid calls with their `Debug` representations.
+    #[must_use]
+    pub fn decode_calls<T: Call + 'static>(self, chain_id: &str) -> Self {
+        let chain_id = chain_id.as_bytes().to_vec();
+        self.decode_with(Box::new(move |bytes: &[u8]| {
+            let bytes = bytes
+                .strip_prefix(chain_id.as_slice())
+                .ok_or_else(|| Error::Signer("Call is for a different chain".into()))?;
+            let call = NonceCall::<PayableCall<T::Call>>::decode(bytes)?;
+            Ok(match call.inner_call {
+                PayableCall::Paid(PaidCall { payer, paid }) => {
+                    vec![format!("{:?}", payer), format!("{:?}", paid)]
+                }
+                PayableCall::Unpaid(call) => vec![format!("{:?}", call)],
+            })
+        }))
+    }
+
+    #[must_use]
+    pub fn decode_with(mut self, decode: DecodeFn) -> Self {
+        self.decode = Some(decode);
+        self
+    }
+
+    /// Only signs calls where every decoded call starts with one of the given
+    /// prefixes, e.g. `"FieldAccounts(MethodTransfer"`. Requires a decoder.
+    #[must_use]
+    pub fn allow_calls(mut self, prefixes: Vec<String>) -> Self {
+        self.allowed_calls = Some(prefixes);
+        self
+    }
+
+    /// Refuses to sign calls once the total spent within `window`, as
+    /// measured by `spend`, would exceed `amount`.
+    #[must_use]
+    pub fn spend_limit(mut self, amount: u64, window: Duration, spend: SpendFn) -> Self {
+        self.spend_limit = Some(SpendLimit {
+            amount,
+            window,
+            spend,
+            history: Mutex::new(VecDeque::new()),
+        });
+        self
+    }
+
+    #[must_use]
+    pub fn approve_with(mut self, approve: ApproveFn) -> Self {
+        self.approve = Some(approve);
+        self
+    }
+
+    /// Asks for approval of each call on the signer's terminal.
+    #[must_use]
+    pub fn prompt(self) -> Self {
+        self.approve_with(Box::new(|req: &SignRequest| {
+            let calls = match &req.calls {
+                Some(calls) => calls.join("\n  "),
+                None => hex::encode(&req.call_bytes),
+            };
+            eprintln!(
+                "Signature requested: {}\n  {}\nApprove? [y/N]",
+                req.description, calls
+            );
+
+            let mut answer = String::new();
+            std::io::stdin().read_line(&mut answer).is_ok()
+                && answer.trim().eq_ignore_ascii_case("y")
+        }))
+    }
+
+    /// Decodes the request and checks it against the policy, recording its
+    /// spend if it is allowed.
+    pub fn check(&self, call_bytes: &[u8], description: &str) -> Result<()> {
+        let calls = self
+            .decode
+            .as_ref()
+            .map(|decode| decode(call_bytes))
+            .transpose()?;
+        let req = SignRequest {
+            call_bytes: call_bytes.to_vec(),
+            description: description.to_string(),
+            calls,
+        };
+
+        if !self.allow_all && self.allowed_calls.is_none() && self.approve.is_none() {
+            return Err(Error::Signer("Call is not allowed by policy".into()));
+        }
+
+        if let Some(prefixes) = &self.allowed_calls {
+            let calls = req
+                .calls
+                .as_ref()
+                .ok_or_else(|| Error::Signer("Policy cannot decode calls".into()))?;
+            let allowed = |call: &String| prefixes.iter().any(|p| call.starts_with(p.as_str()));
+            if !calls.iter().all(allowed) {
+                return Err(Error::Signer("Call is not allowed by policy".into()));
+            }
+        }
+
+        let spend = match &self.spend_limit {
+            Some(limit) => {
+                let amount = (limit.spend)(&req)?;
+                let mut history = limit.history.lock().unwrap();
+                while let Some((time, _)) = history.front() {
+                    if time.elapsed() <= limit.window {
+                        break;
+                    }
+                    history.pop_front();
+                }
+
+                let spent: u64 = history.iter().map(|(_, amount)| amount).sum();
+                if spent.saturating_add(amount) > limit.amount {
+                    return Err(Error::Signer("Call exceeds spend limit".into()));
+                }
+                Some((limit, amount))
+            }
+            None => None,
+        };
+
+        if let Some(approve) = &self.approve {
+            if !approve(&req) {
+                return Err(Error::Signer("Call was rejected".into()));
+            }
+        }
+
+        if let Some((limit, amount)) = spend {
+            limit
+                .history
+                .lock()
+                .unwrap()
+                .push_back((Instant::now(), amount));
+        }
+
+        Ok(())
+    }
+}
+
+/// Serves signing requests from [RemoteWallet]s, signing with its own wallet
+/// when the request is allowed by its [Policy].
+///
+/// Connections are handled one at a time, so approval prompts are never
+/// interleaved.
+pub struct SignerServer<W> {
+    wallet: W,
+    policy: Policy,
+    allow_remote: bool,
+}
+
+impl<W: Wallet> SignerServer<W> {
+    pub fn new(wallet: W, policy: Policy) -> Self {
+        Self {
+            wallet,
+            policy,
+            allow_remote: false,
+        }
+    }
+
+    /// Allows listening on TCP addresses other than loopback. Requests are
+    /// neither authenticated nor encrypted, so this should only be used on
+    /// trusted networks.
+    #[must_use]
+    pub fn allow_remote(mut self) -> Self {
+        self.allow_remote = true;
+        self
+    }
+
+    pub fn handle(&self, req: Request) -> Response {
+        let res = match req {
+            Request::Address => self.wallet.address().map(|address| Response::Address {
+                address: address.map(|a| a.to_string()),
+            }),
+            Request::Sign {
+                call_bytes,
+                description,
+            } => self.sign(&call_bytes, &description),
+        };
+
+        res.unwrap_or_else(|err| Response::Error {
+            message: err.to_string(),
+        })
+    }
+
+    fn sign(&self, call_bytes: &str, description: &str) -> Result<Response> {
+        let call_bytes =
+            hex::decode(call_bytes).map_err(|_| Error::Signer("Malformed call bytes".into()))?;
+        self.policy.check(&call_bytes, description)?;
+
+        let call = self.wallet.sign(&call_bytes)?;
+        match (call.signature, call.pubkey) {
+            (Some(signature), Some(pubkey)) => Ok(Response::Signature {
+                signature: hex::encode(signature),
+                pubkey: hex::encode(pubkey),
+            }),
+            _ => Err(Error::Signer("Wallet did not sign the call".into())),
+        }
+    }
+
+    /// Listens on the endpoint, serving requests until an error occurs. Unix
+    /// sockets are created readable only by the owner, and TCP addresses must
+    /// be loopback addresses unless [SignerServer::allow_remote] is set.
+    pub fn listen(&self, endpoint: &Endpoint) -> Result<()> {
+        match endpoint {
+            Endpoint::Tcp(addr) => {
+                if !addr.ip().is_loopback() && !self.allow_remote {
+                    return Err(Error::Signer(format!(
+                        "Refusing to listen on non-loopback address {}",
+                        addr
+                    )));
+                }
+                self.serve_tcp(TcpListener::bind(addr)?)?;
+            }
+            #[cfg(unix)]
+            Endpoint::Unix(path) => {
+                if path.exists() {
+                    std::fs::remove_file(path)?;
+                }
+                let listener = bind_private(path)?;
+                for stream in listener.incoming() {
+                    let stream = stream?;
+                    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+                    self.serve_connection(stream);
+                }
+            }
+            #[cfg(not(unix))]
+            Endpoint::Unix(_) => {
+                return Err(Error::Signer(
+                    "Unix sockets are not supported on this platform".into(),
+                ))
+            }
+        }
+
+        Ok(())
+    }
+
+    fn serve_tcp(&self, listener: TcpListener) -> Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+            self.serve_connection(stream);
+        }
+
+        Ok(())
+    }
+
+    fn serve_connection<S: Read + Write>(&self, mut stream: S) {
+        if let Err(err) = self.serve_requests(&mut stream) {
+            log::warn!("Signer connection error: {}", err);
+        }
+    }
+
+    fn serve_requests<S: Read + Write>(&self, stream: &mut S) -> Result<()> {
+        let mut reader = BufReader::new(stream);
+        loop {
+            let mut line = String::new();
+            let limit = MAX_REQUEST_LENGTH as u64 + 1;
+            match (&mut reader).take(limit).read_line(&mut line)? {
+                0 => return Ok(()),
+                len if len > MAX_REQUEST_LENGTH => {
+                    return Err(Error::Signer("Request is too long".into()))
+                }
+                _ => {}
+            }
+
+            let res = match serde_json::from_str(&line) {
+                Ok(req) => self.handle(req),
+                Err(err) => Response::Error {
+                    message: err.to_string(),
+                },
+            };
+
+            let mut res = serde_json::to_vec(&res)?;
+            res.push(b'\n');
+            let stream = reader.get_mut();
+            stream.write_all(&res)?;
+            stream.flush()?;
+        }
+    }
+}
+
+/// Binds a Unix socket which only its owner can connect to. The socket is
+/// created inside a new directory readable only by the owner and restricted
+/// before it is moved into place, so it is never reachable by other users.
+#[cfg(unix)]
+fn bind_private(path: &std::path::Path) -> Result<std::os::unix::net::UnixListener> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::Signer(format!("Invalid socket path {}", path.display())))?;
+    let mut dir_name = std::ffi::OsString::from(".");
+    dir_name.push(file_name);
+    dir_name.push(format!(".{}", std::process::id()));
+    let dir = path.with_file_name(dir_name);
+
+    std::fs::DirBuilder::new().mode(0o700).create(&dir)?;
+    let bind = || -> Result<_> {
+        let tmp_path = dir.join("socket");
+        let listener = std::os::unix::net::UnixListener::bind(&tmp_path)?;
+        std::fs::set_permissions(&tmp_path, std::fs::Permissions::from_mode(0o600))?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(listener)
+    };
+    let res = bind();
+    std::fs::remove_dir_all(&dir)?;
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::wallet::DerivedKey;
+
+    #[test]
+    fn remote_signing() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let endpoint = Endpoint::Tcp(addr);
+
+        let policy = Policy::new()
+            .decode_with(Box::new(|bytes: &[u8]| {
+                Ok(vec![String::from_utf8_lossy(bytes).to_string()])
+            }))
+            .allow_calls(vec!["transfer".into()])
+            .spend_limit(
+                10,
+                Duration::from_secs(60),
+                Box::new(|req: &SignRequest| Ok(req.call_bytes.len() as u64)),
+            );
+        let server = SignerServer::new(DerivedKey::new(b"alice")?, policy);
+        std::thread::spawn(move || server.serve_tcp(listener).unwrap());
+
+        let wallet = RemoteWallet::new(endpoint);
+        assert_eq!(wallet.address()?, Some(DerivedKey::address_for(b"alice")?));
+
+        let signed = wallet.sign_with_description(b"transfer", "send coins")?;
+        assert_eq!(signed.address()?, DerivedKey::address_for(b"alice")?);
+        assert!(wallet.sign(b"withdraw").is_err());
+        // 8 + 8 bytes exceeds the limit of 10 within the window
+        assert!(wallet.sign(b"transfer").is_err());
+
+        // overlong requests are dropped without stopping the server
+        let mut stream = TcpStream::connect(addr)?;
+        stream.write_all(&vec![b'a'; MAX_REQUEST_LENGTH + 1])?;
+        let mut res = String::new();
+        let _ = BufReader::new(stream).read_line(&mut res);
+        assert!(res.is_empty());
+        assert!(wallet.address().is_ok());
+
+        assert_eq!(
+            "unix:/tmp/signer.sock".parse::<Endpoint>()?,
+            Endpoint::Unix("/tmp/signer.sock".into())
+        );
+        assert!("tcp:nope".parse::<Endpoint>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn policy_defaults_to_deny() -> Result<()> {
+        assert!(Policy::default().check(b"transfer", "").is_err());
+        assert!(Policy::new().allow_all().check(b"transfer", "").is_ok());
+        assert!(Policy::new()
+            .approve_with(Box::new(|_: &SignRequest| true))
+            .check(b"transfer", "")
+            .is_ok());
+        assert!(Policy::new()
+            .allow_all()
+            .spend_limit(
+                4,
+                Duration::from_secs(60),
+                Box::new(|req: &SignRequest| Ok(req.call_bytes.len() as u64)),
+            )
+            .check(b"transfer", "")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn listen_loopback_only() -> Result<()> {
+        let server = SignerServer::new(DerivedKey::new(b"alice")?, Policy::new());
+        let endpoint = Endpoint::Tcp("0.0.0.0:0".parse().unwrap());
+        match server.listen(&endpoint) {
+            Err(Error::Signer(msg)) => assert!(msg.contains("non-loopback")),
+            res => panic!("unexpected result: {:?}", res),
+        }
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private_unix_socket() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir::TempDir::new("orga-signer").unwrap();
+        let path = dir.path().join("signer.sock");
+        let _listener = bind_private(&path)?;
+
+        let mode = std::fs::metadata(&path)?.permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 1);
+        std::os::unix::net::UnixStream::connect(&path)?;
+
+        Ok(())
+    }
+}
@@ -2,16 +2,38 @@ use crate::coins::{Address, Amount, Coin, Give, Symbol, Take};
 use crate::collections::map::Iter as MapIter;
 use crate::collections::Map;
 use crate::context::GetContext;
+use crate::encoding::LengthVec;
+use crate::migrate::MigrateFrom;
 use crate::orga;
 use crate::plugins::Paid;
 use crate::plugins::Signer;
 use crate::{Error, Result};
 
-#[orga]
+#[orga(version = 1)]
 pub struct Accounts<S: Symbol> {
     transfers_allowed: bool,
     transfer_exceptions: Map<Address, ()>,
     accounts: Map<Address, Coin<S>>,
+
+    #[orga(version(V1))]
+    admin: Option<Address>,
+    #[orga(version(V1))]
+    frozen: Map<Address, ()>,
+    #[orga(version(V1))]
+    blocked_recipients: Map<Address, ()>,
+}
+
+impl<S: Symbol> MigrateFrom<AccountsV0<S>> for AccountsV1<S> {
+    fn migrate_from(value: AccountsV0<S>) -> Result<Self> {
+        Ok(Self {
+            transfers_allowed: value.transfers_allowed,
+            transfer_exceptions: value.transfer_exceptions,
+            accounts: value.accounts,
+            admin: None,
+            frozen: Default::default(),
+            blocked_recipients: Default::default(),
+        })
+    }
 }
 
 #[orga]
@@ -22,10 +44,8 @@ impl<S: Symbol> Accounts<S> {
 
     #[call]
     pub fn transfer(&mut self, to: Address, amount: Amount) -> Result<()> {
-        let signer = self.signer()?;
-        if !self.transfers_allowed && !self.transfer_exceptions.contains_key(signer)? {
-            return Err(Error::Coins("Transfers are currently disabled".into()));
-        }
+        self.assert_transfers_allowed()?;
+        self.assert_can_receive(to)?;
         let taken_coins = self.take_own_coins(amount)?;
         let mut receiver = self.accounts.entry(to)?.or_insert_default()?;
         receiver.give(taken_coins)?;
@@ -44,8 +64,77 @@ impl<S: Symbol> Accounts<S> {
         paid.give::<S, _>(taken_coins.amount)
     }
 
+    /// Pays every output from the signer's account in a single call. Either
+    /// all outputs are paid or the call fails without moving any coins.
+    #[call]
+    pub fn multi_send(&mut self, outputs: LengthVec<u16, (Address, Amount)>) -> Result<()> {
+        self.assert_transfers_allowed()?;
+        let total = self.multi_send_total(&outputs)?;
+        let taken_coins = self.take_own_coins(total)?;
+
+        self.pay_outputs(taken_coins, outputs.into())
+    }
+
+    /// Pays every output from the funding of a paid call, e.g. to batch
+    /// payouts of several denoms by funding each in the payer call.
+    #[call]
+    pub fn multi_send_from_funding(
+        &mut self,
+        outputs: LengthVec<u16, (Address, Amount)>,
+    ) -> Result<()> {
+        self.assert_transfers_allowed()?;
+        let total = self.multi_send_total(&outputs)?;
+        let taken_coins = self
+            .context::<Paid>()
+            .ok_or_else(|| Error::Coins("No Paid context found".into()))?
+            .take(total)?;
+
+        self.pay_outputs(taken_coins, outputs.into())
+    }
+
+    /// Checks the recipients of a multi-send and returns the total amount it
+    /// pays out.
+    fn multi_send_total(&self, outputs: &[(Address, Amount)]) -> Result<Amount> {
+        if outputs.is_empty() {
+            return Err(Error::Coins(
+                "Multi-send must have at least one output".into(),
+            ));
+        }
+
+        let mut total: Amount = 0.into();
+        for (to, amount) in outputs.iter() {
+            if *amount == 0 {
+                return Err(Error::Coins("Amount must be positive".into()));
+            }
+            self.assert_can_receive(*to)?;
+            total = (total + *amount)?;
+        }
+
+        Ok(total)
+    }
+
+    /// Pays out `coins` to the given outputs, which must sum to the amount of
+    /// `coins`. Recipients must already have been checked by
+    /// [Accounts::multi_send_total].
+    fn pay_outputs(&mut self, mut coins: Coin<S>, outputs: Vec<(Address, Amount)>) -> Result<()> {
+        for (to, amount) in outputs {
+            let paid_coins = coins.take(amount)?;
+            self.accounts
+                .entry(to)?
+                .or_insert_default()?
+                .give(paid_coins)?;
+        }
+
+        if coins.amount > 0 {
+            return Err(Error::Coins("Outputs do not sum to the paid amount".into()));
+        }
+
+        Ok(())
+    }
+
     fn take_own_coins(&mut self, amount: Amount) -> Result<Coin<S>> {
         let signer = self.signer()?;
+        self.assert_not_frozen(signer)?;
 
         let taken_coins = self
             .accounts
@@ -86,6 +175,7 @@ impl<S: Symbol> Accounts<S> {
 
     fn give_own_coins(&mut self, coins: Coin<S>) -> Result<()> {
         let signer = self.signer()?;
+        self.assert_can_receive(signer)?;
 
         self.accounts
             .entry(signer)?
@@ -108,6 +198,108 @@ impl<S: Symbol> Accounts<S> {
         Ok(self.accounts.get(address)?.is_some())
     }
 
+    #[query]
+    pub fn is_frozen(&self, address: Address) -> Result<bool> {
+        self.frozen.contains_key(address)
+    }
+
+    #[query]
+    pub fn is_blocked_recipient(&self, address: Address) -> Result<bool> {
+        self.blocked_recipients.contains_key(address)
+    }
+
+    #[query]
+    pub fn admin(&self) -> Result<Option<Address>> {
+        Ok(self.admin)
+    }
+
+    #[call]
+    pub fn freeze(&mut self, address: Address) -> Result<()> {
+        self.assert_admin()?;
+        self.set_frozen(address, true)
+    }
+
+    #[call]
+    pub fn unfreeze(&mut self, address: Address) -> Result<()> {
+        self.assert_admin()?;
+        self.set_frozen(address, false)
+    }
+
+    #[call]
+    pub fn block_recipient(&mut self, address: Address) -> Result<()> {
+        self.assert_admin()?;
+        self.set_recipient_blocked(address, true)
+    }
+
+    #[call]
+    pub fn unblock_recipient(&mut self, address: Address) -> Result<()> {
+        self.assert_admin()?;
+        self.set_recipient_blocked(address, false)
+    }
+
+    /// Sets the address allowed to freeze accounts and block recipients, or
+    /// removes it so that only governance (calling [Accounts::set_frozen] and
+    /// [Accounts::set_recipient_blocked] directly) can.
+    pub fn set_admin(&mut self, admin: Option<Address>) {
+        self.admin = admin;
+    }
+
+    /// Freezes or unfreezes an address. Frozen addresses cannot transfer,
+    /// withdraw or pay from their balance, which also prevents them from
+    /// funding IBC transfers out.
+    pub fn set_frozen(&mut self, address: Address, frozen: bool) -> Result<()> {
+        if frozen {
+            self.frozen.insert(address, ())
+        } else {
+            self.frozen.remove(address)?;
+            Ok(())
+        }
+    }
+
+    /// Blocks or unblocks an address from receiving transfers.
+    pub fn set_recipient_blocked(&mut self, address: Address, blocked: bool) -> Result<()> {
+        if blocked {
+            self.blocked_recipients.insert(address, ())
+        } else {
+            self.blocked_recipients.remove(address)?;
+            Ok(())
+        }
+    }
+
+    fn assert_transfers_allowed(&mut self) -> Result<()> {
+        let signer = self.signer()?;
+        if !self.transfers_allowed && !self.transfer_exceptions.contains_key(signer)? {
+            return Err(Error::Coins("Transfers are currently disabled".into()));
+        }
+
+        Ok(())
+    }
+
+    fn assert_admin(&mut self) -> Result<()> {
+        let signer = self.signer()?;
+        if self.admin != Some(signer) {
+            return Err(Error::Coins("Unauthorized account action".into()));
+        }
+
+        Ok(())
+    }
+
+    fn assert_not_frozen(&self, address: Address) -> Result<()> {
+        if self.frozen.contains_key(address)? {
+            return Err(Error::Coins("Account is frozen".into()));
+        }
+
+        Ok(())
+    }
+
+    fn assert_can_receive(&self, address: Address) -> Result<()> {
+        if self.blocked_recipients.contains_key(address)? {
+            return Err(Error::Coins("Recipient is blocked".into()));
+        }
+
+        Ok(())
+    }
+
     pub fn allow_transfers(&mut self, enabled: bool) {
         self.transfers_allowed = enabled;
     }
@@ -117,6 +309,7 @@ impl<S: Symbol> Accounts<S> {
     }
 
     pub fn deposit(&mut self, address: Address, coins: Coin<S>) -> Result<()> {
+        self.assert_can_receive(address)?;
         let mut account = self.accounts.entry(address)?.or_insert_default()?;
         account.give(coins)?;
 
@@ -124,7 +317,134 @@ impl<S: Symbol> Accounts<S> {
     }
 
     pub fn withdraw(&mut self, address: Address, amount: Amount) -> Result<Coin<S>> {
+        self.assert_not_frozen(address)?;
         let mut account = self.accounts.entry(address)?.or_insert_default()?;
         account.take(amount)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Context;
+
+    #[orga]
+    #[derive(Clone, Debug)]
+    struct Simp;
+    impl Symbol for Simp {
+        const INDEX: u8 = 0;
+        const NAME: &'static str = "SIMP";
+    }
+
+    fn set_signer(address: Address) {
+        Context::add(Signer {
+            signer: Some(address),
+        });
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn freeze_and_block() -> Result<()> {
+        let admin = Address::from_pubkey([0; 33]);
+        let alice = Address::from_pubkey([1; 33]);
+        let bob = Address::from_pubkey([2; 33]);
+
+        let mut accounts = Accounts::<Simp>::default();
+        accounts.allow_transfers(true);
+        accounts.set_admin(Some(admin));
+        accounts.deposit(alice, Simp::mint(100))?;
+
+        set_signer(alice);
+        accounts
+            .freeze(alice)
+            .expect_err("Only the admin can freeze accounts");
+
+        set_signer(admin);
+        accounts.freeze(alice)?;
+        assert!(accounts.is_frozen(alice)?);
+
+        set_signer(alice);
+        accounts
+            .transfer(bob, 10.into())
+            .expect_err("Frozen accounts cannot transfer");
+        accounts
+            .withdraw(alice, 10.into())
+            .expect_err("Frozen accounts cannot withdraw");
+
+        set_signer(admin);
+        accounts.unfreeze(alice)?;
+        accounts.block_recipient(bob)?;
+
+        set_signer(alice);
+        accounts
+            .transfer(bob, 10.into())
+            .expect_err("Blocked recipients cannot receive transfers");
+
+        accounts
+            .deposit(bob, Simp::mint(10))
+            .expect_err("Blocked recipients cannot receive deposits");
+
+        set_signer(bob);
+        let mut paid = Paid::default();
+        paid.give::<Simp, _>(10)?;
+        Context::add(paid);
+        accounts
+            .give_from_funding(10.into())
+            .expect_err("Blocked recipients cannot receive funding");
+        Context::remove::<Paid>();
+
+        set_signer(admin);
+        accounts.unblock_recipient(bob)?;
+
+        set_signer(alice);
+        accounts.transfer(bob, 10.into())?;
+        assert_eq!(accounts.balance(bob)?, 10);
+
+        Context::remove::<Signer>();
+
+        Ok(())
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn multi_send() -> Result<()> {
+        let alice = Address::from_pubkey([1; 33]);
+        let bob = Address::from_pubkey([2; 33]);
+        let carol = Address::from_pubkey([3; 33]);
+
+        let mut accounts = Accounts::<Simp>::default();
+        accounts.allow_transfers(true);
+        accounts.deposit(alice, Simp::mint(100))?;
+
+        set_signer(alice);
+        accounts.multi_send(LengthVec::new(
+            2,
+            vec![(bob, 30.into()), (carol, 20.into())],
+        ))?;
+        assert_eq!(accounts.balance(alice)?, 50);
+        assert_eq!(accounts.balance(bob)?, 30);
+        assert_eq!(accounts.balance(carol)?, 20);
+
+        accounts
+            .multi_send(LengthVec::new(
+                2,
+                vec![(bob, 40.into()), (carol, 20.into())],
+            ))
+            .expect_err("Should not be able to send more than the balance");
+        assert_eq!(accounts.balance(alice)?, 50);
+        assert_eq!(accounts.balance(bob)?, 30);
+
+        accounts.allow_transfers(false);
+        let mut paid = Paid::default();
+        paid.give::<Simp, _>(10)?;
+        Context::add(paid);
+        accounts
+            .multi_send_from_funding(LengthVec::new(1, vec![(bob, 10.into())]))
+            .expect_err("Should not be able to send while transfers are disabled");
+        Context::remove::<Paid>();
+
+        Context::remove::<Signer>();
+
+        Ok(())
+    }
+}
@@ -21,12 +21,23 @@ impl<S: Symbol> Coin<S> {
         }
     }
 
+    /// Mints coins which did not previously exist, recording the increase in
+    /// total supply in the `SupplyChanges` context if one is active.
     pub fn mint<A>(amount: A) -> Self
     where
         A: Into<Amount>,
     {
+        let amount = amount.into();
+        super::supply::record_mint(S::INDEX, amount);
+
+        Self::from_existing(amount)
+    }
+
+    /// Wraps value which already exists, e.g. moved out of shares or
+    /// balances, without changing the total supply.
+    pub(crate) fn from_existing(amount: Amount) -> Self {
         Coin {
-            amount: amount.into(),
+            amount,
             symbol: PhantomData,
         }
     }
@@ -35,7 +46,11 @@ impl<S: Symbol> Coin<S> {
         dest.give(self)
     }
 
-    pub fn burn(self) {}
+    /// Destroys the coins, recording the burn in the `SupplyChanges` context
+    /// if one is active.
+    pub fn burn(self) {
+        super::supply::record_burn(S::INDEX, self.amount);
+    }
 
     pub fn take_as_funding(&mut self, amount: Amount) -> Result<()> {
         let taken_coins = self.take(amount)?;
@@ -75,7 +90,7 @@ impl<S: Symbol> Take<S> for Coin<S> {
         }
         self.amount = (self.amount - amount)?;
 
-        Ok(Coin::mint(amount))
+        Ok(Coin::from_existing(amount))
     }
 }
 
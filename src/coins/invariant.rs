@@ -0,0 +1,119 @@
+use super::Supply;
+use crate::{Error, Result};
+
+/// A named check over application state which must always hold.
+pub struct Invariant<T> {
+    pub name: &'static str,
+    pub check: fn(&T, &Supply) -> Result<()>,
+}
+
+/// A collection of invariants which can be checked against an application.
+pub struct InvariantRegistry<T> {
+    invariants: Vec<Invariant<T>>,
+}
+
+impl<T> Default for InvariantRegistry<T> {
+    fn default() -> Self {
+        Self { invariants: vec![] }
+    }
+}
+
+impl<T> InvariantRegistry<T> {
+    pub fn register(&mut self, name: &'static str, check: fn(&T, &Supply) -> Result<()>) {
+        self.invariants.push(Invariant { name, check });
+    }
+
+    pub fn len(&self) -> usize {
+        self.invariants.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.invariants.is_empty()
+    }
+
+    /// Runs every registered invariant, returning an error naming the first
+    /// one which does not hold.
+    pub fn check(&self, app: &T, supply: &Supply) -> Result<()> {
+        for invariant in self.invariants.iter() {
+            (invariant.check)(app, supply).map_err(|err| {
+                Error::Coins(format!("Invariant '{}' violated: {}", invariant.name, err))
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Implemented by types which declare invariants over their own state.
+///
+/// Types which do not implement this register no invariants. Wrappers such as
+/// plugins override `check_invariants` to check the invariants of the type
+/// they wrap instead.
+pub trait RegisterInvariants: Sized {
+    fn register_invariants(registry: &mut InvariantRegistry<Self>);
+
+    /// Runs every invariant registered by this type.
+    fn check_invariants(&self, supply: &Supply) -> Result<()> {
+        invariants::<Self>().check(self, supply)
+    }
+}
+
+impl<T> RegisterInvariants for T {
+    default fn register_invariants(_registry: &mut InvariantRegistry<Self>) {}
+
+    default fn check_invariants(&self, supply: &Supply) -> Result<()> {
+        invariants::<Self>().check(self, supply)
+    }
+}
+
+/// Builds the registry of invariants declared by `T`.
+pub fn invariants<T: RegisterInvariants>() -> InvariantRegistry<T> {
+    let mut registry = InvariantRegistry::default();
+    T::register_invariants(&mut registry);
+    registry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coins::Amount;
+
+    struct Ledger {
+        balance: Amount,
+    }
+
+    impl RegisterInvariants for Ledger {
+        fn register_invariants(registry: &mut InvariantRegistry<Self>) {
+            registry.register("balance matches supply", |ledger, supply| {
+                if ledger.balance != supply.total(0)? {
+                    return Err(Error::Coins("Balance does not match supply".into()));
+                }
+                Ok(())
+            });
+        }
+    }
+
+    #[test]
+    fn check_registered() -> Result<()> {
+        let mut supply = Supply::default();
+        supply.add_genesis_supply(0, 100.into())?;
+
+        let registry = invariants::<Ledger>();
+        assert_eq!(registry.len(), 1);
+        registry.check(
+            &Ledger {
+                balance: 100.into(),
+            },
+            &supply,
+        )?;
+
+        let err = registry
+            .check(&Ledger { balance: 99.into() }, &supply)
+            .unwrap_err();
+        assert!(err.to_string().contains("balance matches supply"));
+
+        assert!(invariants::<u64>().is_empty());
+
+        Ok(())
+    }
+}
@@ -49,6 +49,12 @@ pub use math::*;
 pub mod faucet;
 pub use faucet::*;
 
+pub mod supply;
+pub use supply::*;
+
+pub mod invariant;
+pub use invariant::*;
+
 mod ops;
 pub use ops::*;
 
@@ -50,7 +50,7 @@ impl<S: Symbol> Take<S, Amount> for MultiShare {
         let amount = amount.into();
         self.deduct(amount, S::INDEX)?;
 
-        Ok(S::mint(amount))
+        Ok(Coin::from_existing(amount))
     }
 }
 
@@ -52,7 +52,7 @@ impl<S: Symbol> Take<S, Amount> for Share<S> {
         }
         self.shares = (self.shares - amount)?;
 
-        Ok(Coin::mint(amount))
+        Ok(Coin::from_existing(amount))
     }
 }
 
@@ -46,7 +46,7 @@ impl<S: Symbol> Delegator<S> {
             };
             self.unbonding.push_back(unbond)
         } else {
-            self.liquid.give(S::mint(amount))
+            self.liquid.give(Coin::<S>::from_existing(amount))
         }
     }
 
@@ -147,7 +147,8 @@ impl<S: Symbol> Delegator<S> {
                     .unbonding
                     .pop_front()?
                     .ok_or_else(|| Error::Coins("Failed to pop unbond".into()))?;
-                self.liquid.give(S::mint(unbond.coins.shares.amount()?))?;
+                self.liquid
+                    .give(Coin::<S>::from_existing(unbond.coins.shares.amount()?))?;
             } else {
                 break;
             }
@@ -0,0 +1,282 @@
+use super::Staking;
+use crate::coins::{Address, Amount, Balance, Coin, Decimal, Give, Symbol, Take};
+use crate::{Error, Result};
+use sha2::{Digest, Sha256};
+use std::convert::TryInto;
+
+const LIQUID_POOL_DOMAIN: &[u8] = b"orga/staking/liquid";
+
+/// Returns the address of the module-held delegator which backs the liquid
+/// tokens of the given validator.
+///
+/// The address is derived by hashing, so no key can sign for it and its stake
+/// can only be moved by tokenizing or redeeming.
+pub fn liquid_pool_address(val_address: Address) -> Address {
+    let mut hasher = Sha256::new();
+    hasher.update(LIQUID_POOL_DOMAIN);
+    hasher.update(val_address.bytes());
+    let hash = hasher.finalize();
+    let bytes: [u8; Address::LENGTH] = hash[..Address::LENGTH].try_into().unwrap();
+
+    bytes.into()
+}
+
+impl<S: Symbol> Staking<S> {
+    /// Moves `amount` of the delegator's stake with the given validator into
+    /// the validator's liquid pool, crediting the delegator with liquid tokens
+    /// representing a proportional claim on the pool.
+    ///
+    /// The pool is an ordinary delegation, so rewards and slashes applied to
+    /// the validator's delegators change the value of each token.
+    pub fn tokenize<A: Into<Amount>>(
+        &mut self,
+        val_address: Address,
+        delegator_address: Address,
+        amount: A,
+    ) -> Result<Amount> {
+        let amount = amount.into();
+        let pool_address = liquid_pool_address(val_address);
+        if delegator_address == pool_address {
+            return Err(Error::Coins("Cannot tokenize the liquid pool".into()));
+        }
+
+        self.compound_liquid_pool(val_address)?;
+        let pool_staked = self.liquid_pool_staked(val_address)?;
+        let supply = *self.liquid_token_supply.get_or_default(val_address)?;
+
+        let minted = if supply == 0 {
+            amount
+        } else if pool_staked == Decimal::zero() {
+            return Err(Error::Coins(
+                "Liquid pool has no stake backing its tokens".into(),
+            ));
+        } else {
+            ((supply / pool_staked) * amount)?.amount()?
+        };
+        if minted == 0 {
+            return Err(Error::Coins("Amount is too small to tokenize".into()));
+        }
+
+        {
+            let mut validator = self.validators.get_mut(val_address)?;
+            let min_self_delegation = validator.min_self_delegation;
+            let mut delegator = validator.get_mut(delegator_address)?;
+            if !delegator.redelegations_in.is_empty() {
+                return Err(Error::Coins(
+                    "Cannot tokenize stake with inbound redelegations".into(),
+                ));
+            }
+            let coins = delegator.staked.take(amount)?;
+            if delegator_address == val_address && delegator.staked.amount()? < min_self_delegation
+            {
+                return Err(Error::Coins(
+                    "Cannot tokenize below the validator's min self-delegation".into(),
+                ));
+            }
+            drop(delegator);
+
+            let mut pool = validator.get_mut(pool_address)?;
+            pool.add_stake(coins)?;
+        }
+        self.index_delegation(val_address, pool_address)?;
+
+        self.liquid_token_supply
+            .insert(val_address, (supply + minted)?)?;
+        let mut balances = self
+            .liquid_token_balances
+            .entry(val_address)?
+            .or_insert_default()?;
+        let mut balance = balances.entry(delegator_address)?.or_default()?;
+        *balance = (*balance + minted)?;
+        drop(balance);
+        drop(balances);
+
+        self.update_vp(val_address)?;
+
+        Ok(minted)
+    }
+
+    /// Burns `tokens` of the holder's liquid tokens for the given validator
+    /// and moves the stake they represent out of the liquid pool into a
+    /// regular delegation owned by the holder.
+    pub fn redeem<A: Into<Amount>>(
+        &mut self,
+        val_address: Address,
+        holder_address: Address,
+        tokens: A,
+    ) -> Result<Amount> {
+        let tokens = tokens.into();
+        let pool_address = liquid_pool_address(val_address);
+        if holder_address == pool_address {
+            return Err(Error::Coins("Cannot redeem to the liquid pool".into()));
+        }
+
+        // rewards are distributed before burning so the holder's tokens still
+        // earn their share
+        self.compound_liquid_pool(val_address)?;
+        self.burn_liquid_tokens(val_address, holder_address, tokens)?;
+
+        let pool_staked = self.liquid_pool_staked(val_address)?;
+        let supply = *self.liquid_token_supply.get_or_default(val_address)?;
+        let remaining_supply = (supply - tokens)?;
+
+        let amount = if remaining_supply == 0 {
+            pool_staked.amount()?
+        } else {
+            ((pool_staked * tokens) / Decimal::from(supply))?.amount()?
+        };
+        self.liquid_token_supply
+            .insert(val_address, remaining_supply)?;
+
+        if amount > 0 {
+            let mut validator = self.validators.get_mut(val_address)?;
+            let coins = validator.get_mut(pool_address)?.staked.take(amount)?;
+            validator.get_mut(holder_address)?.add_stake(coins)?;
+        }
+
+        self.index_delegation(val_address, holder_address)?;
+        self.update_vp(val_address)?;
+
+        Ok(amount)
+    }
+
+    /// Transfers liquid tokens for the given validator between holders.
+    pub fn transfer_liquid_tokens<A: Into<Amount>>(
+        &mut self,
+        val_address: Address,
+        from: Address,
+        to: Address,
+        tokens: A,
+    ) -> Result<()> {
+        let tokens = tokens.into();
+        self.burn_liquid_tokens(val_address, from, tokens)?;
+
+        let mut balances = self
+            .liquid_token_balances
+            .entry(val_address)?
+            .or_insert_default()?;
+        let mut balance = balances.entry(to)?.or_default()?;
+        *balance = (*balance + tokens)?;
+
+        Ok(())
+    }
+
+    /// Returns the amount of stake currently backing `tokens` liquid tokens
+    /// of the given validator.
+    pub fn liquid_token_value<A: Into<Amount>>(
+        &self,
+        val_address: Address,
+        tokens: A,
+    ) -> Result<Amount> {
+        let supply = *self.liquid_token_supply.get_or_default(val_address)?;
+        if supply == 0 {
+            return Ok(0.into());
+        }
+        let pool_staked = self.liquid_pool_staked(val_address)?;
+
+        ((pool_staked * tokens.into()) / Decimal::from(supply))?.amount()
+    }
+
+    fn burn_liquid_tokens(
+        &mut self,
+        val_address: Address,
+        holder_address: Address,
+        tokens: Amount,
+    ) -> Result<()> {
+        if tokens == 0 {
+            return Err(Error::Coins("Amount must be positive".into()));
+        }
+
+        let mut balances = self
+            .liquid_token_balances
+            .get_mut(val_address)?
+            .ok_or_else(|| Error::Coins("No liquid tokens for validator".into()))?;
+        let mut balance = balances
+            .get_mut(holder_address)?
+            .ok_or_else(|| Error::Coins("Insufficient liquid token balance".into()))?;
+        if *balance < tokens {
+            return Err(Error::Coins("Insufficient liquid token balance".into()));
+        }
+        *balance = (*balance - tokens)?;
+        let empty = *balance == 0;
+        drop(balance);
+
+        if empty {
+            balances.remove(holder_address)?;
+        }
+
+        Ok(())
+    }
+
+    fn liquid_pool_staked(&self, val_address: Address) -> Result<Decimal> {
+        let validator = self.validators.get(val_address)?;
+        let pool = validator.get(liquid_pool_address(val_address))?;
+
+        Ok(pool.staked.shares)
+    }
+
+    /// Restakes staking-denom rewards earned by the liquid pool, so they
+    /// accrue to token holders through the token's redemption value.
+    ///
+    /// Rewards in other denoms cannot be staked, so they are paid out to the
+    /// holders in proportion to their tokens and can be claimed from their own
+    /// delegations to the validator. Any remainder from rounding stays in the
+    /// pool until the next payout.
+    fn compound_liquid_pool(&mut self, val_address: Address) -> Result<()> {
+        let pool_address = liquid_pool_address(val_address);
+        let other_rewards: Vec<(u8, Amount)> = {
+            let mut validator = self.validators.get_mut(val_address)?;
+            let mut pool = validator.get_mut(pool_address)?;
+            let rewards: Amount = Balance::<S, Amount>::balance(&pool.liquid)?;
+            if rewards > 0 {
+                pool.liquid.deduct(rewards, S::INDEX)?;
+                pool.add_stake(Coin::from_existing(rewards))?;
+            }
+
+            pool.liquid
+                .amounts()?
+                .into_iter()
+                .filter(|(denom, amount)| *denom != S::INDEX && *amount > 0)
+                .collect()
+        };
+
+        let supply = *self.liquid_token_supply.get_or_default(val_address)?;
+        if other_rewards.is_empty() || supply == 0 {
+            return Ok(());
+        }
+
+        let holders: Vec<(Address, Amount)> = match self.liquid_token_balances.get(val_address)? {
+            Some(balances) => balances
+                .iter()?
+                .map(|entry| {
+                    let (holder, tokens) = entry?;
+                    Ok((*holder, *tokens))
+                })
+                .collect::<Result<_>>()?,
+            None => return Ok(()),
+        };
+
+        {
+            let mut validator = self.validators.get_mut(val_address)?;
+            for (denom, amount) in other_rewards {
+                let mut paid: Amount = 0.into();
+                for (holder, tokens) in holders.iter() {
+                    let share =
+                        ((Decimal::from(amount) * *tokens) / Decimal::from(supply))?.amount()?;
+                    if share == 0 {
+                        continue;
+                    }
+                    validator.get_mut(*holder)?.give((denom, share))?;
+                    paid = (paid + share)?;
+                }
+                validator.get_mut(pool_address)?.deduct(paid, denom)?;
+            }
+        }
+
+        for (holder, _) in holders {
+            self.index_delegation(val_address, holder)?;
+        }
+
+        Ok(())
+    }
+}
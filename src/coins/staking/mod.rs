@@ -3,17 +3,20 @@ use super::{Address, Amount, Balance, Coin, Decimal, Give, Pool, Symbol, Version
 use crate::abci::{BeginBlock, EndBlock};
 use crate::collections::{Deque, Entry, EntryMap, Map};
 use crate::context::GetContext;
-use crate::encoding::{Decode, Encode};
+use crate::describe::{Builder, Describe, Descriptor};
+use crate::encoding::{Decode, Encode, Terminated};
 use crate::migrate::{Migrate, MigrateFrom};
 use crate::orga;
 use crate::plugins::{BeginBlockCtx, EndBlockCtx, Validators};
 use crate::plugins::{Paid, Signer, Time};
+use crate::query::Query;
 use crate::state::State;
+use crate::store::Store;
 use crate::{Error, Result};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::convert::TryInto;
-use tendermint_proto::v0_34::abci::EvidenceType;
+use tendermint_proto::v0_34::abci::{Evidence, EvidenceType};
 
 mod delegator;
 pub use delegator::*;
@@ -21,13 +24,16 @@ pub use delegator::*;
 mod validator;
 pub use validator::*;
 
+mod liquid;
+pub use liquid::liquid_pool_address;
+
 // #[cfg(test)]
 // pub const UNBONDING_SECONDS: u64 = 10; // 10 seconds
 // #[cfg(not(test))]
 pub const UNBONDING_SECONDS: u64 = 60 * 60 * 24 * 14; // 2 weeks
 const EDIT_INTERVAL_SECONDS: u64 = 60 * 60 * 24; // 1 day
 
-#[orga(version = 1)]
+#[orga(version = 3)]
 pub struct Staking<S: Symbol> {
     validators: Pool<Address, Validator<S>, S>,
     pub min_self_delegation_min: u64,
@@ -47,6 +53,21 @@ pub struct Staking<S: Symbol> {
     unbonding_delegation_queue: Deque<UnbondingDelegationEntry>,
     redelegation_queue: Deque<RedelegationEntry>,
     delegation_index: Map<Address, Map<Address, ()>>,
+
+    #[orga(version(V2, V3))]
+    liquid_token_supply: Map<Address, Amount>,
+    #[orga(version(V2, V3))]
+    liquid_token_balances: Map<Address, Map<Address, Amount>>,
+
+    #[orga(version(V3))]
+    pub max_evidence_age_blocks: u64,
+    #[orga(version(V3))]
+    pub max_evidence_age_seconds: u64,
+    #[orga(version(V3))]
+    slash_history: Map<Address, Deque<SlashRecord>>,
+    #[orga(version(V3))]
+    #[state(skip)]
+    current_height: u64,
 }
 
 impl<S: Symbol> MigrateFrom<StakingV0<S>> for StakingV1<S> {
@@ -55,6 +76,121 @@ impl<S: Symbol> MigrateFrom<StakingV0<S>> for StakingV1<S> {
     }
 }
 
+impl<S: Symbol> MigrateFrom<StakingV1<S>> for StakingV2<S> {
+    fn migrate_from(value: StakingV1<S>) -> Result<Self> {
+        Ok(Self {
+            validators: value.validators,
+            min_self_delegation_min: value.min_self_delegation_min,
+            consensus_keys: value.consensus_keys,
+            last_signed_block: value.last_signed_block,
+            validators_by_power: value.validators_by_power,
+            last_validator_powers: value.last_validator_powers,
+            max_validators: value.max_validators,
+            last_indexed_power: value.last_indexed_power,
+            address_for_tm_hash: value.address_for_tm_hash,
+            unbonding_seconds: value.unbonding_seconds,
+            max_offline_blocks: value.max_offline_blocks,
+            slash_fraction_double_sign: value.slash_fraction_double_sign,
+            slash_fraction_downtime: value.slash_fraction_downtime,
+            downtime_jail_seconds: value.downtime_jail_seconds,
+            validator_queue: value.validator_queue,
+            unbonding_delegation_queue: value.unbonding_delegation_queue,
+            redelegation_queue: value.redelegation_queue,
+            delegation_index: value.delegation_index,
+            liquid_token_supply: Default::default(),
+            liquid_token_balances: Default::default(),
+        })
+    }
+}
+
+impl<S: Symbol> MigrateFrom<StakingV2<S>> for StakingV3<S> {
+    fn migrate_from(value: StakingV2<S>) -> Result<Self> {
+        Ok(Self {
+            validators: value.validators,
+            min_self_delegation_min: value.min_self_delegation_min,
+            consensus_keys: value.consensus_keys,
+            last_signed_block: value.last_signed_block,
+            validators_by_power: value.validators_by_power,
+            last_validator_powers: value.last_validator_powers,
+            max_validators: value.max_validators,
+            last_indexed_power: value.last_indexed_power,
+            address_for_tm_hash: value.address_for_tm_hash,
+            unbonding_seconds: value.unbonding_seconds,
+            max_offline_blocks: value.max_offline_blocks,
+            slash_fraction_double_sign: value.slash_fraction_double_sign,
+            slash_fraction_downtime: value.slash_fraction_downtime,
+            downtime_jail_seconds: value.downtime_jail_seconds,
+            validator_queue: value.validator_queue,
+            unbonding_delegation_queue: value.unbonding_delegation_queue,
+            redelegation_queue: value.redelegation_queue,
+            delegation_index: value.delegation_index,
+            liquid_token_supply: value.liquid_token_supply,
+            liquid_token_balances: value.liquid_token_balances,
+            max_evidence_age_blocks: 0,
+            max_evidence_age_seconds: 0,
+            slash_history: Default::default(),
+            current_height: 0,
+        })
+    }
+}
+
+/// The number of slashes kept in each validator's slash history, after which
+/// the oldest records are dropped.
+pub const MAX_SLASH_HISTORY: u64 = 100;
+
+/// The kind of fault a validator was slashed for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+pub enum SlashReason {
+    #[default]
+    Downtime,
+    DoubleSign,
+    LightClientAttack,
+}
+
+impl Terminated for SlashReason {}
+
+impl State for SlashReason {
+    fn attach(&mut self, _store: Store) -> Result<()> {
+        Ok(())
+    }
+
+    fn flush<W: std::io::Write>(self, out: &mut W) -> Result<()> {
+        Ok(self.encode_into(out)?)
+    }
+
+    fn load(_store: Store, bytes: &mut &[u8]) -> Result<Self> {
+        Ok(Self::decode(bytes)?)
+    }
+}
+
+impl Migrate for SlashReason {}
+
+impl Query for SlashReason {
+    type Query = ();
+
+    fn query(&self, _: ()) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Describe for SlashReason {
+    fn describe() -> Descriptor {
+        Builder::new::<Self>().build()
+    }
+}
+
+/// A record of a single slash applied to a validator.
+#[orga]
+#[derive(Clone, Debug)]
+pub struct SlashRecord {
+    pub height: u64,
+    pub seconds: i64,
+    pub fraction: Decimal,
+    pub reason: SlashReason,
+    /// The amount of bonded stake burned from the validator's delegators.
+    pub amount_burned: Amount,
+}
+
 #[derive(Entry, Clone, Serialize, Deserialize, State, Migrate)]
 struct ValidatorQueueEntry {
     #[key]
@@ -65,17 +201,7 @@ struct ValidatorQueueEntry {
 
 impl EntryMap<ValidatorQueueEntry> {
     fn remove_by_address(&mut self, address: Address) -> Result<()> {
-        let entries: Vec<Result<_>> = self.iter()?.collect();
-        for res in entries {
-            let entry = res?;
-            if entry.address_bytes == address.bytes() {
-                self.delete(ValidatorQueueEntry {
-                    start_seconds: entry.start_seconds,
-                    address_bytes: entry.address_bytes,
-                })?;
-            }
-        }
-        Ok(())
+        self.retain(|entry| Ok(entry.address_bytes != address.bytes()))
     }
 }
 
@@ -116,6 +242,8 @@ impl<S: Symbol> EndBlock for Staking<S> {
 
 impl<S: Symbol> BeginBlock for Staking<S> {
     fn begin_block(&mut self, ctx: &BeginBlockCtx) -> Result<()> {
+        self.current_height = ctx.height;
+
         if let Some(last_commit_info) = &ctx.last_commit_info {
             let height = ctx.height;
             // Update last online height
@@ -162,6 +290,9 @@ impl<S: Symbol> BeginBlock for Staking<S> {
         }
 
         for evidence in &ctx.byzantine_validators {
+            if self.evidence_expired(evidence, ctx.height)? {
+                continue;
+            }
             match &evidence.validator {
                 Some(validator) => {
                     let hash: [u8; 20] = validator.address.to_vec().try_into().map_err(|_| {
@@ -402,20 +533,53 @@ impl<S: Symbol> Staking<S> {
     }
 
     pub fn punish_downtime(&mut self, val_address: Address) -> Result<()> {
-        {
+        let staked_before = self.validators.get(val_address)?.delegators.balance()?;
+        let slashed = {
             let mut validator = self.validators.get_mut(val_address)?;
             validator.jail_for_seconds(self.downtime_jail_seconds)?;
+            let slashed = !validator.tombstoned;
             validator.slash(self.slash_fraction_downtime, true)?;
+            slashed
+        };
+        if slashed {
+            self.record_slash(
+                val_address,
+                self.slash_fraction_downtime,
+                SlashReason::Downtime,
+                staked_before,
+            )?;
         }
         self.update_vp(val_address)
     }
 
     fn punish_double_sign(&mut self, val_address: Address) -> Result<()> {
-        let redelegations = {
+        self.punish_equivocation(val_address, SlashReason::DoubleSign)
+    }
+
+    fn punish_light_client_attack(&mut self, val_address: Address) -> Result<()> {
+        // Currently the same punishment as double sign evidence
+        self.punish_equivocation(val_address, SlashReason::LightClientAttack)
+    }
+
+    fn punish_equivocation(&mut self, val_address: Address, reason: SlashReason) -> Result<()> {
+        let staked_before = self.validators.get(val_address)?.delegators.balance()?;
+        let (redelegations, slashed) = {
             let mut validator = self.validators.get_mut(val_address)?;
             validator.jail_forever();
-            validator.slash(self.slash_fraction_double_sign, false)?
+            let slashed = !validator.tombstoned;
+            (
+                validator.slash(self.slash_fraction_double_sign, false)?,
+                slashed,
+            )
         };
+        if slashed {
+            self.record_slash(
+                val_address,
+                self.slash_fraction_double_sign,
+                reason,
+                staked_before,
+            )?;
+        }
         let multiplier = (Decimal::one() - self.slash_fraction_double_sign)?;
         for entry in redelegations.iter() {
             let del_address = entry.delegator_address;
@@ -428,9 +592,63 @@ impl<S: Symbol> Staking<S> {
         self.update_vp(val_address)
     }
 
-    fn punish_light_client_attack(&mut self, val_address: Address) -> Result<()> {
-        // Currently the same punishment as double sign evidence
-        self.punish_double_sign(val_address)
+    fn record_slash(
+        &mut self,
+        val_address: Address,
+        fraction: Decimal,
+        reason: SlashReason,
+        staked_before: Decimal,
+    ) -> Result<()> {
+        let staked_after = self.validators.get(val_address)?.delegators.balance()?;
+        let record = SlashRecord {
+            height: self.current_height,
+            seconds: self.current_seconds()?,
+            fraction,
+            reason,
+            amount_burned: (staked_before - staked_after)?.amount()?,
+        };
+
+        let mut history = self.slash_history.entry(val_address)?.or_insert_default()?;
+        history.push_back(record)?;
+        while history.len() > MAX_SLASH_HISTORY {
+            history.pop_front()?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if the evidence is older than the configured maximum
+    /// evidence age. A limit of zero disables that check, and evidence is only
+    /// considered expired once it exceeds every enabled limit.
+    fn evidence_expired(&mut self, evidence: &Evidence, height: u64) -> Result<bool> {
+        let max_blocks = self.max_evidence_age_blocks;
+        let max_seconds = self.max_evidence_age_seconds;
+        if max_blocks == 0 && max_seconds == 0 {
+            return Ok(false);
+        }
+
+        if max_blocks > 0 {
+            let age = height.saturating_sub(evidence.height.max(0) as u64);
+            if age <= max_blocks {
+                return Ok(false);
+            }
+        }
+
+        if max_seconds > 0 {
+            match evidence.time.as_ref() {
+                Some(time) => {
+                    let age = self.current_seconds()? - time.seconds;
+                    if age <= max_seconds as i64 {
+                        return Ok(false);
+                    }
+                }
+                // without a timestamp only the block age can be checked
+                None if max_blocks == 0 => return Ok(false),
+                None => {}
+            }
+        }
+
+        Ok(true)
     }
 
     pub fn deduct<A: Into<Amount>>(
@@ -609,6 +827,27 @@ impl<S: Symbol> Staking<S> {
             .collect()
     }
 
+    #[query]
+    pub fn slashes(&self, val_address: Address) -> Result<Vec<SlashRecord>> {
+        match self.slash_history.get(val_address)? {
+            Some(history) => history.iter()?.map(|entry| Ok((*entry?).clone())).collect(),
+            None => Ok(vec![]),
+        }
+    }
+
+    #[query]
+    pub fn liquid_token_balance(&self, val_address: Address, holder: Address) -> Result<Amount> {
+        Ok(match self.liquid_token_balances.get(val_address)? {
+            Some(balances) => *balances.get_or_default(holder)?,
+            None => 0.into(),
+        })
+    }
+
+    #[query]
+    pub fn liquid_token_supply(&self, val_address: Address) -> Result<Amount> {
+        Ok(*self.liquid_token_supply.get_or_default(val_address)?)
+    }
+
     #[call]
     pub fn unbond_self(&mut self, val_address: Address, amount: Amount) -> Result<()> {
         assert_positive(amount)?;
@@ -675,6 +914,36 @@ impl<S: Symbol> Staking<S> {
         Ok(())
     }
 
+    #[call]
+    pub fn tokenize_self(&mut self, val_address: Address, amount: Amount) -> Result<()> {
+        assert_positive(amount)?;
+        let signer = self.signer()?;
+        self.tokenize(val_address, signer, amount)?;
+
+        Ok(())
+    }
+
+    #[call]
+    pub fn redeem_self(&mut self, val_address: Address, tokens: Amount) -> Result<()> {
+        assert_positive(tokens)?;
+        let signer = self.signer()?;
+        self.redeem(val_address, signer, tokens)?;
+
+        Ok(())
+    }
+
+    #[call]
+    pub fn transfer_liquid_tokens_self(
+        &mut self,
+        val_address: Address,
+        to: Address,
+        tokens: Amount,
+    ) -> Result<()> {
+        assert_positive(tokens)?;
+        let signer = self.signer()?;
+        self.transfer_liquid_tokens(val_address, signer, to, tokens)
+    }
+
     #[call]
     pub fn unjail(&mut self) -> Result<()> {
         let signer = self.signer()?;
@@ -742,20 +1011,17 @@ impl<S: Symbol> Staking<S> {
 
     fn process_validator_queue(&mut self) -> Result<()> {
         let now = self.current_seconds()?;
-        // TODO: should be one pass (needs drain iterator)
-        self.validator_queue
-            .iter()?
-            .take_while(|entry| match entry {
-                Ok(entry) => now - entry.start_seconds >= self.unbonding_seconds as i64,
-                Err(_) => true,
-            })
-            .collect::<Vec<_>>()
-            .into_iter()
-            .try_for_each(|entry| {
-                let entry = entry?;
-                self.transition_to_unbonded(entry.address_bytes.into())?;
-                self.validator_queue.delete(entry.clone())
-            })
+        let matured_seconds = now - self.unbonding_seconds as i64;
+        let matured: Vec<ValidatorQueueEntry> = self
+            .validator_queue
+            .drain_range(..=(matured_seconds, [u8::MAX; Address::LENGTH]))
+            .collect::<Result<_>>()?;
+
+        for entry in matured {
+            self.transition_to_unbonded(entry.address_bytes.into())?;
+        }
+
+        Ok(())
     }
 
     fn process_unbonding_delegation_queue(&mut self) -> Result<()> {
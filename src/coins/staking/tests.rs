@@ -1572,3 +1572,161 @@ fn alt_coin_rewards() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(feature = "abci")]
+#[test]
+#[serial]
+fn liquid_tokens_slash_pass_through() -> Result<()> {
+    let mut staking = setup_state()?;
+
+    let val_0 = Address::from_pubkey([0; 33]);
+    let staker = Address::from_pubkey([1; 33]);
+    let carol = Address::from_pubkey([2; 33]);
+
+    staking.declare(
+        val_0,
+        Declaration {
+            consensus_key: [0; 32],
+            commission: Commission {
+                rate: dec!(0.0).into(),
+                max: dec!(1.0).into(),
+                max_change: dec!(0.1).into(),
+            },
+            amount: Amount::new(100),
+            min_self_delegation: 100.into(),
+            validator_info: vec![].try_into()?,
+        },
+        Amount::new(100).into(),
+    )?;
+    staking.delegate(val_0, staker, 100.into())?;
+    staking.end_block_step(&Default::default())?;
+
+    staking
+        .tokenize(val_0, val_0, 1)
+        .expect_err("Should not be able to tokenize below min self-delegation");
+
+    let minted = staking.tokenize(val_0, staker, 100)?;
+    assert_eq!(minted, 100);
+    assert_eq!(staking.delegations(liquid_pool_address(val_0))?.len(), 1);
+    assert_eq!(staking.get(val_0)?.get(staker)?.staked.amount()?, 0);
+    assert_eq!(staking.liquid_token_balance(val_0, staker)?, 100);
+    assert_eq!(staking.liquid_token_supply(val_0)?, 100);
+
+    staking.end_block_step(&Default::default())?;
+    let ctx = Context::resolve::<Validators>().unwrap();
+    assert_eq!(ctx.updates.get(&[0; 32]).unwrap().power, 200);
+
+    staking.transfer_liquid_tokens(val_0, staker, carol, 40)?;
+    staking
+        .transfer_liquid_tokens(val_0, staker, carol, 61)
+        .expect_err("Should not be able to transfer more than balance");
+    assert_eq!(staking.liquid_token_balance(val_0, staker)?, 60);
+    assert_eq!(staking.liquid_token_balance(val_0, carol)?, 40);
+
+    staking.punish_double_sign(val_0)?;
+    staking.end_block_step(&Default::default())?;
+    assert_eq!(staking.liquid_token_value(val_0, 40)?, 20);
+
+    // the pool's rewards in other denoms are paid out to token holders
+    staking.give(Alt::mint(100))?;
+    let pool = liquid_pool_address(val_0);
+    assert_eq!(alt_balance(&staking.get(val_0)?.get(pool)?.liquid), 50);
+
+    let redeemed = staking.redeem(val_0, carol, 40)?;
+    assert_eq!(redeemed, 20);
+    assert_eq!(alt_balance(&staking.get(val_0)?.get(carol)?.liquid), 20);
+    assert_eq!(alt_balance(&staking.get(val_0)?.get(staker)?.liquid), 30);
+    assert_eq!(alt_balance(&staking.get(val_0)?.get(pool)?.liquid), 0);
+    assert_eq!(staking.get(val_0)?.get(carol)?.staked.amount()?, 20);
+    assert_eq!(staking.liquid_token_balance(val_0, carol)?, 0);
+
+    let redeemed = staking.redeem(val_0, staker, 60)?;
+    assert_eq!(redeemed, 30);
+    assert_eq!(staking.get(val_0)?.get(staker)?.staked.amount()?, 30);
+    assert_eq!(staking.liquid_token_supply(val_0)?, 0);
+    assert_eq!(
+        staking
+            .get(val_0)?
+            .get(liquid_pool_address(val_0))?
+            .staked
+            .amount()?,
+        0
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "abci")]
+#[test]
+#[serial]
+fn evidence_age_and_slash_history() -> Result<()> {
+    use tendermint_proto::google::protobuf::Timestamp;
+    use tendermint_proto::v0_34::abci::Validator as TmValidator;
+
+    let mut staking = setup_state()?;
+    staking.max_evidence_age_blocks = 100;
+    staking.max_evidence_age_seconds = 1000;
+
+    let val_0 = Address::from_pubkey([0; 33]);
+    staking.declare(
+        val_0,
+        Declaration {
+            consensus_key: [0; 32],
+            commission: Commission {
+                rate: dec!(0.0).into(),
+                max: dec!(1.0).into(),
+                max_change: dec!(0.1).into(),
+            },
+            amount: Amount::new(100),
+            min_self_delegation: 1.into(),
+            validator_info: vec![].try_into()?,
+        },
+        Amount::new(100).into(),
+    )?;
+    staking.end_block_step(&Default::default())?;
+    Context::add(Time::from_seconds(5000));
+
+    let evidence = |height: i64, seconds: i64| {
+        let mut evidence = Evidence {
+            validator: Some(TmValidator {
+                address: tm_pubkey_hash([0; 32]).unwrap().to_vec().into(),
+                power: 100,
+            }),
+            height,
+            time: Some(Timestamp { seconds, nanos: 0 }),
+            ..Default::default()
+        };
+        evidence.set_type(EvidenceType::DuplicateVote);
+        evidence
+    };
+    let begin_block_ctx = |evidence: Evidence| BeginBlockCtx {
+        hash: vec![],
+        height: 500,
+        header: Default::default(),
+        last_commit_info: None,
+        byzantine_validators: vec![evidence],
+    };
+
+    staking.begin_block(&begin_block_ctx(evidence(1, 0)))?;
+    assert!(!staking.get(val_0)?.tombstoned);
+    assert!(staking.slashes(val_0)?.is_empty());
+
+    // Evidence without a timestamp falls back to its age in blocks
+    let mut untimed = evidence(1, 0);
+    untimed.time = None;
+    staking.begin_block(&begin_block_ctx(untimed))?;
+    assert!(!staking.get(val_0)?.tombstoned);
+
+    // Old in blocks but recent in time, so still within the evidence window
+    staking.begin_block(&begin_block_ctx(evidence(1, 4500)))?;
+    assert!(staking.get(val_0)?.tombstoned);
+
+    let slashes = staking.slashes(val_0)?;
+    assert_eq!(slashes.len(), 1);
+    assert_eq!(slashes[0].height, 500);
+    assert_eq!(slashes[0].seconds, 5000);
+    assert_eq!(slashes[0].reason, SlashReason::DoubleSign);
+    assert_eq!(slashes[0].amount_burned, 50);
+
+    Ok(())
+}
@@ -221,7 +221,8 @@ impl<S: Symbol, T: Symbol> Give<Coin<T>> for Validator<S> {
         let delegator_amount = (coins.amount * (one - self.commission.rate))?.amount()?;
         let validator_amount = (coins.amount * self.commission.rate)?.amount()?;
 
-        self.delegators.give(T::mint(delegator_amount))?;
+        self.delegators
+            .give(Coin::<T>::from_existing(delegator_amount))?;
         self.delegators
             .get_mut(self.address.into())?
             .give((T::INDEX, validator_amount))?;
@@ -0,0 +1,239 @@
+use super::{Amount, Coin, Symbol};
+use crate::collections::Map;
+use crate::context::Context;
+use crate::encoding::LengthVec;
+use crate::orga;
+use crate::{Error, Result};
+use std::collections::BTreeMap;
+
+/// The denomination of a token created by IBC transfers, e.g.
+/// `transfer/channel-0/uatom`.
+pub type IbcDenom = LengthVec<u8, u8>;
+
+/// Tracks the total supply of each denomination: native coins by their
+/// [Symbol] index, and IBC vouchers by their full denom.
+///
+/// The ledger itself is only updated by [Supply::apply], which consumes the
+/// changes recorded in the [SupplyChanges] context while a call or block step
+/// runs (see `plugins::SupplyPlugin`).
+///
+/// Coins minted by the app while initializing the chain, e.g. genesis
+/// balances, are recorded like any other mint.
+#[orga]
+pub struct Supply {
+    totals: Map<u8, Amount>,
+    ibc_totals: Map<IbcDenom, Amount>,
+}
+
+#[orga]
+impl Supply {
+    #[query]
+    pub fn total(&self, denom: u8) -> Result<Amount> {
+        Ok(*self.totals.get_or_default(denom)?)
+    }
+
+    #[query]
+    pub fn totals(&self) -> Result<Vec<(u8, Amount)>> {
+        self.totals
+            .iter()?
+            .map(|entry| {
+                let (denom, amount) = entry?;
+                Ok((*denom, *amount))
+            })
+            .collect()
+    }
+
+    #[query]
+    pub fn ibc_total(&self, denom: IbcDenom) -> Result<Amount> {
+        Ok(*self.ibc_totals.get_or_default(denom)?)
+    }
+
+    pub fn total_of<S: Symbol>(&self) -> Result<Amount> {
+        self.total(S::INDEX)
+    }
+
+    /// Adds `amount` to the recorded supply of `denom` without going through
+    /// the [SupplyChanges] context, e.g. to account for genesis balances.
+    pub fn add_genesis_supply(&mut self, denom: u8, amount: Amount) -> Result<()> {
+        let mut total = self.totals.entry(denom)?.or_default()?;
+        *total = (*total + amount)?;
+
+        Ok(())
+    }
+
+    /// Applies the recorded mints and burns to the ledger.
+    pub fn apply(&mut self, changes: SupplyChanges) -> Result<()> {
+        if changes.overflowed {
+            return Err(Error::Coins("Supply change overflowed".into()));
+        }
+
+        for (denom, change) in changes.changes {
+            let mut total = self.totals.entry(denom)?.or_default()?;
+            *total = change.apply_to(*total, &denom.to_string())?;
+        }
+
+        for (denom, change) in changes.ibc_changes {
+            let name = String::from_utf8_lossy(&denom).to_string();
+            let mut total = self.ibc_totals.entry(denom.try_into()?)?.or_default()?;
+            *total = change.apply_to(*total, &name)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SupplyChange {
+    pub minted: Amount,
+    pub burned: Amount,
+}
+
+impl SupplyChange {
+    fn apply_to(&self, total: Amount, denom: &str) -> Result<Amount> {
+        let increased = (total + self.minted)?;
+        if increased < self.burned {
+            return Err(Error::Coins(format!(
+                "Burned more than the total supply of denom {}",
+                denom
+            )));
+        }
+
+        Ok((increased - self.burned)?)
+    }
+}
+
+/// Context which accumulates supply changes made by [Coin::mint],
+/// [Coin::burn] and IBC transfers until they are applied to a [Supply].
+///
+/// Recording never fails so that minting and burning stay infallible; a
+/// change which overflows is instead reported by [Supply::apply].
+#[derive(Default, Debug)]
+pub struct SupplyChanges {
+    changes: BTreeMap<u8, SupplyChange>,
+    ibc_changes: BTreeMap<Vec<u8>, SupplyChange>,
+    overflowed: bool,
+}
+
+impl SupplyChanges {
+    pub fn get(&self, denom: u8) -> SupplyChange {
+        self.changes.get(&denom).copied().unwrap_or_default()
+    }
+
+    pub fn get_ibc(&self, denom: &[u8]) -> SupplyChange {
+        self.ibc_changes.get(denom).copied().unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.ibc_changes.is_empty() && !self.overflowed
+    }
+
+    /// Returns the active context if `amount` needs to be recorded.
+    fn active<'a>(amount: Amount) -> Option<&'a mut SupplyChanges> {
+        if amount > 0 {
+            Context::resolve::<SupplyChanges>()
+        } else {
+            None
+        }
+    }
+
+    fn record(change: &mut Amount, amount: Amount, overflowed: &mut bool) {
+        match *change + amount {
+            Ok(sum) => *change = sum,
+            Err(_) => *overflowed = true,
+        }
+    }
+}
+
+/// Records newly-issued supply if a [SupplyChanges] context is active.
+pub fn record_mint(denom: u8, amount: Amount) {
+    if let Some(changes) = SupplyChanges::active(amount) {
+        let change = changes.changes.entry(denom).or_default();
+        SupplyChanges::record(&mut change.minted, amount, &mut changes.overflowed);
+    }
+}
+
+/// Records destroyed supply if a [SupplyChanges] context is active.
+pub fn record_burn(denom: u8, amount: Amount) {
+    if let Some(changes) = SupplyChanges::active(amount) {
+        let change = changes.changes.entry(denom).or_default();
+        SupplyChanges::record(&mut change.burned, amount, &mut changes.overflowed);
+    }
+}
+
+/// Records IBC vouchers minted for an incoming transfer if a [SupplyChanges]
+/// context is active.
+pub fn record_ibc_mint(denom: &[u8], amount: Amount) {
+    if let Some(changes) = SupplyChanges::active(amount) {
+        let change = changes.ibc_changes.entry(denom.to_vec()).or_default();
+        SupplyChanges::record(&mut change.minted, amount, &mut changes.overflowed);
+    }
+}
+
+/// Records IBC vouchers burned for an outgoing transfer if a [SupplyChanges]
+/// context is active.
+pub fn record_ibc_burn(denom: &[u8], amount: Amount) {
+    if let Some(changes) = SupplyChanges::active(amount) {
+        let change = changes.ibc_changes.entry(denom.to_vec()).or_default();
+        SupplyChanges::record(&mut change.burned, amount, &mut changes.overflowed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[orga]
+    #[derive(Clone, Debug)]
+    struct Simp;
+    impl Symbol for Simp {
+        const INDEX: u8 = 0;
+        const NAME: &'static str = "SIMP";
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn mint_and_burn() -> Result<()> {
+        let mut supply = Supply::default();
+        Context::add(SupplyChanges::default());
+
+        let _coins = Coin::<Simp>::mint(100);
+        Coin::<Simp>::from_existing(30.into()).burn();
+        let _moved = Coin::<Simp>::from_existing(5.into());
+
+        let changes = std::mem::take(Context::resolve::<SupplyChanges>().unwrap());
+        assert_eq!(
+            changes.get(Simp::INDEX),
+            SupplyChange {
+                minted: 100.into(),
+                burned: 30.into(),
+            }
+        );
+
+        supply.apply(changes)?;
+        assert_eq!(supply.total_of::<Simp>()?, 70);
+
+        record_burn(Simp::INDEX, 71.into());
+        let changes = std::mem::take(Context::resolve::<SupplyChanges>().unwrap());
+        supply
+            .apply(changes)
+            .expect_err("Should not be able to burn more than the supply");
+
+        let denom = b"transfer/channel-0/uatom";
+        record_ibc_mint(denom, 50.into());
+        record_ibc_burn(denom, 20.into());
+        let changes = std::mem::take(Context::resolve::<SupplyChanges>().unwrap());
+        assert_eq!(changes.get(Simp::INDEX), SupplyChange::default());
+        supply.apply(changes)?;
+        assert_eq!(supply.ibc_total(denom.to_vec().try_into()?)?, 30);
+
+        record_ibc_burn(denom, 31.into());
+        let changes = std::mem::take(Context::resolve::<SupplyChanges>().unwrap());
+        supply
+            .apply(changes)
+            .expect_err("Should not be able to burn more than the supply");
+
+        Context::remove::<SupplyChanges>();
+
+        Ok(())
+    }
+}
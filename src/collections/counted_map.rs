@@ -0,0 +1,249 @@
+use serde::Serialize;
+
+use super::map::{ChildMut, Iter as MapIter, Map, ReadOnly, Ref};
+use crate::describe::Describe;
+use crate::encoding::{Decode, Encode, Terminated};
+use crate::migrate::Migrate;
+use crate::orga;
+use crate::query::FieldQuery;
+use crate::state::State;
+use crate::store::Store;
+use crate::Result;
+use std::ops::{Deref, DerefMut, RangeBounds};
+
+/// A map collection which also keeps track of its number of entries, so its
+/// length can be read without iterating over the store.
+///
+/// The length is stored in the encoding of the map itself, while the entries
+/// are stored in the same way as in [Map].
+#[derive(FieldQuery, Encode, Decode)]
+pub struct CountedMap<K, V> {
+    len: u64,
+    map: Map<K, V>,
+}
+
+impl<K, V> Describe for CountedMap<K, V>
+where
+    K: Encode + Terminated + Clone + 'static + Describe,
+    V: State + Describe,
+{
+    fn describe() -> crate::describe::Descriptor {
+        use crate::describe::Builder;
+        Builder::new::<Self>()
+            .dynamic_child::<K, V>(|mut query_bytes| {
+                query_bytes.extend_from_slice(&[129]);
+                query_bytes
+            })
+            .build()
+    }
+}
+
+impl<K, V> Default for CountedMap<K, V> {
+    fn default() -> Self {
+        Self {
+            len: 0,
+            map: Map::default(),
+        }
+    }
+}
+
+impl<K, V> std::fmt::Debug for CountedMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CountedMap")
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl<K, V> CountedMap<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K, V> State for CountedMap<K, V>
+where
+    K: Encode + Terminated + 'static,
+    V: State,
+{
+    fn attach(&mut self, store: Store) -> Result<()> {
+        self.map.attach(store)
+    }
+
+    fn flush<W: std::io::Write>(self, out: &mut W) -> Result<()> {
+        self.len.flush(out)?;
+        self.map.flush(out)
+    }
+
+    fn load(store: Store, bytes: &mut &[u8]) -> Result<Self> {
+        let mut value = Self {
+            len: u64::load(store.clone(), bytes)?,
+            map: Map::load(store.clone(), bytes)?,
+        };
+
+        value.attach(store)?;
+
+        Ok(value)
+    }
+}
+
+impl<K, V> Migrate for CountedMap<K, V>
+where
+    K: Encode + Decode + State + Terminated + Clone + Send + Sync + Migrate,
+    V: State + Migrate,
+{
+    fn migrate(src: Store, dest: Store, bytes: &mut &[u8]) -> Result<Self> {
+        Ok(Self {
+            len: u64::migrate(Store::default(), Store::default(), bytes)?,
+            map: Map::migrate(src, dest, bytes)?,
+        })
+    }
+}
+
+impl<K: Serialize, V: Serialize> Serialize for CountedMap<K, V>
+where
+    K: Encode + Decode + Terminated + Clone + 'static,
+    V: State,
+{
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        self.map.serialize(serializer)
+    }
+}
+
+#[orga]
+impl<K, V> CountedMap<K, V>
+where
+    K: Encode + Terminated + Send + Sync + 'static,
+    V: State,
+{
+    #[query]
+    pub fn contains_key(&self, key: K) -> Result<bool> {
+        self.map.contains_key(key)
+    }
+
+    #[query]
+    pub fn get(&self, key: K) -> Result<Option<Ref<V>>> {
+        self.map.get(key)
+    }
+
+    #[query]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    #[query]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<K, V> CountedMap<K, V>
+where
+    K: Encode + Terminated + Clone + Send + Sync + 'static,
+    V: State,
+{
+    /// Inserts a value, replacing any existing value for the key.
+    pub fn insert(&mut self, key: K, value: V) -> Result<()> {
+        if !self.map.contains_key(key.clone())? {
+            self.len += 1;
+        }
+
+        self.map.insert(key, value)
+    }
+
+    /// Gets a mutable reference to the value for the given key.
+    pub fn get_mut(&mut self, key: K) -> Result<Option<ValueMut<K, V>>> {
+        Ok(self.map.get_mut(key)?.map(ValueMut))
+    }
+
+    /// Removes the value at the given key, if any.
+    pub fn remove(&mut self, key: K) -> Result<Option<ReadOnly<V>>> {
+        let removed = self.map.remove(key)?;
+        if removed.is_some() {
+            self.len -= 1;
+        }
+
+        Ok(removed)
+    }
+}
+
+impl<K, V> CountedMap<K, V>
+where
+    K: Encode + Terminated + Send + Sync + 'static,
+    V: State + Default,
+{
+    pub fn get_or_default(&self, key: K) -> Result<Ref<V>> {
+        self.map.get_or_default(key)
+    }
+}
+
+impl<'a, K, V> CountedMap<K, V>
+where
+    K: Encode + Decode + Terminated + Clone + 'static,
+    V: State,
+{
+    pub fn iter(&'a self) -> Result<MapIter<'a, K, V>> {
+        self.map.iter()
+    }
+
+    pub fn range<B: RangeBounds<K>>(&'a self, range: B) -> Result<MapIter<'a, K, V>> {
+        self.map.range(range)
+    }
+}
+
+/// A mutable reference to a value in a [CountedMap].
+///
+/// Unlike [ChildMut], it cannot remove the value, since removals have to go
+/// through [CountedMap::remove] to keep the length accurate.
+pub struct ValueMut<'a, K, V>(ChildMut<'a, K, V>);
+
+impl<'a, K: Encode, V> Deref for ValueMut<'a, K, V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        &self.0
+    }
+}
+
+impl<'a, K: Clone + Encode, V> DerefMut for ValueMut<'a, K, V> {
+    fn deref_mut(&mut self) -> &mut V {
+        &mut self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{MapStore, Shared};
+
+    #[test]
+    fn tracks_len() -> Result<()> {
+        let store = Store::new(Shared::new(MapStore::new()).into());
+        let mut map: CountedMap<u32, u32> = CountedMap::new();
+        map.attach(store.clone())?;
+
+        map.insert(1, 10)?;
+        map.insert(2, 20)?;
+        map.insert(1, 11)?;
+        assert_eq!(map.len(), 2);
+
+        assert!(map.remove(3)?.is_none());
+        assert_eq!(*map.remove(2)?.unwrap(), 20);
+        assert_eq!(map.len(), 1);
+
+        *map.get_mut(1)?.unwrap() = 12;
+        assert_eq!(map.len(), 1);
+
+        let mut bytes = vec![];
+        map.flush(&mut bytes)?;
+
+        let map: CountedMap<u32, u32> = CountedMap::load(store, &mut bytes.as_slice())?;
+        assert_eq!(map.len(), 1);
+        assert_eq!(*map.get(1)?.unwrap(), 12);
+
+        Ok(())
+    }
+}
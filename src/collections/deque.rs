@@ -11,6 +11,7 @@ use crate::query::FieldQuery;
 use crate::state::State;
 use crate::store::Store;
 use crate::Result;
+use std::ops::{Bound, RangeBounds};
 
 #[derive(FieldQuery, Encode, Decode)]
 pub struct Deque<T> {
@@ -307,6 +308,242 @@ impl<T: State> Deque<T> {
     }
 }
 
+impl<'a, T: State> Deque<T> {
+    /// Returns an iterator which pops and yields values from the front of the
+    /// deque for as long as `pred` returns `true`, e.g. to process the matured
+    /// entries of a time-ordered queue.
+    pub fn drain_front_while<F>(&'a mut self, pred: F) -> DrainFrontWhile<'a, T, F>
+    where
+        F: FnMut(&T) -> Result<bool>,
+    {
+        DrainFrontWhile {
+            deque: self,
+            pred,
+            done: false,
+        }
+    }
+
+    /// Returns an iterator which removes and yields the values at the given
+    /// range of indexes, in order.
+    ///
+    /// Values are removed from the store as the iterator advances. When it is
+    /// dropped, any values left in the range are removed and the gap is closed
+    /// by shifting whichever side of the range is shorter, so draining `k`
+    /// values costs `O(k + min(start, len - end))` store operations. Store
+    /// errors hit while closing the gap on drop cannot be returned, so they
+    /// are logged instead.
+    pub fn drain_range<B: RangeBounds<u64>>(&'a mut self, range: B) -> DrainRange<'a, T> {
+        let len = self.len();
+        let start = match range.start_bound() {
+            Bound::Included(start) => *start,
+            Bound::Excluded(start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        }
+        .min(len);
+        let end = match range.end_bound() {
+            Bound::Included(end) => end.saturating_add(1),
+            Bound::Excluded(end) => *end,
+            Bound::Unbounded => len,
+        }
+        .clamp(start, len);
+
+        DrainRange {
+            deque: self,
+            start,
+            next: start,
+            end,
+        }
+    }
+
+    /// Returns an iterator which removes and yields every value for which
+    /// `pred` returns `true`, in order. The remaining values keep their
+    /// relative order.
+    ///
+    /// Each value is visited once and retained values are moved down into the
+    /// space left by removed ones as the iterator advances. If the iterator is
+    /// dropped early, the unvisited values are kept and the gap is closed by
+    /// shifting whichever side of it is shorter. As with [Deque::drain_range],
+    /// store errors hit while closing the gap on drop are logged.
+    pub fn extract_if<F>(&'a mut self, pred: F) -> ExtractIf<'a, T, F>
+    where
+        F: FnMut(&T) -> Result<bool>,
+    {
+        let len = self.len();
+        ExtractIf {
+            deque: self,
+            pred,
+            read: 0,
+            write: 0,
+            len,
+        }
+    }
+
+    /// Removes the value at the given index, leaving a hole to be closed by
+    /// [Deque::close_gap].
+    fn take_at(&mut self, index: u64) -> Result<ReadOnly<T>> {
+        self.map
+            .remove(index + self.meta.head)?
+            .ok_or_else(|| crate::Error::App("Deque index out of bounds".into()))
+    }
+
+    fn move_value(&mut self, from: u64, to: u64) -> Result<()> {
+        let value = self.take_at(from)?;
+        self.map.insert(to + self.meta.head, value.into_inner())
+    }
+
+    /// Closes the gap of already-removed values between the `start` and
+    /// `end` indexes, shifting the shorter side of the deque.
+    fn close_gap(&mut self, start: u64, end: u64) -> Result<()> {
+        let gap = end - start;
+        if gap == 0 {
+            return Ok(());
+        }
+
+        let len = self.len();
+        if start < len - end {
+            for index in (0..start).rev() {
+                self.move_value(index, index + gap)?;
+            }
+            self.meta.head += gap;
+        } else {
+            for index in end..len {
+                self.move_value(index, index - gap)?;
+            }
+            self.meta.tail -= gap;
+        }
+
+        Ok(())
+    }
+}
+
+/// An iterator which removes a range of values from a [Deque], created by
+/// [Deque::drain_range].
+pub struct DrainRange<'a, T: State> {
+    deque: &'a mut Deque<T>,
+    start: u64,
+    next: u64,
+    end: u64,
+}
+
+impl<'a, T: State> Iterator for DrainRange<'a, T> {
+    type Item = Result<ReadOnly<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.end {
+            return None;
+        }
+
+        let index = self.next;
+        self.next += 1;
+        Some(self.deque.take_at(index))
+    }
+}
+
+impl<'a, T: State> Drop for DrainRange<'a, T> {
+    fn drop(&mut self) {
+        let res = (self.next..self.end)
+            .try_for_each(|index| self.deque.take_at(index).map(drop))
+            .and_then(|_| self.deque.close_gap(self.start, self.end));
+        if let Err(err) = res {
+            log::error!("Failed to close drained range of deque: {}", err);
+        }
+    }
+}
+
+/// An iterator which removes the values of a [Deque] matching a predicate,
+/// created by [Deque::extract_if].
+pub struct ExtractIf<'a, T: State, F> {
+    deque: &'a mut Deque<T>,
+    pred: F,
+    read: u64,
+    write: u64,
+    len: u64,
+}
+
+impl<'a, T, F> Iterator for ExtractIf<'a, T, F>
+where
+    T: State,
+    F: FnMut(&T) -> Result<bool>,
+{
+    type Item = Result<ReadOnly<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.read < self.len {
+            let index = self.read;
+            self.read += 1;
+
+            let matches = match self.deque.get(index) {
+                Ok(Some(value)) => (self.pred)(&value),
+                Ok(None) => Err(crate::Error::App("Deque index out of bounds".into())),
+                Err(err) => Err(err),
+            };
+            if let Ok(true) = matches {
+                return Some(self.deque.take_at(index));
+            }
+
+            let write = self.write;
+            self.write += 1;
+            if write != index {
+                if let Err(err) = self.deque.move_value(index, write) {
+                    return Some(Err(err));
+                }
+            }
+            if let Err(err) = matches {
+                return Some(Err(err));
+            }
+        }
+
+        None
+    }
+}
+
+impl<'a, T: State, F> Drop for ExtractIf<'a, T, F> {
+    fn drop(&mut self) {
+        if let Err(err) = self.deque.close_gap(self.write, self.read) {
+            log::error!("Failed to close extracted values of deque: {}", err);
+        }
+    }
+}
+
+/// An iterator which pops values from the front of a [Deque], created by
+/// [Deque::drain_front_while].
+pub struct DrainFrontWhile<'a, T, F> {
+    deque: &'a mut Deque<T>,
+    pred: F,
+    done: bool,
+}
+
+impl<'a, T, F> Iterator for DrainFrontWhile<'a, T, F>
+where
+    T: State,
+    F: FnMut(&T) -> Result<bool>,
+{
+    type Item = Result<ReadOnly<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let matches = match self.deque.front() {
+            Ok(Some(value)) => (self.pred)(&*value),
+            Ok(None) => Ok(false),
+            Err(err) => Err(err),
+        };
+        match matches {
+            Ok(true) => self.deque.pop_front().transpose(),
+            Ok(false) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
 impl<T: Migrate> Migrate for Deque<T> {
     fn migrate(src: Store, dest: Store, bytes: &mut &[u8]) -> Result<Self> {
         Ok(Self {
@@ -681,4 +918,65 @@ mod test {
         let mut iter = deque.iter().unwrap();
         assert!(iter.next().is_none());
     }
+
+    #[test]
+    fn deque_drain_front_while() {
+        let mut deque: Deque<u32> = Deque::new();
+        for i in 0..5 {
+            deque.push_back(i).unwrap();
+        }
+
+        let drained: Vec<u32> = deque
+            .drain_front_while(|x| Ok(*x < 3))
+            .map(|x| *x.unwrap())
+            .collect();
+        assert_eq!(drained, vec![0, 1, 2]);
+        assert_eq!(deque.len(), 2);
+        assert_eq!(*deque.front().unwrap().unwrap(), 3);
+    }
+
+    #[test]
+    fn deque_drain_range_and_extract_if() {
+        let mut deque: Deque<u32> = Deque::new();
+        for i in 0..6 {
+            deque.push_back(i).unwrap();
+        }
+
+        let drained: Vec<u32> = deque.drain_range(1..3).map(|x| *x.unwrap()).collect();
+        assert_eq!(drained, vec![1, 2]);
+
+        let extracted: Vec<u32> = deque
+            .extract_if(|x| Ok(x % 2 == 1))
+            .map(|x| *x.unwrap())
+            .collect();
+        assert_eq!(extracted, vec![3, 5]);
+
+        let remaining: Vec<u32> = deque.iter().unwrap().map(|x| *x.unwrap()).collect();
+        assert_eq!(remaining, vec![0, 4]);
+        assert_eq!(deque.len(), 2);
+    }
+
+    #[test]
+    fn deque_drain_range_and_extract_if_dropped_early() {
+        let mut deque: Deque<u32> = Deque::new();
+        for i in 0..10 {
+            deque.push_back(i).unwrap();
+        }
+
+        // closes the gap by shifting the values before the range
+        let mut drain = deque.drain_range(2..5);
+        assert_eq!(*drain.next().unwrap().unwrap(), 2);
+        drop(drain);
+        let remaining: Vec<u32> = deque.iter().unwrap().map(|x| *x.unwrap()).collect();
+        assert_eq!(remaining, vec![0, 1, 5, 6, 7, 8, 9]);
+
+        let mut extract = deque.extract_if(|x| Ok(x % 2 == 1));
+        assert_eq!(*extract.next().unwrap().unwrap(), 1);
+        assert_eq!(*extract.next().unwrap().unwrap(), 5);
+        drop(extract);
+        let remaining: Vec<u32> = deque.iter().unwrap().map(|x| *x.unwrap()).collect();
+        assert_eq!(remaining, vec![0, 6, 7, 8, 9]);
+        assert_eq!(*deque.front().unwrap().unwrap(), 0);
+        assert_eq!(*deque.back().unwrap().unwrap(), 9);
+    }
 }
@@ -3,6 +3,7 @@ use serde::Serialize;
 use super::map::Iter as MapIter;
 use super::map::Map;
 use super::map::ReadOnly;
+use super::map::{Drain as MapDrain, ExtractIf as MapExtractIf};
 
 use crate::describe::Describe;
 use crate::encoding::{Decode, Encode, Terminated};
@@ -193,6 +194,100 @@ where
     }
 }
 
+impl<'a, T: Entry> EntryMap<T>
+where
+    T::Key: Decode + Encode + Terminated + Clone,
+    T::Value: State + Clone,
+{
+    /// Returns an iterator which removes and yields each entry with a key in
+    /// the given range, in key order. See [Map::drain_range].
+    pub fn drain_range<B: RangeBounds<T::Key>>(&'a mut self, range: B) -> Drain<'a, T> {
+        Drain {
+            map_drain: self.map.drain_range(range),
+        }
+    }
+
+    /// Returns an iterator which removes and yields each entry in the given
+    /// range for which `pred` returns `true`. See [Map::extract_if].
+    pub fn extract_if<B, F>(&'a mut self, range: B, mut pred: F) -> ExtractIf<'a, T>
+    where
+        B: RangeBounds<T::Key>,
+        F: FnMut(&T) -> Result<bool> + 'a,
+    {
+        let pred: EntryPredicate<'a, T> =
+            Box::new(move |key, value| pred(&T::from_entry((key.clone(), value.clone()))));
+
+        ExtractIf {
+            map_extract: self.map.extract_if(range, pred),
+        }
+    }
+
+    /// Removes every entry for which `pred` returns `false`.
+    pub fn retain<F>(&mut self, mut pred: F) -> Result<()>
+    where
+        F: FnMut(&T) -> Result<bool>,
+    {
+        for entry in self.extract_if(.., |entry| Ok(!pred(entry)?)) {
+            entry?;
+        }
+
+        Ok(())
+    }
+}
+
+type EntryPredicate<'a, T> =
+    Box<dyn FnMut(&<T as Entry>::Key, &<T as Entry>::Value) -> Result<bool> + 'a>;
+
+/// An iterator which removes entries from an [EntryMap], created by
+/// [EntryMap::drain_range].
+pub struct Drain<'a, T: Entry>
+where
+    T::Key: Decode + Encode + Terminated + Clone,
+    T::Value: State,
+{
+    map_drain: MapDrain<'a, T::Key, T::Value>,
+}
+
+impl<'a, T: Entry> Iterator for Drain<'a, T>
+where
+    T::Key: Decode + Encode + Terminated + Clone,
+    T::Value: State,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.map_drain.next().map(|entry| {
+            let (key, value) = entry?;
+            Ok(T::from_entry((key, value.into_inner())))
+        })
+    }
+}
+
+/// An iterator which removes entries matching a predicate from an
+/// [EntryMap], created by [EntryMap::extract_if].
+pub struct ExtractIf<'a, T: Entry>
+where
+    T::Key: Decode + Encode + Terminated + Clone,
+    T::Value: State,
+{
+    map_extract: MapExtractIf<'a, T::Key, T::Value, EntryPredicate<'a, T>>,
+}
+
+impl<'a, T: Entry> Iterator for ExtractIf<'a, T>
+where
+    T::Key: Decode + Encode + Terminated + Clone,
+    T::Value: State,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.map_extract.next().map(|entry| {
+            let (key, value) = entry?;
+            Ok(T::from_entry((key, value.into_inner())))
+        })
+    }
+}
+
 impl<T> Migrate for EntryMap<T>
 where
     T: Entry,
@@ -596,4 +691,33 @@ mod tests {
 
         assert!(result);
     }
+
+    #[test]
+    fn drain_and_retain() {
+        let (_store, mut entry_map) = setup();
+
+        for key in 0..6 {
+            entry_map
+                .insert(MapEntry {
+                    key,
+                    value: key * 2,
+                })
+                .unwrap();
+        }
+
+        let drained: Vec<MapEntry> = entry_map.drain_range(..2).collect::<Result<_>>().unwrap();
+        assert_eq!(
+            drained,
+            vec![MapEntry { key: 0, value: 0 }, MapEntry { key: 1, value: 2 }]
+        );
+
+        entry_map.retain(|entry| Ok(entry.key != 4)).unwrap();
+
+        let remaining: Vec<u32> = entry_map
+            .iter()
+            .unwrap()
+            .map(|entry| entry.unwrap().key)
+            .collect();
+        assert_eq!(remaining, vec![2, 3, 5]);
+    }
 }
@@ -0,0 +1,603 @@
+use serde::Serialize;
+
+use super::map::{Iter as MapIter, Map, ReadOnly, Ref};
+use crate::call::FieldCall;
+use crate::describe::Describe;
+use crate::encoding::{Decode, Encode, Terminated};
+use crate::migrate::Migrate;
+use crate::orga;
+use crate::query::FieldQuery;
+use crate::state::State;
+use crate::store::Store;
+use crate::{Error, Result};
+use std::ops::{Bound, RangeBounds};
+
+/// Whether a secondary index maps each index key to at most one primary key,
+/// or to any number of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexKind {
+    Unique,
+    Multi,
+}
+
+/// A secondary index declared by an [Indexed] value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndexDef {
+    pub name: &'static str,
+    pub kind: IndexKind,
+}
+
+impl IndexDef {
+    pub const fn unique(name: &'static str) -> Self {
+        Self {
+            name,
+            kind: IndexKind::Unique,
+        }
+    }
+
+    pub const fn multi(name: &'static str) -> Self {
+        Self {
+            name,
+            kind: IndexKind::Multi,
+        }
+    }
+}
+
+/// A trait for values which declare the secondary indexes an [IndexedMap]
+/// keeps for them.
+///
+/// Index keys are compared by their length, then by their encoding, so types
+/// with a fixed-length encoding which preserves their ordering (e.g.
+/// big-endian integers, fixed-size arrays) can be queried by range.
+pub trait Indexed {
+    /// The indexes of the value. Each index is identified by its position in
+    /// this slice, and at most 255 indexes may be declared.
+    const INDEXES: &'static [IndexDef];
+
+    /// Returns the encoded key of the value for each index in `INDEXES`, in
+    /// the same order. A `None` key leaves the value out of that index.
+    fn index_keys(&self) -> Result<Vec<Option<Vec<u8>>>>;
+}
+
+/// The raw key of an entry in the index map: the index id, followed by the
+/// length of the encoded index key as a big-endian `u32` and the encoded index
+/// key itself, followed by the encoded primary key for multi indexes.
+///
+/// The length prefix keeps an index key from being confused with a longer
+/// index key which starts with the same bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct IndexKey(Vec<u8>);
+
+impl Encode for IndexKey {
+    fn encode_into<W: std::io::Write>(&self, dest: &mut W) -> ed::Result<()> {
+        dest.write_all(self.0.as_slice())?;
+        Ok(())
+    }
+
+    fn encoding_length(&self) -> ed::Result<usize> {
+        Ok(self.0.len())
+    }
+}
+
+impl Decode for IndexKey {
+    fn decode<R: std::io::Read>(mut input: R) -> ed::Result<Self> {
+        let mut bytes = vec![];
+        input.read_to_end(&mut bytes)?;
+        Ok(Self(bytes))
+    }
+}
+
+// Index keys are only ever decoded from a complete map key, so they may
+// consume the rest of their input.
+impl Terminated for IndexKey {}
+
+impl State for IndexKey {
+    fn attach(&mut self, _: Store) -> Result<()> {
+        Ok(())
+    }
+
+    fn flush<W: std::io::Write>(self, out: &mut W) -> Result<()> {
+        Ok(self.encode_into(out)?)
+    }
+
+    fn load(_store: Store, bytes: &mut &[u8]) -> Result<Self> {
+        Ok(Self::decode(bytes)?)
+    }
+}
+
+impl Migrate for IndexKey {}
+
+/// A map collection which also maintains secondary indexes over its values.
+///
+/// The indexes are declared by the value type through [Indexed] and are kept
+/// up to date by [IndexedMap::insert], [IndexedMap::update] and
+/// [IndexedMap::remove]. Values cannot be mutated in place through a
+/// reference, since that would leave the indexes stale.
+#[derive(FieldQuery, FieldCall, Encode, Decode)]
+pub struct IndexedMap<K, V> {
+    values: Map<K, V>,
+    indexes: Map<IndexKey, K>,
+}
+
+impl<K, V> Default for IndexedMap<K, V> {
+    fn default() -> Self {
+        Self {
+            values: Map::default(),
+            indexes: Map::default(),
+        }
+    }
+}
+
+impl<K, V> std::fmt::Debug for IndexedMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IndexedMap").finish()
+    }
+}
+
+impl<K, V> IndexedMap<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K, V> IndexedMap<K, V>
+where
+    K: Encode + Decode + Terminated + State + Clone + Send + Sync,
+    V: State,
+{
+    pub fn with_store(store: Store) -> Result<Self> {
+        Self::load(store, &mut &[][..])
+    }
+}
+
+impl<K, V> State for IndexedMap<K, V>
+where
+    K: Encode + Decode + Terminated + State + Clone + Send + Sync,
+    V: State,
+{
+    fn attach(&mut self, store: Store) -> Result<()> {
+        self.values.attach(store.sub(&[0]))?;
+        self.indexes.attach(store.sub(&[1]))
+    }
+
+    fn flush<W: std::io::Write>(self, out: &mut W) -> Result<()> {
+        self.values.flush(out)?;
+        self.indexes.flush(out)
+    }
+
+    fn load(store: Store, _bytes: &mut &[u8]) -> Result<Self> {
+        let mut map = Self::default();
+        map.attach(store)?;
+
+        Ok(map)
+    }
+}
+
+impl<K, V> Migrate for IndexedMap<K, V>
+where
+    K: Encode + Decode + State + Terminated + Clone + Send + Sync + Migrate,
+    V: State + Migrate,
+{
+    fn migrate(src: Store, dest: Store, bytes: &mut &[u8]) -> Result<Self> {
+        Ok(Self {
+            values: Map::migrate(src.sub(&[0]), dest.sub(&[0]), bytes)?,
+            indexes: Map::migrate(src.sub(&[1]), dest.sub(&[1]), bytes)?,
+        })
+    }
+}
+
+impl<K, V> Describe for IndexedMap<K, V>
+where
+    K: Encode + Terminated + Clone + 'static + Describe,
+    V: State + Describe,
+{
+    fn describe() -> crate::describe::Descriptor {
+        use crate::describe::Builder;
+        Builder::new::<Self>()
+            .dynamic_child_with_prefix::<K, V>(&[0], |mut query_bytes| {
+                query_bytes.extend_from_slice(&[129]);
+                query_bytes
+            })
+            .build()
+    }
+}
+
+impl<K: Serialize, V: Serialize> Serialize for IndexedMap<K, V>
+where
+    K: Encode + Decode + Terminated + State + Clone + Send + Sync,
+    V: State,
+{
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        self.values.serialize(serializer)
+    }
+}
+
+#[orga]
+impl<K, V> IndexedMap<K, V>
+where
+    K: Encode + Decode + Terminated + State + Clone + Send + Sync,
+    V: State,
+{
+    #[query]
+    pub fn contains_key(&self, key: K) -> Result<bool> {
+        self.values.contains_key(key)
+    }
+
+    #[query]
+    pub fn get(&self, key: K) -> Result<Option<Ref<V>>> {
+        self.values.get(key)
+    }
+}
+
+impl<'a, K, V> IndexedMap<K, V>
+where
+    K: Encode + Decode + Terminated + State + Clone + Send + Sync,
+    V: State + Indexed,
+{
+    /// Inserts a value, replacing any existing value for the key and updating
+    /// its index entries.
+    ///
+    /// Returns an error without modifying the map if the value's key in a
+    /// unique index is already used by another primary key.
+    pub fn insert(&mut self, key: K, value: V) -> Result<()> {
+        let old_keys = match self.values.get(key.clone())? {
+            Some(old) => old.index_keys()?,
+            None => vec![],
+        };
+        let new_keys = value.index_keys()?;
+
+        self.reindex(&key, old_keys, new_keys)?;
+        self.values.insert(key, value)
+    }
+
+    /// Mutates a copy of the value for the key with `f`, then writes it back
+    /// and updates its index entries. Returns an error if there is no value
+    /// for the key.
+    ///
+    /// The map is left unchanged if `f` returns an error or if the updated
+    /// value's key in a unique index is already used by another primary key.
+    pub fn update<F, R>(&mut self, key: K, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut V) -> Result<R>,
+        V: Clone,
+    {
+        let (old_keys, mut value) = {
+            let old = self
+                .values
+                .get(key.clone())?
+                .ok_or_else(|| Error::App("Key not found in indexed map".into()))?;
+            (old.index_keys()?, (*old).clone())
+        };
+        let res = f(&mut value)?;
+        let new_keys = value.index_keys()?;
+
+        self.reindex(&key, old_keys, new_keys)?;
+        self.values.insert(key, value)?;
+
+        Ok(res)
+    }
+
+    /// Removes the value for the key along with its index entries.
+    pub fn remove(&mut self, key: K) -> Result<Option<ReadOnly<V>>> {
+        let removed = self.values.remove(key.clone())?;
+        if let Some(value) = removed.as_ref() {
+            self.reindex(&key, value.index_keys()?, vec![])?;
+        }
+
+        Ok(removed)
+    }
+
+    /// Returns the primary key stored under `index_key` in the given unique
+    /// index.
+    pub fn get_by<E: Encode>(&self, index: usize, index_key: E) -> Result<Option<K>> {
+        let def = Self::index_def(index)?;
+        if def.kind != IndexKind::Unique {
+            return Err(Error::App(format!("Index '{}' is not unique", def.name)));
+        }
+
+        let raw_key = Self::raw_key(index, &index_key.encode()?, None)?;
+        Ok(self.indexes.get(raw_key)?.map(|key| (*key).clone()))
+    }
+
+    /// Iterates over the primary keys in the given index, ordered by their
+    /// index keys (then by primary key within a multi index).
+    pub fn iter_by(&'a self, index: usize) -> Result<IndexIter<'a, K>> {
+        self.range_by::<Vec<u8>, _>(index, ..)
+    }
+
+    /// Iterates over the primary keys whose index keys fall within `range`,
+    /// ordered by their index keys (then by primary key within a multi
+    /// index).
+    pub fn range_by<E, B>(&'a self, index: usize, range: B) -> Result<IndexIter<'a, K>>
+    where
+        E: Encode,
+        B: RangeBounds<E>,
+    {
+        let def = Self::index_def(index)?;
+        let multi = def.kind == IndexKind::Multi;
+        let prefix = vec![index as u8];
+        let with_prefix =
+            |key: &E| -> Result<Vec<u8>> { Ok(Self::raw_key(index, &key.encode()?, None)?.0) };
+
+        let start = match range.start_bound() {
+            Bound::Included(start) => Bound::Included(with_prefix(start)?),
+            Bound::Excluded(start) if multi => {
+                Bound::Included(prefix_successor(with_prefix(start)?))
+            }
+            Bound::Excluded(start) => Bound::Excluded(with_prefix(start)?),
+            Bound::Unbounded => Bound::Included(prefix.clone()),
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) if multi => Bound::Excluded(prefix_successor(with_prefix(end)?)),
+            Bound::Included(end) => Bound::Included(with_prefix(end)?),
+            Bound::Excluded(end) => Bound::Excluded(with_prefix(end)?),
+            Bound::Unbounded => Bound::Excluded(prefix_successor(prefix.clone())),
+        };
+
+        Ok(IndexIter {
+            map_iter: self
+                .indexes
+                .range((start.map(IndexKey), end.map(IndexKey)))?,
+        })
+    }
+
+    pub fn iter(&'a self) -> Result<MapIter<'a, K, V>> {
+        self.values.iter()
+    }
+
+    fn index_def(index: usize) -> Result<&'static IndexDef> {
+        V::INDEXES
+            .get(index)
+            .filter(|_| index < u8::MAX as usize)
+            .ok_or_else(|| Error::App(format!("Index {} is not declared", index)))
+    }
+
+    fn raw_key(index: usize, index_key: &[u8], primary: Option<&K>) -> Result<IndexKey> {
+        let len: u32 = index_key
+            .len()
+            .try_into()
+            .map_err(|_| Error::App("Index key is too long".into()))?;
+
+        let mut bytes = Vec::with_capacity(5 + index_key.len());
+        bytes.push(index as u8);
+        bytes.extend_from_slice(&len.to_be_bytes());
+        bytes.extend_from_slice(index_key);
+        if let Some(primary) = primary {
+            primary.encode_into(&mut bytes)?;
+        }
+
+        Ok(IndexKey(bytes))
+    }
+
+    fn reindex(
+        &mut self,
+        key: &K,
+        old_keys: Vec<Option<Vec<u8>>>,
+        new_keys: Vec<Option<Vec<u8>>>,
+    ) -> Result<()> {
+        let mut removals = vec![];
+        let mut additions = vec![];
+
+        for (index, def) in V::INDEXES.iter().enumerate() {
+            Self::index_def(index)?;
+            let old_key = old_keys.get(index).cloned().flatten();
+            let new_key = new_keys.get(index).cloned().flatten();
+            if old_key == new_key {
+                continue;
+            }
+
+            let primary = match def.kind {
+                IndexKind::Unique => None,
+                IndexKind::Multi => Some(key),
+            };
+            if let Some(old_key) = old_key {
+                removals.push(Self::raw_key(index, &old_key, primary)?);
+            }
+            if let Some(new_key) = new_key {
+                let raw_key = Self::raw_key(index, &new_key, primary)?;
+                if def.kind == IndexKind::Unique {
+                    if let Some(existing) = self.indexes.get(raw_key.clone())? {
+                        if existing.encode()? != key.encode()? {
+                            return Err(Error::App(format!(
+                                "Key already exists in unique index '{}'",
+                                def.name
+                            )));
+                        }
+                    }
+                }
+                additions.push(raw_key);
+            }
+        }
+
+        for raw_key in removals {
+            self.indexes.remove(raw_key)?;
+        }
+        for raw_key in additions {
+            self.indexes.insert(raw_key, key.clone())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the smallest byte string which is greater than every byte string
+/// starting with `bytes`. `bytes` must start with an index id below 255, so
+/// the successor always exists.
+fn prefix_successor(mut bytes: Vec<u8>) -> Vec<u8> {
+    while let Some(last) = bytes.pop() {
+        if last < u8::MAX {
+            bytes.push(last + 1);
+            return bytes;
+        }
+    }
+
+    unreachable!("Index ids are below 255")
+}
+
+/// An iterator over the primary keys of an [IndexedMap] in index order.
+pub struct IndexIter<'a, K>
+where
+    K: Encode + Decode + Terminated + State + Clone + 'static,
+{
+    map_iter: MapIter<'a, IndexKey, K>,
+}
+
+impl<'a, K> Iterator for IndexIter<'a, K>
+where
+    K: Encode + Decode + Terminated + State + Clone + 'static,
+{
+    type Item = Result<K>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.map_iter
+            .next()
+            .map(|entry| entry.map(|(_, key)| (*key).clone()))
+    }
+}
+
+impl<'a, K> DoubleEndedIterator for IndexIter<'a, K>
+where
+    K: Encode + Decode + Terminated + State + Clone + 'static,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.map_iter
+            .next_back()
+            .map(|entry| entry.map(|(_, key)| (*key).clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orga;
+    use crate::store::{MapStore, Shared};
+
+    #[orga]
+    #[derive(Clone)]
+    struct Validator {
+        power: u64,
+        consensus_key: [u8; 4],
+        jailed: bool,
+    }
+
+    const BY_POWER: usize = 0;
+    const BY_CONSENSUS_KEY: usize = 1;
+
+    impl Indexed for Validator {
+        const INDEXES: &'static [IndexDef] =
+            &[IndexDef::multi("power"), IndexDef::unique("consensus_key")];
+
+        fn index_keys(&self) -> Result<Vec<Option<Vec<u8>>>> {
+            let power = if self.jailed {
+                None
+            } else {
+                Some(self.power.encode()?)
+            };
+            Ok(vec![power, Some(self.consensus_key.encode()?)])
+        }
+    }
+
+    fn validator(power: u64, consensus_key: u8) -> Validator {
+        Validator {
+            power,
+            consensus_key: [consensus_key; 4],
+            jailed: false,
+        }
+    }
+
+    fn keys<I: Iterator<Item = Result<u32>>>(iter: I) -> Result<Vec<u32>> {
+        iter.collect()
+    }
+
+    #[test]
+    fn indexes_follow_mutations() -> Result<()> {
+        let store = Store::new(Shared::new(MapStore::new()).into());
+        let mut map: IndexedMap<u32, Validator> = IndexedMap::with_store(store)?;
+
+        map.insert(1, validator(10, 1))?;
+        map.insert(2, validator(30, 2))?;
+        map.insert(3, validator(20, 3))?;
+        map.insert(4, validator(20, 4))?;
+
+        assert_eq!(keys(map.iter_by(BY_POWER)?)?, vec![1, 3, 4, 2]);
+        assert_eq!(keys(map.iter_by(BY_POWER)?.rev())?, vec![2, 4, 3, 1]);
+        assert_eq!(keys(map.range_by(BY_POWER, 20u64..=20)?)?, vec![3, 4]);
+        assert_eq!(keys(map.range_by(BY_POWER, 15u64..)?)?, vec![3, 4, 2]);
+        assert_eq!(map.get_by(BY_CONSENSUS_KEY, [3u8; 4])?, Some(3));
+
+        map.update(1, |v| {
+            v.power = 40;
+            Ok(())
+        })?;
+        map.update(3, |v| {
+            v.jailed = true;
+            Ok(())
+        })?;
+        assert_eq!(keys(map.iter_by(BY_POWER)?)?, vec![4, 2, 1]);
+
+        map.insert(5, validator(50, 4))
+            .expect_err("Consensus key should be unique");
+        assert!(!map.contains_key(5)?);
+
+        map.update(1, |v| {
+            v.power = 60;
+            v.consensus_key = [2; 4];
+            Ok(())
+        })
+        .expect_err("Consensus key should be unique");
+        map.update(1, |v| {
+            v.power = 60;
+            Err::<(), _>(Error::App("Failed update".into()))
+        })
+        .expect_err("Update should fail");
+        assert_eq!(map.get(1)?.unwrap().power, 40);
+        assert_eq!(keys(map.iter_by(BY_POWER)?)?, vec![4, 2, 1]);
+        assert_eq!(map.get_by(BY_CONSENSUS_KEY, [1u8; 4])?, Some(1));
+
+        map.remove(4)?;
+        assert_eq!(keys(map.iter_by(BY_POWER)?)?, vec![2, 1]);
+        assert_eq!(map.get_by(BY_CONSENSUS_KEY, [4u8; 4])?, None);
+        map.insert(5, validator(50, 4))?;
+        assert_eq!(map.get_by(BY_CONSENSUS_KEY, [4u8; 4])?, Some(5));
+
+        Ok(())
+    }
+
+    #[orga]
+    struct Tagged {
+        tag: Vec<u8>,
+    }
+
+    impl Indexed for Tagged {
+        const INDEXES: &'static [IndexDef] = &[IndexDef::multi("tag")];
+
+        fn index_keys(&self) -> Result<Vec<Option<Vec<u8>>>> {
+            Ok(vec![Some(self.tag.encode()?)])
+        }
+    }
+
+    #[test]
+    fn multi_index_keys_sharing_a_prefix() -> Result<()> {
+        let store = Store::new(Shared::new(MapStore::new()).into());
+        let mut map: IndexedMap<u32, Tagged> = IndexedMap::with_store(store)?;
+
+        map.insert(1, Tagged { tag: b"a".to_vec() })?;
+        map.insert(
+            2,
+            Tagged {
+                tag: b"ab".to_vec(),
+            },
+        )?;
+        map.insert(3, Tagged { tag: b"a".to_vec() })?;
+
+        let tag = b"a".to_vec();
+        assert_eq!(keys(map.range_by(0, tag.clone()..=tag)?)?, vec![1, 3]);
+        let tag = b"ab".to_vec();
+        assert_eq!(keys(map.range_by(0, tag.clone()..=tag)?)?, vec![2]);
+
+        Ok(())
+    }
+}
@@ -428,6 +428,150 @@ where
     }
 }
 
+impl<'a, K, V> Map<K, V>
+where
+    K: Encode + Decode + Terminated + Clone + Send + Sync + 'static,
+    V: State,
+{
+    /// Returns an iterator which removes and yields each entry with a key in
+    /// the given range, in key order.
+    ///
+    /// Each step seeks to the next remaining key rather than loading the whole
+    /// range up front, so the iterator can be dropped early to only remove a
+    /// prefix of the range.
+    pub fn drain_range<B: RangeBounds<K>>(&'a mut self, range: B) -> Drain<'a, K, V> {
+        Drain {
+            map: self,
+            start: range.start_bound().cloned(),
+            end: range.end_bound().cloned(),
+        }
+    }
+
+    /// Returns an iterator which removes and yields each entry with a key in
+    /// the given range for which `pred` returns `true`, in key order. Entries
+    /// for which `pred` returns `false` are left in the map.
+    pub fn extract_if<B, F>(&'a mut self, range: B, pred: F) -> ExtractIf<'a, K, V, F>
+    where
+        B: RangeBounds<K>,
+        F: FnMut(&K, &V) -> Result<bool>,
+    {
+        ExtractIf {
+            map: self,
+            start: range.start_bound().cloned(),
+            end: range.end_bound().cloned(),
+            pred,
+        }
+    }
+
+    /// Removes every entry for which `pred` returns `false`.
+    pub fn retain<F>(&mut self, mut pred: F) -> Result<()>
+    where
+        F: FnMut(&K, &V) -> Result<bool>,
+    {
+        for entry in self.extract_if(.., |key, value| Ok(!pred(key, value)?)) {
+            entry?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An iterator which removes entries from a [Map], created by
+/// [Map::drain_range].
+pub struct Drain<'a, K, V> {
+    map: &'a mut Map<K, V>,
+    start: Bound<K>,
+    end: Bound<K>,
+}
+
+impl<'a, K, V> Drain<'a, K, V>
+where
+    K: Encode + Decode + Terminated + Clone + Send + Sync + 'static,
+    V: State,
+{
+    fn next_key(&self) -> Result<Option<K>> {
+        let mut iter = self.map.range((self.start.clone(), self.end.clone()))?;
+        iter.next()
+            .transpose()
+            .map(|entry| entry.map(|(key, _)| (*key).clone()))
+    }
+}
+
+impl<'a, K, V> Iterator for Drain<'a, K, V>
+where
+    K: Encode + Decode + Terminated + Clone + Send + Sync + 'static,
+    V: State,
+{
+    type Item = Result<(K, ReadOnly<V>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = match self.next_key() {
+            Ok(Some(key)) => key,
+            Ok(None) => return None,
+            Err(err) => return Some(Err(err)),
+        };
+        self.start = Bound::Excluded(key.clone());
+
+        match self.map.remove(key.clone()) {
+            Ok(Some(value)) => Some(Ok((key, value))),
+            Ok(None) => Some(Err(Error::App("Drained key has no value".into()))),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// An iterator which removes entries matching a predicate from a [Map],
+/// created by [Map::extract_if].
+pub struct ExtractIf<'a, K, V, F> {
+    map: &'a mut Map<K, V>,
+    start: Bound<K>,
+    end: Bound<K>,
+    pred: F,
+}
+
+impl<'a, K, V, F> ExtractIf<'a, K, V, F>
+where
+    K: Encode + Decode + Terminated + Clone + Send + Sync + 'static,
+    V: State,
+    F: FnMut(&K, &V) -> Result<bool>,
+{
+    fn next_key(&mut self) -> Result<Option<K>> {
+        let iter = self.map.range((self.start.clone(), self.end.clone()))?;
+        for entry in iter {
+            let (key, value) = entry?;
+            self.start = Bound::Excluded((*key).clone());
+            if (self.pred)(&*key, &*value)? {
+                return Ok(Some((*key).clone()));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl<'a, K, V, F> Iterator for ExtractIf<'a, K, V, F>
+where
+    K: Encode + Decode + Terminated + Clone + Send + Sync + 'static,
+    V: State,
+    F: FnMut(&K, &V) -> Result<bool>,
+{
+    type Item = Result<(K, ReadOnly<V>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = match self.next_key() {
+            Ok(Some(key)) => key,
+            Ok(None) => return None,
+            Err(err) => return Some(Err(err)),
+        };
+
+        match self.map.remove(key.clone()) {
+            Ok(Some(value)) => Some(Ok((key, value))),
+            Ok(None) => Some(Err(Error::App("Extracted key has no value".into()))),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
 fn encode_bound<K: Encode>(bound: Bound<&K>) -> Result<Bound<Vec<u8>>> {
     match bound {
         Bound::Included(inner) => Ok(Bound::Included(inner.encode()?)),
@@ -1030,6 +1174,12 @@ impl<K: Encode + Decode + Terminated + 'static, V: State> Map<K, V> {
     }
 }
 
+impl<K, V> AttachedStore for Map<K, V> {
+    fn attached_store(&self) -> Store {
+        self.store.clone()
+    }
+}
+
 impl<'a, K, V> Entry<'a, K, V>
 where
     K: Encode + Terminated + Clone + Send + Sync + 'static,
@@ -2356,4 +2506,58 @@ mod tests {
         let expected: Vec<(u32, u32)> = vec![(12, 26), (13, 24)];
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn drain_range() {
+        let store = mapstore();
+        let mut edit_map: Map<u32, u32> = Default::default();
+        edit_map.attach(store.clone()).unwrap();
+        for i in 0..5 {
+            edit_map.insert(i, i * 10).unwrap();
+        }
+        let mut buf = vec![];
+        edit_map.flush(&mut buf).unwrap();
+
+        let mut read_map: Map<u32, u32> = Default::default();
+        read_map.attach(store).unwrap();
+        read_map.insert(5, 50).unwrap();
+        read_map.remove(2).unwrap();
+
+        let drained: Vec<(u32, u32)> = read_map
+            .drain_range(1..=5)
+            .map(|result| result.map(|(k, v)| (k, *v)).unwrap())
+            .collect();
+        assert_eq!(drained, vec![(1, 10), (3, 30), (4, 40), (5, 50)]);
+
+        let mut remaining = vec![];
+        read_map
+            .iter()
+            .unwrap()
+            .map(|result| result.unwrap())
+            .for_each(|(k, v)| remaining.push((*k, *v)));
+        assert_eq!(remaining, vec![(0, 0)]);
+    }
+
+    #[test]
+    fn extract_if_and_retain() {
+        let (_, mut map) = setup();
+        for i in 0..6 {
+            map.insert(i, i * 10).unwrap();
+        }
+
+        let extracted: Vec<u32> = map
+            .extract_if(1.., |k, _| Ok(k % 2 == 0))
+            .map(|result| result.unwrap().0)
+            .collect();
+        assert_eq!(extracted, vec![2, 4]);
+
+        map.retain(|_, v| Ok(*v != 30)).unwrap();
+
+        let mut remaining = vec![];
+        map.iter()
+            .unwrap()
+            .map(|result| result.unwrap())
+            .for_each(|(k, _)| remaining.push(*k));
+        assert_eq!(remaining, vec![0, 1, 5]);
+    }
 }
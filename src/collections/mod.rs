@@ -3,13 +3,21 @@ use crate::state::State;
 
 pub use crate::macros::{Entry, Next};
 
+pub mod counted_map;
 pub mod deque;
 pub mod entry_map;
+pub mod indexed_map;
 pub mod map;
+pub mod priority_queue;
+pub mod set;
 
+pub use counted_map::CountedMap;
 pub use deque::Deque;
 pub use entry_map::EntryMap;
+pub use indexed_map::{IndexDef, IndexKind, Indexed, IndexedMap};
 pub use map::Map;
+pub use priority_queue::PriorityQueue;
+pub use set::Set;
 
 pub use map::{ChildMut, Ref};
 
@@ -0,0 +1,265 @@
+use serde::Serialize;
+
+use super::map::{Iter as MapIter, Map, ReadOnly, Ref};
+use crate::describe::Describe;
+use crate::encoding::{Decode, Encode, Terminated};
+use crate::migrate::Migrate;
+use crate::orga;
+use crate::query::FieldQuery;
+use crate::state::State;
+use crate::store::Store;
+use crate::Result;
+
+/// A queue collection which yields its values in ascending order of their
+/// priority, e.g. a timestamp or height at which an entry matures.
+///
+/// Priorities are compared by their encoding, and values with equal priority
+/// are yielded in the order they were pushed.
+#[derive(FieldQuery, Encode, Decode)]
+pub struct PriorityQueue<P, T> {
+    meta: Meta,
+    map: Map<(P, u64), T>,
+}
+
+#[orga]
+#[derive(Clone, Debug)]
+pub struct Meta {
+    len: u64,
+    next_seq: u64,
+}
+
+impl<P, T> Describe for PriorityQueue<P, T>
+where
+    P: Encode + Terminated + Clone + 'static + Describe,
+    T: State + Describe,
+{
+    fn describe() -> crate::describe::Descriptor {
+        use crate::describe::Builder;
+        Builder::new::<Self>()
+            .dynamic_child::<(P, u64), T>(|mut query_bytes| {
+                query_bytes.extend_from_slice(&[129]);
+                query_bytes
+            })
+            .build()
+    }
+}
+
+impl<P, T> Default for PriorityQueue<P, T> {
+    fn default() -> Self {
+        Self {
+            meta: Meta::default(),
+            map: Map::default(),
+        }
+    }
+}
+
+impl<P, T> std::fmt::Debug for PriorityQueue<P, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PriorityQueue")
+            .field("meta", &self.meta)
+            .finish()
+    }
+}
+
+impl<P, T> PriorityQueue<P, T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<P, T> State for PriorityQueue<P, T>
+where
+    P: Encode + Terminated + 'static,
+    T: State,
+{
+    fn attach(&mut self, store: Store) -> Result<()> {
+        self.map.attach(store)
+    }
+
+    fn flush<W: std::io::Write>(self, out: &mut W) -> Result<()> {
+        self.meta.flush(out)?;
+        self.map.flush(out)
+    }
+
+    fn load(store: Store, bytes: &mut &[u8]) -> Result<Self> {
+        let mut value = Self {
+            meta: Meta::load(store.clone(), bytes)?,
+            map: Map::load(store.clone(), bytes)?,
+        };
+
+        value.attach(store)?;
+
+        Ok(value)
+    }
+}
+
+impl<P, T> Migrate for PriorityQueue<P, T>
+where
+    P: Encode + Decode + State + Terminated + Clone + Send + Sync + Migrate,
+    T: State + Migrate,
+{
+    fn migrate(src: Store, dest: Store, bytes: &mut &[u8]) -> Result<Self> {
+        Ok(Self {
+            meta: Meta::migrate(Store::default(), Store::default(), bytes)?,
+            map: Map::migrate(src, dest, bytes)?,
+        })
+    }
+}
+
+impl<P: Serialize, T: Serialize> Serialize for PriorityQueue<P, T>
+where
+    P: Encode + Decode + Terminated + Clone + 'static,
+    T: State,
+{
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::{Error, SerializeSeq};
+        let mut seq = serializer.serialize_seq(None)?;
+        for entry in self.iter().map_err(Error::custom)? {
+            let (priority, value) = entry.map_err(Error::custom)?;
+            seq.serialize_element(&(priority, &*value))?;
+        }
+        seq.end()
+    }
+}
+
+#[orga]
+impl<P, T> PriorityQueue<P, T>
+where
+    P: Encode + Terminated + Send + Sync + 'static,
+    T: State,
+{
+    #[query]
+    pub fn len(&self) -> u64 {
+        self.meta.len
+    }
+
+    #[query]
+    pub fn get_raw(&self, key: (P, u64)) -> Result<Option<Ref<T>>> {
+        self.map.get(key)
+    }
+
+    #[query]
+    pub fn is_empty(&self) -> bool {
+        self.meta.len == 0
+    }
+
+    /// Adds a value to the queue with the given priority.
+    pub fn push(&mut self, priority: P, value: T) -> Result<()> {
+        let seq = self.meta.next_seq;
+        self.meta.next_seq += 1;
+        self.meta.len += 1;
+
+        self.map.insert((priority, seq), value)
+    }
+}
+
+impl<'a, P, T> PriorityQueue<P, T>
+where
+    P: Encode + Decode + Terminated + Clone + Send + Sync + 'static,
+    T: State,
+{
+    /// Returns the value with the lowest priority, along with its priority.
+    pub fn peek(&'a self) -> Result<Option<(P, Ref<'a, T>)>> {
+        self.map
+            .iter()?
+            .next()
+            .transpose()
+            .map(|entry| entry.map(|(key, value)| (key.0.clone(), value)))
+    }
+
+    /// Removes and returns the value with the lowest priority, along with its
+    /// priority.
+    pub fn pop(&mut self) -> Result<Option<(P, ReadOnly<T>)>> {
+        let key = match self.map.iter()?.next().transpose()? {
+            Some((key, _)) => (*key).clone(),
+            None => return Ok(None),
+        };
+
+        let value = self.map.remove(key.clone())?;
+        if value.is_some() {
+            self.meta.len -= 1;
+        }
+
+        Ok(value.map(|value| (key.0, value)))
+    }
+
+    /// Removes and returns every value whose priority is lower than or equal
+    /// to `max_priority`, in order. Only the popped entries are read from the
+    /// store.
+    pub fn pop_until(&mut self, max_priority: P) -> Result<Vec<(P, ReadOnly<T>)>> {
+        let popped: Vec<_> = self
+            .map
+            .drain_range(..=(max_priority, u64::MAX))
+            .map(|entry| entry.map(|((priority, _), value)| (priority, value)))
+            .collect::<Result<_>>()?;
+        self.meta.len -= popped.len() as u64;
+
+        Ok(popped)
+    }
+
+    /// Iterates over the queue in the order values would be popped.
+    pub fn iter(&'a self) -> Result<Iter<'a, P, T>> {
+        Ok(Iter {
+            map_iter: self.map.iter()?,
+        })
+    }
+}
+
+/// An iterator over the values of a [PriorityQueue] and their priorities.
+pub struct Iter<'a, P, T>
+where
+    P: Encode + Decode + Terminated + 'static,
+    T: State,
+{
+    map_iter: MapIter<'a, (P, u64), T>,
+}
+
+impl<'a, P, T> Iterator for Iter<'a, P, T>
+where
+    P: Encode + Decode + Terminated + Clone + 'static,
+    T: State,
+{
+    type Item = Result<(P, Ref<'a, T>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.map_iter
+            .next()
+            .map(|entry| entry.map(|(key, value)| (key.0.clone(), value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pops_in_priority_order() -> Result<()> {
+        let mut queue: PriorityQueue<u64, u32> = PriorityQueue::new();
+        queue.push(30, 1)?;
+        queue.push(10, 2)?;
+        queue.push(20, 3)?;
+        queue.push(10, 4)?;
+        assert_eq!(queue.len(), 4);
+
+        let (priority, value) = queue.peek()?.unwrap();
+        assert_eq!((priority, *value), (10, 2));
+
+        let popped: Vec<(u64, u32)> = queue
+            .pop_until(20)?
+            .into_iter()
+            .map(|(priority, value)| (priority, *value))
+            .collect();
+        assert_eq!(popped, vec![(10, 2), (10, 4), (20, 3)]);
+        assert_eq!(queue.len(), 1);
+
+        let (priority, value) = queue.pop()?.unwrap();
+        assert_eq!((priority, *value), (30, 1));
+        assert!(queue.pop()?.is_none());
+        assert!(queue.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+use serde::Serialize;
+
+use super::counted_map::CountedMap;
+use super::map::{Iter as MapIter, Ref};
+use crate::describe::Describe;
+use crate::encoding::{Decode, Encode, Terminated};
+use crate::migrate::Migrate;
+use crate::orga;
+use crate::query::FieldQuery;
+use crate::state::State;
+use crate::store::Store;
+use crate::Result;
+use std::ops::RangeBounds;
+
+/// A set collection which stores its values as keys in the backing store,
+/// ordered by their encoding.
+#[derive(FieldQuery, Encode, Decode)]
+pub struct Set<T> {
+    map: CountedMap<T, ()>,
+}
+
+impl<T> Describe for Set<T>
+where
+    T: Encode + Terminated + Clone + 'static + Describe,
+{
+    fn describe() -> crate::describe::Descriptor {
+        use crate::describe::Builder;
+        Builder::new::<Self>()
+            .dynamic_child::<T, ()>(|mut query_bytes| {
+                query_bytes.extend_from_slice(&[131]);
+                query_bytes
+            })
+            .build()
+    }
+}
+
+impl<T> Default for Set<T> {
+    fn default() -> Self {
+        Self {
+            map: CountedMap::default(),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Set<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Set").field("len", &self.map.len()).finish()
+    }
+}
+
+impl<T> Set<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T> State for Set<T>
+where
+    T: Encode + Terminated + 'static,
+{
+    fn attach(&mut self, store: Store) -> Result<()> {
+        self.map.attach(store)
+    }
+
+    fn flush<W: std::io::Write>(self, out: &mut W) -> Result<()> {
+        self.map.flush(out)
+    }
+
+    fn load(store: Store, bytes: &mut &[u8]) -> Result<Self> {
+        Ok(Self {
+            map: CountedMap::load(store, bytes)?,
+        })
+    }
+}
+
+impl<T> Migrate for Set<T>
+where
+    T: Encode + Decode + State + Terminated + Clone + Send + Sync + Migrate,
+{
+    fn migrate(src: Store, dest: Store, bytes: &mut &[u8]) -> Result<Self> {
+        Ok(Self {
+            map: CountedMap::migrate(src, dest, bytes)?,
+        })
+    }
+}
+
+impl<T: Serialize> Serialize for Set<T>
+where
+    T: Encode + Decode + Terminated + Clone + 'static,
+{
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::{Error, SerializeSeq};
+        let mut seq = serializer.serialize_seq(None)?;
+        for value in self.iter().map_err(Error::custom)? {
+            let value = value.map_err(Error::custom)?;
+            seq.serialize_element(&value)?;
+        }
+        seq.end()
+    }
+}
+
+#[orga]
+impl<T> Set<T>
+where
+    T: Encode + Terminated + Send + Sync + 'static,
+{
+    #[query]
+    pub fn len(&self) -> u64 {
+        self.map.len()
+    }
+
+    #[query]
+    pub fn contains(&self, value: T) -> Result<bool> {
+        self.map.contains_key(value)
+    }
+
+    #[query]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the (empty) entry for the value if it is in the set. Used to
+    /// resolve the set's entries by key, as with [Map::get](super::Map::get).
+    #[query]
+    pub fn get(&self, value: T) -> Result<Option<Ref<()>>> {
+        self.map.get(value)
+    }
+}
+
+impl<T> Set<T>
+where
+    T: Encode + Terminated + Clone + Send + Sync + 'static,
+{
+    /// Adds a value to the set, returning `false` if it was already present.
+    pub fn insert(&mut self, value: T) -> Result<bool> {
+        if self.map.contains_key(value.clone())? {
+            return Ok(false);
+        }
+        self.map.insert(value, ())?;
+
+        Ok(true)
+    }
+
+    /// Removes a value from the set, returning `false` if it was not present.
+    pub fn remove(&mut self, value: T) -> Result<bool> {
+        Ok(self.map.remove(value)?.is_some())
+    }
+}
+
+impl<'a, T> Set<T>
+where
+    T: Encode + Decode + Terminated + Clone + 'static,
+{
+    pub fn iter(&'a self) -> Result<Iter<'a, T>> {
+        Ok(Iter {
+            map_iter: self.map.iter()?,
+        })
+    }
+
+    pub fn range<B: RangeBounds<T>>(&'a self, range: B) -> Result<Iter<'a, T>> {
+        Ok(Iter {
+            map_iter: self.map.range(range)?,
+        })
+    }
+}
+
+/// An iterator over the values of a [Set], in order of their encoding.
+pub struct Iter<'a, T>
+where
+    T: Encode + Decode + Terminated + 'static,
+{
+    map_iter: MapIter<'a, T, ()>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T>
+where
+    T: Encode + Decode + Terminated + Clone + 'static,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.map_iter
+            .next()
+            .map(|entry| entry.map(|(value, _)| (*value).clone()))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T>
+where
+    T: Encode + Decode + Terminated + Clone + 'static,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.map_iter
+            .next_back()
+            .map(|entry| entry.map(|(value, _)| (*value).clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_remove_iter() -> Result<()> {
+        let mut set: Set<u32> = Set::new();
+
+        assert!(set.insert(3)?);
+        assert!(set.insert(1)?);
+        assert!(!set.insert(3)?);
+        assert!(set.insert(2)?);
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(2)?);
+
+        assert!(set.remove(2)?);
+        assert!(!set.remove(2)?);
+        assert_eq!(set.len(), 2);
+
+        let values: Vec<u32> = set.iter()?.collect::<Result<_>>()?;
+        assert_eq!(values, vec![1, 3]);
+
+        Ok(())
+    }
+}
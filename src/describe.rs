@@ -14,9 +14,15 @@ use wasm_bindgen::prelude::*;
 
 mod builder;
 pub mod child;
+mod methods;
+pub mod typescript;
 
 pub use crate::macros::Describe;
 pub use builder::Builder;
+pub use methods::{
+    format_value, parse_value, AccessFn, ArgDescriptor, DescribeCalls, DescribeQueries,
+    MethodDescriptor, ParseFn, QueryFn,
+};
 
 pub trait Describe {
     fn describe() -> Descriptor;
@@ -30,7 +36,11 @@ pub struct Descriptor {
     pub state_version: u32,
     children: Children,
     pub load: Option<LoadFn>,
+    pub format: Option<FormatFn>,
+    pub parse: Option<ParseFn>,
     pub meta: Option<Box<Self>>,
+    pub calls: Vec<MethodDescriptor>,
+    pub queries: Vec<MethodDescriptor>,
 }
 
 impl Debug for Descriptor {
@@ -39,6 +49,8 @@ impl Debug for Descriptor {
             .field("type_name", &self.type_name)
             .field("state_version", &self.state_version)
             .field("children", &self.children)
+            .field("calls", &self.calls)
+            .field("queries", &self.queries)
             .finish()
     }
 }
@@ -48,6 +60,190 @@ impl Descriptor {
         &self.children
     }
 
+    /// Decodes a value of the described type from the start of `bytes`,
+    /// advancing past it, and formats it for display. Types without a
+    /// `Display` or `Debug` implementation are formatted as hex.
+    pub fn format_bytes(&self, bytes: &mut &[u8]) -> Result<String> {
+        let format = self
+            .format
+            .ok_or_else(|| Error::App(format!("Cannot format {}", self.type_name)))?;
+        format(bytes)
+    }
+
+    /// Resolves a raw key of the described type's store into a readable path of
+    /// field names and formatted map keys, e.g.
+    /// `staking.validators[nomic1...].info`.
+    ///
+    /// Also returns the descriptor of the value stored at the key, or `None`
+    /// if the end of the key could not be resolved, in which case the remaining
+    /// bytes are appended to the path as hex.
+    pub fn resolve_key(&self, key: &[u8]) -> (String, Option<&Descriptor>) {
+        let mut path = String::new();
+        let mut desc = self;
+        let mut rest = key;
+
+        while !rest.is_empty() {
+            let resolved = match desc.children() {
+                Children::None => None,
+                Children::Named(children) => children
+                    .iter()
+                    .filter_map(|child| match child.store_key {
+                        KeyOp::Append(ref prefix) if rest.starts_with(prefix) => {
+                            Some((child, &rest[prefix.len()..]))
+                        }
+                        KeyOp::Absolute(ref prefix) if key.starts_with(prefix) => {
+                            Some((child, &key[prefix.len()..]))
+                        }
+                        _ => None,
+                    })
+                    .min_by_key(|(_, child_rest)| child_rest.len())
+                    .map(|(child, child_rest)| {
+                        if !path.is_empty() {
+                            path.push('.');
+                        }
+                        path.push_str(&child.name);
+                        (&child.desc, child_rest)
+                    }),
+                Children::Dynamic(child) => {
+                    rest.strip_prefix(child.store_prefix())
+                        .and_then(|mut child_rest| {
+                            let child_key = child.key_desc().format_bytes(&mut child_rest).ok()?;
+                            path.push_str(&format!("[{}]", child_key));
+                            Some((child.value_desc(), child_rest))
+                        })
+                }
+            };
+
+            match resolved {
+                Some((child_desc, child_rest)) => {
+                    desc = child_desc;
+                    rest = child_rest;
+                }
+                None => {
+                    path.push_str(&format!("#{}", hex::encode(rest)));
+                    return (path, None);
+                }
+            }
+        }
+
+        (path, Some(desc))
+    }
+
+    /// Parses a value of the described type from a string, e.g. a map key
+    /// given on the command line, returning its encoding.
+    pub fn parse_str(&self, value: &str) -> Result<Vec<u8>> {
+        let parse = self
+            .parse
+            .ok_or_else(|| Error::App(format!("Cannot parse {}", self.type_name)))?;
+        parse(value)
+    }
+
+    pub fn named_child(&self, name: &str) -> Option<&NamedChild> {
+        match self.children() {
+            Children::Named(children) => children.iter().find(|child| child.name == name),
+            _ => None,
+        }
+    }
+
+    /// Finds the descriptor of the type with the given `TypeId` in the state
+    /// tree, following named children with appended store keys. Returns the
+    /// store key prefix of the found type along with its descriptor.
+    pub fn find_type(&self, type_id: TypeId) -> Option<(Vec<u8>, &Descriptor)> {
+        if self.type_id == type_id {
+            return Some((vec![], self));
+        }
+
+        let Children::Named(children) = self.children() else {
+            return None;
+        };
+        children.iter().find_map(|child| match child.store_key {
+            KeyOp::Append(ref prefix) => child
+                .desc
+                .find_type(type_id)
+                .map(|(suffix, desc)| ([prefix.as_slice(), suffix.as_slice()].concat(), desc)),
+            KeyOp::Absolute(_) => None,
+        })
+    }
+
+    /// Resolves a readable path of field names and map keys, e.g.
+    /// `staking.validators[nomic1...]`, into the store key prefix of the value
+    /// at the path, and the value's descriptor. The inverse of
+    /// [Descriptor::resolve_key].
+    pub fn resolve_path(&self, path: &str) -> Result<(Vec<u8>, &Descriptor)> {
+        let mut key = vec![];
+        let mut desc = self;
+        let mut rest = path;
+
+        while !rest.is_empty() {
+            rest = rest.strip_prefix('.').unwrap_or(rest);
+            if let Some(inner) = rest.strip_prefix('[') {
+                let end = inner
+                    .find(']')
+                    .ok_or_else(|| Error::App(format!("Unclosed key in path: {}", path)))?;
+                let Children::Dynamic(child) = desc.children() else {
+                    return Err(Error::App(format!("{} has no keys", desc.type_name)));
+                };
+                key.extend_from_slice(child.store_prefix());
+                key.extend(child.key_desc().parse_str(&inner[..end])?);
+                desc = child.value_desc();
+                rest = &inner[end + 1..];
+            } else {
+                let end = rest.find(|c| c == '.' || c == '[').unwrap_or(rest.len());
+                let name = &rest[..end];
+                let child = desc
+                    .named_child(name)
+                    .ok_or_else(|| Error::App(format!("No field named {} in path", name)))?;
+                key = child.store_key.apply_bytes(key.as_slice());
+                desc = &child.desc;
+                rest = &rest[end..];
+            }
+        }
+
+        Ok((key, desc))
+    }
+
+    /// Runs a query method on `value`, a value of the described type, from the
+    /// encoding of a query on it, e.g. one built by the generated TypeScript
+    /// bindings. Returns the result formatted for display.
+    pub fn run_query_bytes(&self, value: &dyn Any, bytes: &[u8]) -> Result<String> {
+        let mut desc = self;
+        let mut value = value;
+        let mut rest = bytes;
+
+        loop {
+            let prefix = *rest
+                .first()
+                .ok_or_else(|| Error::Query("Query does not end in a method".into()))?;
+            if prefix >= crate::query::PREFIX_OFFSET {
+                let index = (prefix - crate::query::PREFIX_OFFSET) as usize;
+                let method = desc.queries.get(index).ok_or_else(|| {
+                    Error::Query(format!("No query method {} on {}", index, desc.type_name))
+                })?;
+                return method.run_query(value, &rest[1..]);
+            }
+
+            let Children::Named(children) = desc.children() else {
+                return Err(Error::Query(format!("{} has no fields", desc.type_name)));
+            };
+            let (child, len) = children
+                .iter()
+                .filter_map(|child| match child.store_key {
+                    KeyOp::Append(ref prefix) if !prefix.is_empty() && rest.starts_with(prefix) => {
+                        Some((child, prefix.len()))
+                    }
+                    _ => None,
+                })
+                .max_by_key(|(_, len)| *len)
+                .ok_or_else(|| Error::Query(format!("Unknown field in {}", desc.type_name)))?;
+
+            value = child
+                .access(value)
+                .ok_or_else(|| Error::Query(format!("{} cannot be queried", child.name)))?;
+            desc = &child.desc;
+            rest = &rest[len..];
+        }
+    }
+
     // pub fn kv_descs(self) -> impl Iterator<Item = DynamicChild> {
     //     let (own, named) = match self.children {
     //         Children::None => (vec![], vec![]),
@@ -60,6 +256,7 @@ impl Descriptor {
 }
 
 pub type LoadFn = fn(Store, &mut &[u8]) -> Result<()>;
+pub type FormatFn = fn(&mut &[u8]) -> Result<String>;
 pub type ApplyQueryBytesFn = fn(Vec<u8>) -> Vec<u8>;
 
 #[derive(Clone, Debug, Default)]
@@ -76,6 +273,15 @@ pub struct NamedChild {
     pub name: String,
     pub desc: Descriptor,
     pub store_key: KeyOp,
+    pub access: Option<AccessFn>,
+}
+
+impl NamedChild {
+    /// Borrows the child from a value of the parent type, if the child's
+    /// accessor is known.
+    pub fn access<'a>(&self, parent: &'a dyn Any) -> Option<&'a dyn Any> {
+        self.access.and_then(|access| access(parent))
+    }
 }
 
 // #[wasm_bindgen(inspectable)]
@@ -83,6 +289,7 @@ pub struct NamedChild {
 pub struct DynamicChild {
     key_desc: Box<Descriptor>,
     value_desc: Box<Descriptor>,
+    store_prefix: Vec<u8>,
     apply_query_bytes: ApplyQueryBytesFn,
 }
 
@@ -95,6 +302,12 @@ impl DynamicChild {
         &self.value_desc
     }
 
+    /// The prefix of the parent's store under which the entries are stored,
+    /// before their encoded keys.
+    pub fn store_prefix(&self) -> &[u8] {
+        &self.store_prefix
+    }
+
     pub fn apply_query_bytes(&self, bytes: Vec<u8>) -> Vec<u8> {
         (self.apply_query_bytes)(bytes)
     }
@@ -1,18 +1,24 @@
 use crate::state::State;
 use std::any::{type_name, TypeId};
 
+use super::methods::{parse_state, MaybeDescribeCalls, MaybeDescribeQueries};
 use super::{
-    ApplyQueryBytesFn, Children, Describe, Descriptor, DynamicChild, Inspect, KeyOp, LoadFn,
-    NamedChild,
+    AccessFn, ApplyQueryBytesFn, Children, Describe, Descriptor, DynamicChild, FormatFn, Inspect,
+    KeyOp, LoadFn, MethodDescriptor, NamedChild, ParseFn,
 };
+use crate::store::Store;
 
 pub struct Builder {
     type_id: TypeId,
     type_name: String,
     state_version: u32,
     load: LoadFn,
+    format: FormatFn,
+    parse: ParseFn,
     children: Option<Children>,
     meta: Option<Box<Descriptor>>,
+    calls: Vec<MethodDescriptor>,
+    queries: Vec<MethodDescriptor>,
 }
 
 impl Builder {
@@ -25,9 +31,21 @@ impl Builder {
                 T::load(store, bytes)?;
                 Ok(())
             },
+            format: |bytes| {
+                let start = *bytes;
+                let value = T::load(Store::default(), bytes)?;
+                let consumed = &start[..start.len() - bytes.len()];
+                Ok(value
+                    .maybe_to_string()
+                    .or_else(|| value.maybe_debug(false))
+                    .unwrap_or_else(|| hex::encode(consumed)))
+            },
+            parse: parse_state::<T>,
             // meta: Some(Box::new(<u8 as Describe>::describe())),
             meta: None,
             children: None,
+            calls: <T as MaybeDescribeCalls>::maybe_describe_calls(),
+            queries: <T as MaybeDescribeQueries>::maybe_describe_queries(),
         }
     }
 
@@ -36,6 +54,7 @@ impl Builder {
             name: name.to_string(),
             store_key: keyop,
             desc: T::describe(),
+            access: None,
         };
 
         match self.children {
@@ -62,6 +81,23 @@ impl Builder {
         }
     }
 
+    /// Like [Builder::named_child_from_state], also setting the function
+    /// used to borrow the child from a value of the parent type.
+    pub fn named_child_with_access<T: State + Describe, U: Describe>(
+        mut self,
+        name: &'static str,
+        access: AccessFn,
+    ) -> Self {
+        self = self.named_child_from_state::<T, U>(name);
+        if let Some(Children::Named(ref mut children)) = self.children {
+            if let Some(child) = children.iter_mut().find(|child| child.name == name) {
+                child.access = Some(access);
+            }
+        }
+
+        self
+    }
+
     pub fn meta<T: Describe>(self) -> Self {
         Builder {
             meta: Some(Box::new(T::describe())),
@@ -70,12 +106,23 @@ impl Builder {
     }
 
     pub fn dynamic_child<K: Describe, V: Describe>(
+        self,
+        apply_query_bytes: ApplyQueryBytesFn,
+    ) -> Self {
+        self.dynamic_child_with_prefix::<K, V>(&[], apply_query_bytes)
+    }
+
+    /// Like [Builder::dynamic_child], for collections which store their
+    /// entries under `store_prefix` rather than directly in their own store.
+    pub fn dynamic_child_with_prefix<K: Describe, V: Describe>(
         mut self,
+        store_prefix: &[u8],
         apply_query_bytes: ApplyQueryBytesFn,
     ) -> Self {
         let child = DynamicChild {
             key_desc: Box::new(K::describe()),
             value_desc: Box::new(V::describe()),
+            store_prefix: store_prefix.to_vec(),
             apply_query_bytes,
         };
 
@@ -93,8 +140,12 @@ impl Builder {
             type_name: self.type_name,
             state_version: self.state_version,
             load: Some(self.load),
+            format: Some(self.format),
+            parse: Some(self.parse),
             children: self.children.unwrap_or_default(),
             meta: self.meta,
+            calls: self.calls,
+            queries: self.queries,
         }
     }
 }
@@ -0,0 +1,235 @@
+use std::any::{type_name, Any};
+use std::fmt::Debug;
+use std::str::FromStr;
+
+use serde::de::DeserializeOwned;
+
+use super::{DebugWrapper, DisplayWrapper, MaybeDebug, MaybeDisplay};
+use crate::{encoding::Encode, state::State, Error, Result};
+
+pub type ParseFn = fn(&str) -> Result<Vec<u8>>;
+pub type QueryFn = fn(&dyn Any, &[u8]) -> Result<String>;
+pub type AccessFn = fn(&dyn Any) -> Option<&dyn Any>;
+
+/// Describes a `#[call]` or `#[query]` method, generated by the `#[orga]`
+/// macro.
+#[derive(Clone)]
+pub struct MethodDescriptor {
+    pub name: String,
+    pub args: Vec<ArgDescriptor>,
+    query: Option<QueryFn>,
+}
+
+impl Debug for MethodDescriptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MethodDescriptor")
+            .field("name", &self.name)
+            .field("args", &self.args)
+            .finish()
+    }
+}
+
+impl MethodDescriptor {
+    pub fn call(name: &str, args: Vec<ArgDescriptor>) -> Self {
+        Self {
+            name: name.to_string(),
+            args,
+            query: None,
+        }
+    }
+
+    pub fn query(name: &str, args: Vec<ArgDescriptor>, query: QueryFn) -> Self {
+        Self {
+            name: name.to_string(),
+            args,
+            query: Some(query),
+        }
+    }
+
+    /// Parses the arguments from strings, returning their concatenated
+    /// encodings.
+    pub fn parse_args<S: AsRef<str>>(&self, args: &[S]) -> Result<Vec<u8>> {
+        if args.len() != self.args.len() {
+            return Err(Error::App(format!(
+                "Expected {} arguments for {}, got {}",
+                self.args.len(),
+                self.name,
+                args.len()
+            )));
+        }
+
+        let mut bytes = vec![];
+        for (desc, arg) in self.args.iter().zip(args) {
+            bytes.extend(desc.parse(arg.as_ref())?);
+        }
+
+        Ok(bytes)
+    }
+
+    /// Runs the query method on `value`, which must be of the type the
+    /// method was described for, with encoded arguments. Returns the result
+    /// formatted for display.
+    pub fn run_query(&self, value: &dyn Any, args: &[u8]) -> Result<String> {
+        let query = self
+            .query
+            .ok_or_else(|| Error::Query(format!("{} is not a query method", self.name)))?;
+        query(value, args)
+    }
+}
+
+/// Describes an argument of a `#[call]` or `#[query]` method.
+#[derive(Clone)]
+pub struct ArgDescriptor {
+    pub name: String,
+    pub type_name: String,
+    parse: ParseFn,
+}
+
+impl Debug for ArgDescriptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArgDescriptor")
+            .field("name", &self.name)
+            .field("type_name", &self.type_name)
+            .finish()
+    }
+}
+
+impl ArgDescriptor {
+    pub fn new<T: Encode>(name: &str) -> Self {
+        Self {
+            name: name.trim_start_matches('_').to_string(),
+            type_name: type_name::<T>().to_string(),
+            parse: parse_value::<T>,
+        }
+    }
+
+    /// Parses the argument from a string, returning its encoding.
+    pub fn parse(&self, value: &str) -> Result<Vec<u8>> {
+        (self.parse)(value)
+    }
+}
+
+/// Implemented by the `#[orga]` macro for types with `#[call]` methods.
+pub trait DescribeCalls {
+    fn describe_calls() -> Vec<MethodDescriptor>;
+}
+
+/// Implemented by the `#[orga]` macro for types with `#[query]` methods.
+pub trait DescribeQueries {
+    fn describe_queries() -> Vec<MethodDescriptor>;
+}
+
+pub(super) trait MaybeDescribeCalls {
+    fn maybe_describe_calls() -> Vec<MethodDescriptor>;
+}
+
+impl<T> MaybeDescribeCalls for T {
+    default fn maybe_describe_calls() -> Vec<MethodDescriptor> {
+        vec![]
+    }
+}
+
+impl<T: DescribeCalls> MaybeDescribeCalls for T {
+    fn maybe_describe_calls() -> Vec<MethodDescriptor> {
+        T::describe_calls()
+    }
+}
+
+pub(super) trait MaybeDescribeQueries {
+    fn maybe_describe_queries() -> Vec<MethodDescriptor>;
+}
+
+impl<T> MaybeDescribeQueries for T {
+    default fn maybe_describe_queries() -> Vec<MethodDescriptor> {
+        vec![]
+    }
+}
+
+impl<T: DescribeQueries> MaybeDescribeQueries for T {
+    fn maybe_describe_queries() -> Vec<MethodDescriptor> {
+        T::describe_queries()
+    }
+}
+
+/// Parses a value from a string, using its `FromStr` implementation or
+/// otherwise parsing it as JSON, and returns its encoding.
+pub fn parse_value<T: Encode>(value: &str) -> Result<Vec<u8>> {
+    Ok(parse_str::<T>(value)?.encode()?)
+}
+
+/// Parses a state value from a string like [parse_value], returning its
+/// encoding as written by `State::flush`.
+pub(super) fn parse_state<T: State>(value: &str) -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    parse_str::<T>(value)?.flush(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn parse_str<T>(value: &str) -> Result<T> {
+    T::maybe_from_str(value)
+        .or_else(|| T::maybe_from_json(value))
+        .ok_or_else(|| Error::App(format!("Cannot parse {}", type_name::<T>())))?
+        .map_err(|_| Error::App(format!("Invalid {}: {}", type_name::<T>(), value)))
+}
+
+trait MaybeFromStr: Sized {
+    fn maybe_from_str(value: &str) -> Option<Result<Self>>;
+}
+
+impl<T> MaybeFromStr for T {
+    default fn maybe_from_str(_: &str) -> Option<Result<Self>> {
+        None
+    }
+}
+
+impl<T: FromStr> MaybeFromStr for T {
+    fn maybe_from_str(value: &str) -> Option<Result<Self>> {
+        match T::from_str(value) {
+            Ok(value) => Some(Ok(value)),
+            // fall back to JSON if the string is not in the FromStr format
+            Err(_) => T::maybe_from_json(value)
+                .or_else(|| Some(Err(Error::App(format!("Invalid {}", type_name::<T>()))))),
+        }
+    }
+}
+
+trait MaybeFromJson: Sized {
+    fn maybe_from_json(value: &str) -> Option<Result<Self>>;
+}
+
+impl<T> MaybeFromJson for T {
+    default fn maybe_from_json(_: &str) -> Option<Result<Self>> {
+        None
+    }
+}
+
+impl<T: DeserializeOwned> MaybeFromJson for T {
+    fn maybe_from_json(value: &str) -> Option<Result<Self>> {
+        Some(serde_json::from_str(value).map_err(Into::into))
+    }
+}
+
+/// Formats the value returned by a query method for display, using its
+/// `Display` or `Debug` implementation. Errors returned by the query are
+/// propagated so missing state can be fetched by the client.
+pub fn format_value<T>(value: T) -> Result<String> {
+    FormatValue::format_value(value)
+}
+
+trait FormatValue {
+    fn format_value(self) -> Result<String>;
+}
+
+impl<T> FormatValue for T {
+    default fn format_value(self) -> Result<String> {
+        Ok(MaybeDisplay::maybe_to_string(&DisplayWrapper(&self))
+            .or_else(|| MaybeDebug::maybe_debug(&DebugWrapper(&self), true))
+            .unwrap_or_else(|| format!("<{}>", type_name::<T>())))
+    }
+}
+
+impl<T> FormatValue for Result<T> {
+    fn format_value(self) -> Result<String> {
+        format_value(self?)
+    }
+}
@@ -0,0 +1,592 @@
+//! Generates TypeScript bindings for building and sending an app's calls and
+//! queries from the browser, from the app's [Descriptor].
+//!
+//! The bindings are usually written from a build script or test of the app's
+//! crate, e.g. `typescript::write::<App>("web/src/app.ts")`. Argument types
+//! with no TypeScript encoder are taken as `Uint8Array`s of their encoding.
+//!
+//! Queries are run and nonces fetched by the app's wasm client, through a
+//! [JsClient] passed to the generated `Client`.
+
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::future_to_promise;
+
+use super::{err_to_js, Children, Describe, Descriptor, KeyOp, MethodDescriptor};
+use crate::abci::App;
+use crate::call::Call;
+use crate::client::{exec, AppClient};
+use crate::coins::Address;
+use crate::plugins::{ABCIPlugin, ConvertSdkTx, DefaultPlugins, PaidCall};
+use crate::query::Query;
+use crate::state::State;
+use crate::Result;
+
+const RUNTIME: &str = include_str!("typescript/runtime.ts");
+
+/// Generates the TypeScript bindings for the app `T`.
+pub fn generate<T: Describe>() -> String {
+    let desc = T::describe();
+
+    let mut out = format!(
+        "// Generated by orga from the descriptor of `{}`. Do not edit.\n\n",
+        desc.type_name
+    );
+    out.push_str(RUNTIME);
+
+    out.push_str("\n/** Builds calls to the app, to pass to `Client.call`. */\n");
+    out.push_str("export const calls = ");
+    out.push_str(
+        &methods_object(&desc, MethodKind::Call, vec![], 0).unwrap_or_else(|| "{}".into()),
+    );
+    out.push_str(";\n");
+
+    out.push_str("\n/** Builds queries on the app, to pass to `Client.query`. */\n");
+    out.push_str("export const queries = ");
+    out.push_str(
+        &methods_object(&desc, MethodKind::Query, vec![], 0).unwrap_or_else(|| "{}".into()),
+    );
+    out.push_str(";\n");
+
+    out
+}
+
+/// Writes the TypeScript bindings for the app `T` to `path`, unless the file
+/// is already up to date so build scripts do not trigger rebuilds.
+pub fn write<T: Describe, P: AsRef<Path>>(path: P) -> Result<()> {
+    let bindings = generate::<T>();
+    if std::fs::read_to_string(&path).ok().as_deref() == Some(bindings.as_str()) {
+        return Ok(());
+    }
+
+    if let Some(parent) = path.as_ref().parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, bindings)?;
+
+    Ok(())
+}
+
+#[derive(Clone, Copy)]
+enum MethodKind {
+    Call,
+    Query,
+}
+
+impl MethodKind {
+    fn methods(self, desc: &Descriptor) -> &[MethodDescriptor] {
+        match self {
+            MethodKind::Call => &desc.calls,
+            MethodKind::Query => &desc.queries,
+        }
+    }
+
+    fn prefix_offset(self) -> u8 {
+        match self {
+            MethodKind::Call => crate::call::PREFIX_OFFSET,
+            MethodKind::Query => crate::query::PREFIX_OFFSET,
+        }
+    }
+}
+
+/// Generates an object literal with a builder function for each of the
+/// type's methods and a nested object for each of its fields with methods,
+/// returning `None` if there are none. `prefix` is the encoding of the field
+/// calls or queries leading to the type.
+fn methods_object(
+    desc: &Descriptor,
+    kind: MethodKind,
+    prefix: Vec<u8>,
+    depth: usize,
+) -> Option<String> {
+    let indent = "  ".repeat(depth + 1);
+    let methods = kind.methods(desc);
+    let mut entries = vec![];
+
+    for (i, method) in methods.iter().enumerate() {
+        let mut prefix = prefix.clone();
+        prefix.push(i as u8 + kind.prefix_offset());
+        entries.push(method_builder(method, &prefix, &indent));
+    }
+
+    if let Children::Named(children) = desc.children() {
+        for child in children {
+            // methods take precedence over fields with the same name
+            if methods.iter().any(|method| method.name == child.name) {
+                continue;
+            }
+            // fields without a store key prefix cannot be called or queried
+            let KeyOp::Append(ref child_prefix) = child.store_key else {
+                continue;
+            };
+            if child_prefix.is_empty() {
+                continue;
+            }
+
+            let child_prefix = [prefix.as_slice(), child_prefix.as_slice()].concat();
+            if let Some(object) = methods_object(&child.desc, kind, child_prefix, depth + 1) {
+                entries.push(format!("{}{}: {},\n", indent, child.name, object));
+            }
+        }
+    }
+
+    if entries.is_empty() {
+        return None;
+    }
+
+    Some(format!("{{\n{}{}}}", entries.concat(), "  ".repeat(depth)))
+}
+
+fn method_builder(method: &MethodDescriptor, prefix: &[u8], indent: &str) -> String {
+    let args: Vec<_> = method
+        .args
+        .iter()
+        .map(|arg| (ts_ident(&arg.name), TsType::parse(&arg.type_name)))
+        .collect();
+
+    let params = args
+        .iter()
+        .map(|(name, ty)| format!("{}: {}", name, ty.ts()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let prefix = prefix
+        .iter()
+        .map(|byte| format!("0x{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut out = format!(
+        "{}/** `{}({})` */\n",
+        indent,
+        method.name,
+        method
+            .args
+            .iter()
+            .map(|arg| format!("{}: {}", arg.name, arg.type_name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    out.push_str(&format!(
+        "{}{}: ({}): Uint8Array =>\n{}  build([{}], (e) => {{\n",
+        indent, method.name, params, indent, prefix
+    ));
+    for (name, ty) in args.iter() {
+        out.push_str(&format!("{}    {}(e, {});\n", indent, ty.encoder(), name));
+    }
+    out.push_str(&format!("{}  }}),\n", indent));
+
+    out
+}
+
+/// Renames argument names which are reserved words in TypeScript.
+fn ts_ident(name: &str) -> String {
+    const RESERVED: &[&str] = &[
+        "break",
+        "case",
+        "catch",
+        "class",
+        "const",
+        "continue",
+        "debugger",
+        "default",
+        "delete",
+        "do",
+        "else",
+        "enum",
+        "export",
+        "extends",
+        "false",
+        "finally",
+        "for",
+        "function",
+        "if",
+        "import",
+        "in",
+        "instanceof",
+        "new",
+        "null",
+        "return",
+        "super",
+        "switch",
+        "this",
+        "throw",
+        "true",
+        "try",
+        "typeof",
+        "var",
+        "void",
+        "while",
+        "with",
+        "e",
+    ];
+
+    if RESERVED.contains(&name) {
+        format!("{}_", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// The TypeScript representation of a Rust type, parsed from its type name.
+#[derive(Clone, Debug, PartialEq)]
+enum TsType {
+    Int { bits: u32, signed: bool },
+    Bool,
+    Address,
+    Bytes,
+    FixedBytes(usize),
+    Vec(Box<TsType>),
+    LengthVec(Box<TsType>, Box<TsType>),
+    Array(Box<TsType>, usize),
+    Option(Box<TsType>),
+    Tuple(Vec<TsType>),
+    Raw,
+}
+
+impl TsType {
+    fn parse(type_name: &str) -> Self {
+        let type_name = type_name.trim();
+
+        if let Some(inner) = type_name
+            .strip_prefix('(')
+            .and_then(|t| t.strip_suffix(')'))
+        {
+            return TsType::Tuple(split_args(inner).into_iter().map(Self::parse).collect());
+        }
+
+        if let Some(inner) = type_name
+            .strip_prefix('[')
+            .and_then(|t| t.strip_suffix(']'))
+        {
+            let Some((item, len)) = inner.rsplit_once(';') else {
+                return TsType::Raw;
+            };
+            let Ok(len) = len.trim().parse() else {
+                return TsType::Raw;
+            };
+            return match Self::parse(item) {
+                TsType::Int {
+                    bits: 8,
+                    signed: false,
+                } => TsType::FixedBytes(len),
+                item => TsType::Array(Box::new(item), len),
+            };
+        }
+
+        let (path, args) = match type_name.find('<') {
+            Some(start) if type_name.ends_with('>') => (
+                &type_name[..start],
+                split_args(&type_name[start + 1..type_name.len() - 1]),
+            ),
+            _ => (type_name, vec![]),
+        };
+        let name = path.rsplit("::").next().unwrap_or(path);
+        let arg = |i: usize| Box::new(Self::parse(args[i]));
+
+        match (name, args.len()) {
+            ("u8" | "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32" | "i64" | "i128", 0) => {
+                TsType::Int {
+                    bits: name[1..].parse().unwrap(),
+                    signed: name.starts_with('i'),
+                }
+            }
+            ("bool", 0) => TsType::Bool,
+            ("Address", 0) if path.starts_with("orga::") => TsType::Address,
+            ("Amount", 0) if path.starts_with("orga::") => TsType::Int {
+                bits: 64,
+                signed: false,
+            },
+            ("Vec", 1) => match *arg(0) {
+                TsType::Int {
+                    bits: 8,
+                    signed: false,
+                } => TsType::Bytes,
+                item => TsType::Vec(Box::new(item)),
+            },
+            ("LengthVec", 2) => TsType::LengthVec(arg(0), arg(1)),
+            ("Option", 1) => TsType::Option(arg(0)),
+            _ => TsType::Raw,
+        }
+    }
+
+    fn ts(&self) -> String {
+        match self {
+            TsType::Int { bits, .. } if *bits <= 32 => "number".into(),
+            TsType::Int { .. } => "bigint".into(),
+            TsType::Bool => "boolean".into(),
+            TsType::Address => "Address".into(),
+            TsType::Bytes | TsType::FixedBytes(_) | TsType::Raw => "Uint8Array".into(),
+            TsType::Vec(item) | TsType::LengthVec(_, item) | TsType::Array(item, _) => match **item
+            {
+                TsType::Option(_) => format!("({})[]", item.ts()),
+                _ => format!("{}[]", item.ts()),
+            },
+            TsType::Option(item) => format!("{} | null", item.ts()),
+            TsType::Tuple(items) if items.is_empty() => "null".into(),
+            TsType::Tuple(items) => format!(
+                "[{}]",
+                items.iter().map(Self::ts).collect::<Vec<_>>().join(", ")
+            ),
+        }
+    }
+
+    fn encoder(&self) -> String {
+        match self {
+            TsType::Int { bits, signed } => {
+                format!("enc.{}{}", if *signed { "i" } else { "u" }, bits)
+            }
+            TsType::Bool => "enc.bool".into(),
+            TsType::Address => "enc.address".into(),
+            TsType::Bytes => "enc.bytes".into(),
+            TsType::FixedBytes(len) => format!("enc.fixedBytes({})", len),
+            TsType::Vec(item) => format!("enc.vec({})", item.encoder()),
+            TsType::LengthVec(len, item) => {
+                format!("enc.lengthVec({}, {})", len.encoder(), item.encoder())
+            }
+            TsType::Array(item, len) => format!("enc.array({}, {})", item.encoder(), len),
+            TsType::Option(item) => format!("enc.option({})", item.encoder()),
+            TsType::Tuple(items) => format!(
+                "enc.tuple({})",
+                items
+                    .iter()
+                    .map(Self::encoder)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            TsType::Raw => "enc.raw".into(),
+        }
+    }
+}
+
+/// Splits a list of generic arguments or tuple items at top-level commas.
+fn split_args(list: &str) -> Vec<&str> {
+    let mut args = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, c) in list.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                args.push(list[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(list[start..].trim());
+    args.retain(|arg| !arg.is_empty());
+
+    args
+}
+
+type LocalFuture<T> = Pin<Box<dyn Future<Output = Result<T>>>>;
+
+/// The methods of an [AppClient] used by [JsClient], which cannot be generic.
+trait BytesClient {
+    fn query_bytes(self: Rc<Self>, query: Vec<u8>) -> LocalFuture<String>;
+
+    fn nonce(self: Rc<Self>, address: Address) -> LocalFuture<u64>;
+}
+
+impl<T, U, Transport, Symbol, Wallet> BytesClient for AppClient<T, U, Transport, Symbol, Wallet>
+where
+    Transport: exec::Transport<ABCIPlugin<DefaultPlugins<Symbol, T>>> + 'static,
+    T: App
+        + Call
+        + State
+        + Query
+        + Default
+        + Describe
+        + ConvertSdkTx<Output = PaidCall<T::Call>>
+        + 'static,
+    U: Describe + 'static,
+    Wallet: crate::client::Wallet + Clone + 'static,
+    Symbol: crate::coins::Symbol,
+{
+    fn query_bytes(self: Rc<Self>, query: Vec<u8>) -> LocalFuture<String> {
+        Box::pin(async move { AppClient::query_bytes(&self, &query).await })
+    }
+
+    fn nonce(self: Rc<Self>, address: Address) -> LocalFuture<u64> {
+        Box::pin(async move { AppClient::nonce(&self, address).await })
+    }
+}
+
+/// Exposes an [AppClient] to the `Client` of the generated bindings, e.g.
+/// `new Client(rpcUrl, { runQuery: (q) => js.runQuery(q), fetchNonce: (a) =>
+/// js.fetchNonce(a) })`.
+///
+/// `wasm_bindgen` cannot export generic functions, so apps export their own
+/// function returning a [JsClient] for their client from their wasm build.
+#[wasm_bindgen]
+pub struct JsClient(Rc<dyn BytesClient>);
+
+impl JsClient {
+    pub fn new<T, U, Transport, Symbol, Wallet>(
+        client: AppClient<T, U, Transport, Symbol, Wallet>,
+    ) -> Self
+    where
+        Transport: exec::Transport<ABCIPlugin<DefaultPlugins<Symbol, T>>> + 'static,
+        T: App
+            + Call
+            + State
+            + Query
+            + Default
+            + Describe
+            + ConvertSdkTx<Output = PaidCall<T::Call>>
+            + 'static,
+        U: Describe + 'static,
+        Wallet: crate::client::Wallet + Clone + 'static,
+        Symbol: crate::coins::Symbol,
+    {
+        Self(Rc::new(client))
+    }
+}
+
+#[wasm_bindgen]
+impl JsClient {
+    /// Runs a query built by the generated `queries`, resolving to its result
+    /// formatted for display.
+    #[wasm_bindgen(js_name = runQuery)]
+    pub fn run_query(&self, query: Vec<u8>) -> js_sys::Promise {
+        let res = self.0.clone().query_bytes(query);
+        future_to_promise(async move { res.await.map(JsValue::from).map_err(err_to_js) })
+    }
+
+    /// Resolves to the last nonce the address used on chain, as a `bigint`.
+    #[wasm_bindgen(js_name = fetchNonce)]
+    pub fn fetch_nonce(&self, address: String) -> js_sys::Promise {
+        let client = self.0.clone();
+        future_to_promise(async move {
+            let address: Address = address.parse().map_err(err_to_js)?;
+            client
+                .nonce(address)
+                .await
+                .map(JsValue::from)
+                .map_err(err_to_js)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::call::{build_call, Call};
+    use crate::coins::{Address, BECH32_PREFIX};
+    use crate::collections::Map;
+    use crate::encoding::{Decode, Encode};
+    use crate::orga;
+    use crate::plugins::sdk_compat::sdk::NATIVE_CALL_MSG_TYPE;
+
+    #[orga]
+    pub struct Counter {
+        pub count: u64,
+        pub by_key: Map<u32, u64>,
+    }
+
+    #[orga]
+    impl Counter {
+        #[call]
+        pub fn increment(&mut self, n: u64) -> crate::Result<()> {
+            self.count += n;
+            Ok(())
+        }
+
+        #[call]
+        pub fn set(&mut self, key: u32, value: Option<u64>, _to: Address) -> crate::Result<()> {
+            self.by_key.insert(key, value.unwrap_or_default())
+        }
+
+        #[query]
+        pub fn get(&self, key: u32) -> crate::Result<Option<u64>> {
+            Ok(self.by_key.get(key)?.map(|v| *v))
+        }
+    }
+
+    #[orga]
+    pub struct Counters {
+        #[call]
+        pub a: Counter,
+        #[call]
+        #[state(prefix(7))]
+        pub b: Counter,
+    }
+
+    #[test]
+    fn type_names() {
+        let parse = |ty: &str| TsType::parse(ty);
+        assert_eq!(parse("u64").ts(), "bigint");
+        assert_eq!(parse("i32").encoder(), "enc.i32");
+        assert_eq!(parse("alloc::vec::Vec<u8>").encoder(), "enc.bytes");
+        assert_eq!(parse("[u8; 32]").encoder(), "enc.fixedBytes(32)");
+        assert_eq!(
+            parse("core::option::Option<(u32, orga::coins::Address)>").ts(),
+            "[number, Address] | null"
+        );
+        assert_eq!(
+            parse("orga::encoding::LengthVec<u8, core::option::Option<u16>>").encoder(),
+            "enc.lengthVec(enc.u8, enc.option(enc.u16))"
+        );
+        assert_eq!(parse("my_app::Thing<u8>").encoder(), "enc.raw");
+    }
+
+    #[test]
+    fn bindings() -> crate::Result<()> {
+        let bindings = generate::<Counters>();
+        let app = Counters::default();
+
+        // the prefixes in the bindings match the encodings of the calls
+        let call = build_call!(app.b.increment(5)).encode()?;
+        assert_eq!(call[..2], [0x07, 0x40]);
+        assert!(bindings.contains(
+            "    increment: (n: bigint): Uint8Array =>\n      build([0x07, 0x40], (e) => {\n        enc.u64(e, n);\n"
+        ));
+        let call = <Counters as Call>::Call::decode(
+            [&[0x07, 0x41][..], &3u32.encode()?, &[0], &[0; 20]]
+                .concat()
+                .as_slice(),
+        );
+        assert!(call.is_ok());
+        assert!(bindings
+            .contains("set: (key: number, value: bigint | null, to: Address): Uint8Array =>"));
+
+        assert!(bindings.contains("export const queries = {\n  a: {\n"));
+        assert!(bindings.contains("build([0x07, 0x80], (e) => {"));
+
+        // the runtime's constants match the app's
+        assert!(bindings.contains(&format!(
+            "export const BECH32_PREFIX = {:?};",
+            BECH32_PREFIX
+        )));
+        assert!(bindings.contains(&format!(
+            "export const NATIVE_CALL_MSG_TYPE = {:?};",
+            NATIVE_CALL_MSG_TYPE
+        )));
+
+        Ok(())
+    }
+
+    #[test]
+    fn run_query_bytes() -> crate::Result<()> {
+        let desc = Counters::describe();
+        let mut app = Counters::default();
+        app.b.by_key.insert(3, 12)?;
+
+        let query = [&[0x07, 0x80][..], &3u32.encode()?].concat();
+        assert_eq!(desc.run_query_bytes(&app, &query)?, "Some(\n    12,\n)");
+
+        let query = [&[0x07, 0x80][..], &4u32.encode()?].concat();
+        assert_eq!(desc.run_query_bytes(&app, &query)?, "None");
+
+        assert!(desc.run_query_bytes(&app, &[0x07]).is_err());
+        assert!(desc.run_query_bytes(&app, &[0x07, 0x81]).is_err());
+
+        Ok(())
+    }
+}
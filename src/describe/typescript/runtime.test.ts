@@ -0,0 +1,126 @@
+// Tests for the runtime included in the generated bindings, run with e.g.
+// `npx tsx --test src/describe/typescript/runtime.test.ts`.
+
+import assert from "node:assert/strict";
+import { test } from "node:test";
+
+import { Client, Encoder, decodeAddress, enc, encodeAddress, type CallToSign, type Signer } from "./runtime";
+
+const encode = <T>(encoder: (e: Encoder, value: T) => void, value: T): number[] => {
+  const e = new Encoder();
+  encoder(e, value);
+  return [...e.finish()];
+};
+
+test("encodes integers as big-endian two's complement", () => {
+  assert.deepEqual(encode(enc.u16, 0x1234), [0x12, 0x34]);
+  assert.deepEqual(encode(enc.u64, 1n), [0, 0, 0, 0, 0, 0, 0, 1]);
+  assert.deepEqual(encode(enc.i8, -1), [0xff]);
+  assert.deepEqual(encode(enc.i16, -256), [0xff, 0x00]);
+  assert.throws(() => encode(enc.u8, 256), RangeError);
+  assert.throws(() => encode(enc.u8, -1), RangeError);
+  assert.throws(() => encode(enc.i8, 128), RangeError);
+});
+
+test("encodes options, tuples and length-prefixed vecs", () => {
+  assert.deepEqual(encode(enc.option(enc.u8), null), [0]);
+  assert.deepEqual(encode(enc.option(enc.u8), 7), [1, 7]);
+  assert.deepEqual(encode(enc.tuple(enc.u8, enc.bool), [1, true]), [1, 1]);
+  assert.deepEqual(encode(enc.lengthVec(enc.u8, enc.u16), [1, 2]), [2, 0, 1, 0, 2]);
+  assert.throws(() => encode(enc.fixedBytes(2), Uint8Array.from([1])), RangeError);
+});
+
+test("round-trips bech32 addresses", () => {
+  const bytes = Uint8Array.from({ length: 20 }, (_, i) => i);
+  const address = encodeAddress(bytes);
+  assert.ok(address.startsWith("oraibtc1"));
+  assert.deepEqual(decodeAddress(address), bytes);
+  assert.deepEqual(decodeAddress(address.toUpperCase()), bytes);
+
+  const corrupted = address.slice(0, -1) + (address.endsWith("q") ? "p" : "q");
+  assert.throws(() => decodeAddress(corrupted), /checksum/);
+  assert.throws(() => decodeAddress("oraibtc1"), /Invalid address/);
+});
+
+/** Answers broadcasts like a node which expects the given nonce next. */
+function mockNode(expected: { nonce: bigint }): bigint[] {
+  const broadcast: bigint[] = [];
+  globalThis.fetch = (async (_url: string, init: { body: string }) => {
+    const { method, params } = JSON.parse(init.body);
+    assert.equal(method, "broadcast_tx_commit");
+    const nonce = BigInt(new TextDecoder().decode(Uint8Array.from(atob(params.tx), (c) => c.charCodeAt(0))));
+    broadcast.push(nonce);
+
+    const ok = nonce === expected.nonce;
+    if (ok) {
+      expected.nonce += 1n;
+    }
+    const checkTx = ok
+      ? { code: 0, log: "" }
+      : { code: 1, log: `Nonce is not valid. Expected ${expected.nonce}-${expected.nonce + 999n}, got ${nonce}` };
+    return {
+      json: async () => ({ result: { check_tx: checkTx, deliver_tx: { code: 0, log: "" }, hash: "AB", height: "5" } }),
+    };
+  }) as any;
+  return broadcast;
+}
+
+/** Signs calls by encoding only their nonce, which the mock node reads back. */
+const signer: Signer = {
+  address: async () => "oraibtc1alice",
+  signTx: async (call: CallToSign) => new TextEncoder().encode(call.nonce.toString()),
+};
+
+test("fetches the nonce of the signer's first call and counts up from it", async () => {
+  const expected = { nonce: 8n };
+  const broadcast = mockNode(expected);
+  let fetches = 0;
+  const client = new Client("http://node", {
+    signer,
+    chainId: "test",
+    fetchNonce: async () => {
+      fetches += 1;
+      return expected.nonce - 1n;
+    },
+  });
+
+  assert.deepEqual(await client.call(new Uint8Array(), new Uint8Array()), { hash: "AB", height: 5 });
+  await client.call(new Uint8Array(), new Uint8Array());
+  assert.deepEqual(broadcast, [8n, 9n]);
+  assert.equal(fetches, 1);
+});
+
+test("re-fetches the nonce once when it is rejected", async () => {
+  const expected = { nonce: 3n };
+  const broadcast = mockNode(expected);
+  let fetches = 0;
+  const client = new Client("http://node", {
+    signer,
+    chainId: "test",
+    fetchNonce: async () => (fetches++ === 0 ? 0n : expected.nonce - 1n),
+  });
+
+  await client.call(new Uint8Array(), new Uint8Array());
+  assert.deepEqual(broadcast, [1n, 3n]);
+
+  // an explicit nonce is not retried
+  await assert.rejects(client.call(new Uint8Array(), new Uint8Array(), 1n), /Nonce is not valid/);
+});
+
+test("requires a nonce source for signed calls", async () => {
+  mockNode({ nonce: 1n });
+  const client = new Client("http://node", { signer, chainId: "test" });
+  await assert.rejects(client.call(new Uint8Array(), new Uint8Array()), /fetchNonce/);
+  assert.ok(await client.call(new Uint8Array(), new Uint8Array(), 1n));
+});
+
+test("runs queries through the runQuery option", async () => {
+  const client = new Client("http://node", { chainId: "test" });
+  await assert.rejects(client.query(Uint8Array.from([0x80])), /runQuery/);
+
+  const queried = new Client("http://node", {
+    chainId: "test",
+    runQuery: async (query) => `ran ${[...query]}`,
+  });
+  assert.equal(await queried.query(Uint8Array.from([0x07, 0x80])), "ran 7,128");
+});
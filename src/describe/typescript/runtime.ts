@@ -0,0 +1,463 @@
+/** The bech32 prefix of the app's addresses, `coins::BECH32_PREFIX`. */
+export const BECH32_PREFIX = "oraibtc";
+/** The type of amino messages carrying native calls, `sdk::NATIVE_CALL_MSG_TYPE`. */
+export const NATIVE_CALL_MSG_TYPE = "orga/NativeCall";
+
+export type Address = string;
+
+/** Writes values in the `ed` encoding used by orga apps. */
+export class Encoder {
+  private bytes: number[] = [];
+
+  push(...bytes: number[]): void {
+    for (const byte of bytes) {
+      this.bytes.push(byte & 0xff);
+    }
+  }
+
+  append(bytes: Uint8Array | number[]): void {
+    for (const byte of bytes) {
+      this.bytes.push(byte);
+    }
+  }
+
+  /** Writes an integer as big-endian bytes, in two's complement if signed. */
+  int(value: number | bigint, bits: number, signed: boolean): void {
+    let n = BigInt(value);
+    const range = 1n << BigInt(bits);
+    const min = signed ? -(range >> 1n) : 0n;
+    const max = signed ? range >> 1n : range;
+    if (n < min || n >= max) {
+      throw new RangeError(`${value} does not fit in ${signed ? "i" : "u"}${bits}`);
+    }
+    if (n < 0n) {
+      n += range;
+    }
+
+    const out = new Array<number>(bits / 8);
+    for (let i = out.length - 1; i >= 0; i--) {
+      out[i] = Number(n & 0xffn);
+      n >>= 8n;
+    }
+    this.append(out);
+  }
+
+  finish(): Uint8Array {
+    return Uint8Array.from(this.bytes);
+  }
+}
+
+export type Enc<T> = (e: Encoder, value: T) => void;
+
+const int =
+  (bits: number, signed: boolean): Enc<number | bigint> =>
+  (e, value) =>
+    e.int(value, bits, signed);
+
+/** Encoders for the types of call and query arguments. */
+export const enc = {
+  u8: int(8, false),
+  u16: int(16, false),
+  u32: int(32, false),
+  u64: int(64, false),
+  u128: int(128, false),
+  i8: int(8, true),
+  i16: int(16, true),
+  i32: int(32, true),
+  i64: int(64, true),
+  i128: int(128, true),
+  bool: ((e, value) => e.push(value ? 1 : 0)) as Enc<boolean>,
+  address: ((e, value) => e.append(decodeAddress(value))) as Enc<Address>,
+  /** A `Vec<u8>`, which is only valid as the last value of an encoding. */
+  bytes: ((e, value) => e.append(value)) as Enc<Uint8Array>,
+  /** A value of a type without a generated encoder, already encoded. */
+  raw: ((e, value) => e.append(value)) as Enc<Uint8Array>,
+  fixedBytes:
+    (length: number): Enc<Uint8Array> =>
+    (e, value) => {
+      if (value.length !== length) {
+        throw new RangeError(`Expected ${length} bytes, got ${value.length}`);
+      }
+      e.append(value);
+    },
+  array:
+    <T>(item: Enc<T>, length: number): Enc<T[]> =>
+    (e, value) => {
+      if (value.length !== length) {
+        throw new RangeError(`Expected ${length} items, got ${value.length}`);
+      }
+      value.forEach((v) => item(e, v));
+    },
+  /** A `Vec<T>`, which is only valid as the last value of an encoding. */
+  vec:
+    <T>(item: Enc<T>): Enc<T[]> =>
+    (e, value) =>
+      value.forEach((v) => item(e, v)),
+  lengthVec:
+    <T>(length: Enc<number>, item: Enc<T>): Enc<T[]> =>
+    (e, value) => {
+      length(e, value.length);
+      value.forEach((v) => item(e, v));
+    },
+  option:
+    <T>(item: Enc<T>): Enc<T | null> =>
+    (e, value) => {
+      if (value === null || value === undefined) {
+        e.push(0);
+      } else {
+        e.push(1);
+        item(e, value);
+      }
+    },
+  tuple:
+    (...items: Enc<any>[]): Enc<any[]> =>
+    (e, value) =>
+      items.forEach((item, i) => item(e, value[i])),
+};
+
+/** Builds a call or query from the prefix selecting its method and the method's arguments. */
+function build(prefix: number[], args: (e: Encoder) => void): Uint8Array {
+  const e = new Encoder();
+  e.append(prefix);
+  args(e);
+  return e.finish();
+}
+
+const BECH32_CHARSET = "qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+function bech32Polymod(values: number[]): number {
+  const generator = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+  let checksum = 1;
+  for (const value of values) {
+    const top = checksum >>> 25;
+    checksum = ((checksum & 0x1ffffff) << 5) ^ value;
+    for (let i = 0; i < 5; i++) {
+      if ((top >>> i) & 1) {
+        checksum ^= generator[i];
+      }
+    }
+  }
+  return checksum;
+}
+
+function bech32HrpExpand(hrp: string): number[] {
+  const codes = [...hrp].map((c) => c.charCodeAt(0));
+  return [...codes.map((c) => c >> 5), 0, ...codes.map((c) => c & 31)];
+}
+
+function convertBits(data: number[], from: number, to: number, pad: boolean): number[] {
+  let acc = 0;
+  let bits = 0;
+  const out: number[] = [];
+  const max = (1 << to) - 1;
+  for (const value of data) {
+    acc = ((acc << from) | value) & 0xffff;
+    bits += from;
+    while (bits >= to) {
+      bits -= to;
+      out.push((acc >> bits) & max);
+    }
+  }
+  if (pad && bits > 0) {
+    out.push((acc << (to - bits)) & max);
+  } else if (!pad && (bits >= from || (acc << (to - bits)) & max)) {
+    throw new Error("Invalid bech32 padding");
+  }
+  return out;
+}
+
+/** Decodes a bech32 address into its 20 bytes. */
+export function decodeAddress(address: string): Uint8Array {
+  const lower = address.toLowerCase();
+  const separator = lower.lastIndexOf("1");
+  if (separator < 1 || separator + 7 > lower.length) {
+    throw new Error(`Invalid address: ${address}`);
+  }
+
+  const hrp = lower.slice(0, separator);
+  const data = [...lower.slice(separator + 1)].map((c) => {
+    const value = BECH32_CHARSET.indexOf(c);
+    if (value === -1) {
+      throw new Error(`Invalid address: ${address}`);
+    }
+    return value;
+  });
+  if (bech32Polymod([...bech32HrpExpand(hrp), ...data]) !== 1) {
+    throw new Error(`Invalid address checksum: ${address}`);
+  }
+
+  const bytes = convertBits(data.slice(0, -6), 5, 8, false);
+  if (bytes.length !== 20) {
+    throw new Error(`Invalid address length: ${address}`);
+  }
+  return Uint8Array.from(bytes);
+}
+
+/** Encodes 20 address bytes as a bech32 address with the app's prefix. */
+export function encodeAddress(bytes: Uint8Array, prefix: string = BECH32_PREFIX): Address {
+  const data = convertBits([...bytes], 8, 5, true);
+  const values = [...bech32HrpExpand(prefix), ...data, 0, 0, 0, 0, 0, 0];
+  const polymod = bech32Polymod(values) ^ 1;
+  const checksum = [0, 1, 2, 3, 4, 5].map((i) => (polymod >>> (5 * (5 - i))) & 31);
+  return prefix + "1" + [...data, ...checksum].map((v) => BECH32_CHARSET[v]).join("");
+}
+
+function toBase64(bytes: Uint8Array): string {
+  return btoa(String.fromCharCode(...bytes));
+}
+
+function fromBase64(value: string): Uint8Array {
+  return Uint8Array.from(atob(value), (c) => c.charCodeAt(0));
+}
+
+function toHex(bytes: Uint8Array): string {
+  return [...bytes].map((b) => b.toString(16).padStart(2, "0")).join("");
+}
+
+function fromHex(value: string): Uint8Array {
+  const hex = value.startsWith("0x") ? value.slice(2) : value;
+  return Uint8Array.from(hex.match(/../g) ?? [], (b) => parseInt(b, 16));
+}
+
+const NATIVE_CALL_FLAG = 0xff;
+const SIG_TYPE_NATIVE = 0;
+const SIG_TYPE_ADR36 = 1;
+
+/** A call to sign, paid for by the `payer` call. */
+export interface CallToSign {
+  chainId: string;
+  nonce: bigint;
+  payer: Uint8Array;
+  paid: Uint8Array;
+}
+
+/** Signs calls with a browser wallet, returning the transaction to broadcast. */
+export interface Signer {
+  address(): Promise<Address>;
+  signTx(call: CallToSign): Promise<Uint8Array>;
+}
+
+/** The bytes signed for a native call: the chain ID, nonce, and paid call. */
+function nativeCallBytes(chainId: string, nonce: bigint | null, payer: Uint8Array, paid: Uint8Array): Uint8Array {
+  const e = new Encoder();
+  e.append(new TextEncoder().encode(chainId));
+  enc.option(enc.u64)(e, nonce);
+  // PayableCall::Paid
+  e.push(0);
+  enc.u32(e, payer.length);
+  e.append(payer);
+  enc.u32(e, paid.length);
+  e.append(paid);
+  return e.finish();
+}
+
+function nativeTx(callBytes: Uint8Array, sigType: number, signature: Uint8Array | null, pubkey: Uint8Array | null): Uint8Array {
+  const e = new Encoder();
+  e.push(NATIVE_CALL_FLAG);
+  enc.option(enc.fixedBytes(64))(e, signature);
+  enc.option(enc.fixedBytes(33))(e, pubkey);
+  e.push(sigType);
+  e.append(callBytes);
+  return e.finish();
+}
+
+/** Signs calls with Keplr, using ADR-36 arbitrary signing of native calls. */
+export function keplrSigner(chainId: string, keplr: any = (globalThis as any).keplr): Signer {
+  return {
+    async address() {
+      const key = await keplr.getKey(chainId);
+      return key.bech32Address;
+    },
+
+    async signTx(call) {
+      const address = await this.address();
+      const callBytes = nativeCallBytes(call.chainId, call.nonce, call.payer, call.paid);
+      const res = await keplr.signArbitrary(chainId, address, callBytes);
+      return nativeTx(callBytes, SIG_TYPE_ADR36, fromBase64(res.signature), fromBase64(res.pub_key.value));
+    },
+  };
+}
+
+/**
+ * Signs calls with MetaMask, as amino transactions of `NATIVE_CALL_MSG_TYPE`
+ * messages signed with `personal_sign`, which `PayablePlugin` converts back
+ * into the native calls.
+ *
+ * MetaMask does not expose public keys, so `recoverPubkey` must recover the
+ * compressed secp256k1 public key from the signed message and the 65-byte
+ * signature, e.g. with `SigningKey.recoverPublicKey` from ethers.
+ */
+export function metamaskSigner(
+  recoverPubkey: (message: Uint8Array, signature: Uint8Array) => Uint8Array | Promise<Uint8Array>,
+  ethereum: any = (globalThis as any).ethereum,
+): Signer {
+  const account = async (): Promise<string> => {
+    const accounts = await ethereum.request({ method: "eth_requestAccounts" });
+    return accounts[0];
+  };
+
+  return {
+    async address() {
+      return encodeAddress(fromHex(await account()));
+    },
+
+    async signTx(call) {
+      const fee = { amount: [], gas: "0" };
+      // keys are in the order the node serializes them when verifying
+      const msg = {
+        type: NATIVE_CALL_MSG_TYPE,
+        value: { paid: toBase64(call.paid), payer: toBase64(call.payer) },
+      };
+      const signDoc = {
+        account_number: "0",
+        chain_id: call.chainId,
+        fee,
+        memo: "",
+        msgs: [msg],
+        sequence: call.nonce.toString(),
+      };
+      const signBytes = new TextEncoder().encode(JSON.stringify(signDoc));
+
+      const signature = fromHex(
+        await ethereum.request({
+          method: "personal_sign",
+          params: ["0x" + toHex(signBytes), await account()],
+        }),
+      );
+      const pubkey = await recoverPubkey(signBytes, signature);
+
+      const tx = {
+        msg: [msg],
+        fee,
+        memo: "",
+        signatures: [
+          {
+            pub_key: { type: "tendermint/PubKeySecp256k1", value: toBase64(pubkey) },
+            signature: toBase64(signature.slice(0, 64)),
+            type: "eth",
+          },
+        ],
+      };
+      return new TextEncoder().encode(JSON.stringify(tx));
+    },
+  };
+}
+
+export interface ClientOptions {
+  /** Signs calls; calls are sent unsigned if not set. */
+  signer?: Signer;
+  /** The chain ID, read from the node if not set. */
+  chainId?: string;
+  /**
+   * Runs an encoded query and returns its formatted result. Queries are
+   * verified against state proofs, so they are run by the app's wasm client,
+   * e.g. with `JsClient.runQuery`.
+   */
+  runQuery?: (query: Uint8Array) => Promise<string>;
+  /**
+   * Returns the last nonce an address used on chain, e.g. with
+   * `JsClient.fetchNonce`. Signed calls without an explicit nonce need this.
+   */
+  fetchNonce?: (address: Address) => Promise<bigint>;
+}
+
+export interface TxResult {
+  hash: string;
+  height: number;
+}
+
+/** Broadcasts calls to a node over Tendermint's JSON-RPC interface. */
+export class Client {
+  private nonces = new Map<Address, bigint>();
+
+  constructor(private rpcUrl: string, private options: ClientOptions = {}) {}
+
+  private async rpc(method: string, params: object): Promise<any> {
+    const res = await fetch(this.rpcUrl, {
+      method: "POST",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify({ jsonrpc: "2.0", id: 0, method, params }),
+    });
+    const body = await res.json();
+    if (body.error) {
+      throw new Error(body.error.data ?? body.error.message);
+    }
+    return body.result;
+  }
+
+  async chainId(): Promise<string> {
+    if (this.options.chainId === undefined) {
+      const status = await this.rpc("status", {});
+      this.options.chainId = status.node_info.network as string;
+    }
+    return this.options.chainId;
+  }
+
+  /**
+   * Signs and broadcasts the `paid` call, paid for by the `payer` call, and
+   * waits for it to be committed.
+   *
+   * Signed calls need the signer's next nonce. If `nonce` is not given, it is
+   * fetched from the chain with the `fetchNonce` option the first time an
+   * address signs, then counted up from the last nonce used. If the call is
+   * rejected for its nonce, e.g. because another client signed with the same
+   * key, it is retried once with a nonce fetched again.
+   */
+  async call(payer: Uint8Array, paid: Uint8Array, nonce?: bigint): Promise<TxResult> {
+    const chainId = await this.chainId();
+    const signer = this.options.signer;
+    if (signer === undefined) {
+      return this.broadcast(nativeTx(nativeCallBytes(chainId, null, payer, paid), SIG_TYPE_NATIVE, null, null));
+    }
+
+    const address = await signer.address();
+    const send = async (nonce: bigint) => {
+      const res = await this.broadcast(await signer.signTx({ chainId, nonce, payer, paid }));
+      this.nonces.set(address, nonce);
+      return res;
+    };
+
+    if (nonce !== undefined) {
+      return send(nonce);
+    }
+    try {
+      return await send(await this.nextNonce(address));
+    } catch (err) {
+      if (!/Nonce is not valid/.test(String(err))) {
+        throw err;
+      }
+      this.nonces.delete(address);
+      return send(await this.nextNonce(address));
+    }
+  }
+
+  private async nextNonce(address: Address): Promise<bigint> {
+    let last = this.nonces.get(address);
+    if (last === undefined) {
+      if (this.options.fetchNonce === undefined) {
+        throw new Error("Signed calls require a nonce or the fetchNonce option");
+      }
+      last = await this.options.fetchNonce(address);
+      this.nonces.set(address, last);
+    }
+    return last + 1n;
+  }
+
+  async broadcast(tx: Uint8Array): Promise<TxResult> {
+    const res = await this.rpc("broadcast_tx_commit", { tx: toBase64(tx) });
+    for (const result of [res.check_tx, res.deliver_tx]) {
+      if (result.code !== 0) {
+        throw new Error(result.log);
+      }
+    }
+    return { hash: res.hash, height: Number(res.height) };
+  }
+
+  /** Runs a query built by `queries` with the `runQuery` option. */
+  async query(query: Uint8Array): Promise<string> {
+    if (this.options.runQuery === undefined) {
+      throw new Error("Running queries requires the runQuery option");
+    }
+    return this.options.runQuery(query);
+  }
+}
@@ -13,16 +13,32 @@ pub enum Error {
     App(String),
     #[error("Call Error: {0}")]
     Call(String),
+    #[error("CheckTx Error: code {code} ({codespace}): {log}")]
+    CheckTx {
+        code: u32,
+        codespace: String,
+        log: String,
+    },
     #[error("Client Error: {0}")]
     Client(String),
     #[error("Coins Error: {0}")]
     Coins(String),
+    #[error("Config Error: {0}")]
+    Config(String),
     #[error(transparent)]
     Dalek(#[from] ed25519_dalek::ed25519::Error),
     #[error(transparent)]
     Decimal(#[from] rust_decimal::Error),
     #[error("Divide by Zero Error: Cannot divide by zero")]
     DivideByZero,
+    #[error("DeliverTx Error: code {code} ({codespace}) in tx {hash} at height {height}: {log}")]
+    DeliverTx {
+        code: u32,
+        codespace: String,
+        log: String,
+        hash: String,
+        height: u64,
+    },
     #[error("Downcast Error: {0}")]
     Downcast(String),
     #[error(transparent)]
@@ -81,5 +97,29 @@ pub enum Error {
     Unknown,
 }
 
+impl Error {
+    /// Returns the ABCI code of a failed transaction.
+    pub fn abci_code(&self) -> Option<u32> {
+        match self {
+            Error::CheckTx { code, .. } | Error::DeliverTx { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the transaction was rejected for having an invalid
+    /// nonce, so signing it again with a nonce re-synced from the chain may
+    /// succeed.
+    ///
+    /// The app reports every failed call with the same ABCI code, so nonce
+    /// mismatches are recognized by their log. Other failures, including
+    /// those in `DeliverTx`, will fail the same way if retried.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Nonce(log) | Error::CheckTx { log, .. } => log.contains("Nonce is not valid"),
+            _ => false,
+        }
+    }
+}
+
 /// A result type bound to the standard orga error type.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -1,5 +1,8 @@
 use crate::{
-    coins::{Address, Amount, Coin, Symbol, BECH32_PREFIX},
+    coins::{
+        supply::{record_ibc_burn, record_ibc_mint},
+        Address, Amount, Coin, Symbol, BECH32_PREFIX,
+    },
     collections::Map,
     describe::{Builder, Describe},
     encoding::LengthVec,
@@ -170,6 +173,7 @@ impl TokenTransferExecutionContext for Transfer {
     ) -> Result<(), TokenTransferError> {
         let denom: Denom = coin.denom.clone().try_into()?;
         let amount: Amount = coin.amount.try_into()?;
+        record_ibc_burn(&denom, amount);
 
         let mut denom_balances = self.accounts.entry(denom)?.or_default()?;
 
@@ -206,6 +210,7 @@ impl TokenTransferExecutionContext for Transfer {
     ) -> Result<(), TokenTransferError> {
         let denom: Denom = coin.denom.clone().try_into()?;
         let amount: Amount = coin.amount.try_into()?;
+        record_ibc_mint(&denom, amount);
 
         let mut denom_balances = self.accounts.entry(denom)?.or_default()?;
 
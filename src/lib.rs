@@ -23,6 +23,10 @@ pub mod abci;
 
 pub mod call;
 
+/// Command-line interface generated from an app's calls and queries.
+#[cfg(feature = "cli")]
+pub mod cli;
+
 pub mod client;
 
 /// Data structures which implement the [`state::State`](state/trait.State.html)
@@ -0,0 +1,245 @@
+use crate::encoding::{Decode, Encode, Terminated};
+use crate::store::PartialMapStore;
+use crate::{Error, Result};
+use merk::proofs::query::{verify, Map as ProofMap, Query};
+use merk::Hash;
+use std::collections::BTreeMap;
+use std::ops::{Bound, RangeInclusive};
+
+/// A set of keys and inclusive key ranges to prove against a Merk root hash in
+/// a single proof, e.g. for verifying several balances at once.
+///
+/// Keys which are absent from the tree are proven to be absent.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatchQuery {
+    items: Vec<BatchItem>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BatchItem {
+    Key(Vec<u8>),
+    Range(Vec<u8>, Vec<u8>),
+}
+
+impl BatchQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn key(mut self, key: Vec<u8>) -> Self {
+        self.items.push(BatchItem::Key(key));
+        self
+    }
+
+    #[must_use]
+    pub fn range(mut self, range: RangeInclusive<Vec<u8>>) -> Self {
+        let (start, end) = range.into_inner();
+        self.items.push(BatchItem::Range(start, end));
+        self
+    }
+
+    pub fn items(&self) -> &[BatchItem] {
+        &self.items
+    }
+
+    /// Converts the batch into a Merk query, which can be passed to
+    /// [Prove](super::Prove) implementations.
+    pub fn to_query(&self) -> Query {
+        let mut query = Query::new();
+        for item in self.items.iter() {
+            match item {
+                BatchItem::Key(key) => query.insert_key(key.clone()),
+                BatchItem::Range(start, end) => {
+                    query.insert_range_inclusive(start.clone()..=end.clone())
+                }
+            }
+        }
+
+        query
+    }
+}
+
+impl Encode for BatchQuery {
+    fn encode_into<W: std::io::Write>(&self, dest: &mut W) -> ed::Result<()> {
+        (self.items.len() as u16).encode_into(dest)?;
+        for item in self.items.iter() {
+            match item {
+                BatchItem::Key(key) => {
+                    0u8.encode_into(dest)?;
+                    encode_bytes(key, dest)?;
+                }
+                BatchItem::Range(start, end) => {
+                    1u8.encode_into(dest)?;
+                    encode_bytes(start, dest)?;
+                    encode_bytes(end, dest)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn encoding_length(&self) -> ed::Result<usize> {
+        Ok(2 + self
+            .items
+            .iter()
+            .map(|item| match item {
+                BatchItem::Key(key) => 3 + key.len(),
+                BatchItem::Range(start, end) => 5 + start.len() + end.len(),
+            })
+            .sum::<usize>())
+    }
+}
+
+impl Decode for BatchQuery {
+    fn decode<R: std::io::Read>(mut input: R) -> ed::Result<Self> {
+        let len = u16::decode(&mut input)?;
+        let mut items = Vec::with_capacity(len as usize);
+        for _ in 0..len {
+            let item = match u8::decode(&mut input)? {
+                0 => BatchItem::Key(decode_bytes(&mut input)?),
+                1 => BatchItem::Range(decode_bytes(&mut input)?, decode_bytes(&mut input)?),
+                byte => return Err(ed::Error::UnexpectedByte(byte)),
+            };
+            items.push(item);
+        }
+
+        Ok(Self { items })
+    }
+}
+
+impl Terminated for BatchQuery {}
+
+fn encode_bytes<W: std::io::Write>(bytes: &[u8], dest: &mut W) -> ed::Result<()> {
+    let len: u16 = bytes
+        .len()
+        .try_into()
+        .map_err(|_| ed::Error::UnencodableVariant)?;
+    len.encode_into(dest)?;
+    dest.write_all(bytes)?;
+
+    Ok(())
+}
+
+fn decode_bytes<R: std::io::Read>(mut input: R) -> ed::Result<Vec<u8>> {
+    let len = u16::decode(&mut input)?;
+    let mut bytes = vec![0; len as usize];
+    input.read_exact(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+/// Creates a proof of every key and range in the batch.
+#[cfg(feature = "merk-full")]
+pub fn prove_batch<T: super::Prove>(store: &T, query: &BatchQuery) -> Result<Vec<u8>> {
+    store.prove(query.to_query())
+}
+
+/// Verifies a proof created by [prove_batch] against a trusted root hash,
+/// returning a store with the proven entries.
+///
+/// Reading a key covered by the query returns its value, or `None` if it was
+/// proven to be absent. Reading a key outside of the query returns an error.
+pub fn verify_batch(proof: &[u8], root_hash: Hash, query: &BatchQuery) -> Result<PartialMapStore> {
+    let map = verify(proof, root_hash)?;
+
+    let mut store = PartialMapStore::new();
+    for item in query.items() {
+        let (start, end) = match item {
+            BatchItem::Key(key) => (key, key),
+            BatchItem::Range(start, end) => (start, end),
+        };
+        store = store.join(proven_range(&map, start, end)?);
+    }
+
+    Ok(store)
+}
+
+/// Collects the entries proven for the inclusive range, along with the entries
+/// bounding it, so that absent keys within the range read as `None`.
+fn proven_range(map: &ProofMap, start: &[u8], end: &[u8]) -> Result<PartialMapStore> {
+    let mut entries = BTreeMap::new();
+
+    let mut contiguous = match map
+        .range((Bound::Unbounded, Bound::Excluded(start)))
+        .next_back()
+        .transpose()
+    {
+        Ok(Some((key, value))) => {
+            entries.insert(key.to_vec(), (false, value.to_vec()));
+            true
+        }
+        Ok(None) => true,
+        Err(merk::Error::MissingData) => false,
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut right_edge = false;
+    let mut last_key: Option<Vec<u8>> = None;
+    let mut iter = map.range((Bound::Included(start), Bound::Unbounded));
+    loop {
+        match iter.next() {
+            None => {
+                right_edge = true;
+                break;
+            }
+            Some(Ok((key, value))) => {
+                entries.insert(key.to_vec(), (contiguous, value.to_vec()));
+                contiguous = true;
+                if key > end {
+                    break;
+                }
+                last_key = Some(key.to_vec());
+            }
+            Some(Err(merk::Error::MissingData)) => {
+                if last_key.as_deref() == Some(end) {
+                    break;
+                }
+                return Err(Error::Store("Proof does not cover the queried keys".into()));
+            }
+            Some(Err(err)) => return Err(err.into()),
+        }
+    }
+
+    Ok(PartialMapStore::from_map(entries, right_edge))
+}
+
+#[cfg(all(test, feature = "merk-full"))]
+mod tests {
+    use super::*;
+    use crate::merk::MerkStore;
+    use crate::store::{Read, Write};
+    use tempdir::TempDir;
+
+    #[test]
+    fn batch_keys_and_ranges() -> Result<()> {
+        let dir = TempDir::new("orga-batch-proof").unwrap();
+        let mut store = MerkStore::new(dir.path());
+        for i in 0..100u8 {
+            store.put(vec![i * 2], vec![i])?;
+        }
+        store.write(vec![])?;
+
+        let query = BatchQuery::new()
+            .key(vec![10])
+            .key(vec![11])
+            .range(vec![50]..=vec![55]);
+        let query = BatchQuery::decode(query.encode()?.as_slice())?;
+
+        let proof = prove_batch(&store, &query)?;
+        let root_hash = store.merk().root_hash();
+        let proven = verify_batch(&proof, root_hash, &query)?;
+
+        assert_eq!(proven.get(&[10])?, Some(vec![5]));
+        assert_eq!(proven.get(&[11])?, None);
+        assert_eq!(proven.get(&[52])?, Some(vec![26]));
+        assert_eq!(proven.get(&[53])?, None);
+        assert_eq!(proven.get_next(&[50])?, Some((vec![52], vec![26])));
+        assert!(proven.get(&[80]).is_err());
+
+        assert!(verify_batch(&proof, [0; 32], &query).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// The number of entries kept by a [ReadCache] unless configured otherwise.
+pub const DEFAULT_CACHE_CAPACITY: usize = 10_000;
+
+/// A bounded least-recently-used cache of committed tree values, used by
+/// [MerkStore](super::MerkStore) to avoid re-reading hot keys from disk.
+///
+/// Absent keys are cached as `None`, so repeated misses (e.g. for accounts
+/// which do not exist yet) are also served from memory. The cache only holds
+/// values which have been written to the underlying `Merk`, so it must be
+/// updated with every batch applied to the tree and cleared whenever the tree
+/// is replaced.
+#[derive(Default)]
+pub struct ReadCache {
+    capacity: usize,
+    entries: HashMap<Vec<u8>, (u64, Option<Vec<u8>>)>,
+    recency: BTreeMap<u64, Vec<u8>>,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+}
+
+/// Counters describing the effectiveness of a [ReadCache].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub capacity: usize,
+}
+
+impl CacheStats {
+    /// The fraction of reads which were served from the cache, or 0 if there
+    /// have been no reads.
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            return 0.0;
+        }
+
+        self.hits as f64 / total as f64
+    }
+}
+
+impl ReadCache {
+    /// Creates a cache holding at most `capacity` entries. A capacity of 0
+    /// disables caching.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ..Default::default()
+        }
+    }
+
+    /// Gets the cached value for `key`, marking it as recently used. The outer
+    /// `Option` is `None` on a cache miss.
+    #[allow(clippy::option_option)]
+    pub fn get(&mut self, key: &[u8]) -> Option<Option<Vec<u8>>> {
+        let tick = self.next_tick();
+        match self.entries.get_mut(key) {
+            Some((last_used, value)) => {
+                let key = self.recency.remove(last_used).unwrap();
+                self.recency.insert(tick, key);
+                *last_used = tick;
+                self.hits += 1;
+                Some(value.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Inserts or replaces the cached value for `key`, evicting the least
+    /// recently used entry if the cache is full.
+    pub fn insert(&mut self, key: Vec<u8>, value: Option<Vec<u8>>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let tick = self.next_tick();
+        if let Some((last_used, _)) = self.entries.get(&key) {
+            self.recency.remove(last_used);
+        } else if self.entries.len() >= self.capacity {
+            if let Some((_, evicted)) = self.recency.pop_first() {
+                self.entries.remove(&evicted);
+            }
+        }
+
+        self.recency.insert(tick, key.clone());
+        self.entries.insert(key, (tick, value));
+    }
+
+    /// Updates any cached entry for `key` after a write, without caching keys
+    /// which have not been read.
+    pub fn update(&mut self, key: &[u8], value: Option<&[u8]>) {
+        if let Some((_, cached)) = self.entries.get_mut(key) {
+            *cached = value.map(|v| v.to_vec());
+        }
+    }
+
+    /// Removes all entries, keeping the hit and miss counters.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+
+    /// Changes the maximum number of entries, evicting the least recently used
+    /// entries if the cache is now over capacity.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.entries.len() > capacity {
+            let (_, evicted) = self.recency.pop_first().unwrap();
+            self.entries.remove(&evicted);
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            entries: self.entries.len(),
+            capacity: self.capacity,
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lru_eviction() {
+        let mut cache = ReadCache::new(2);
+        cache.insert(vec![1], Some(vec![10]));
+        cache.insert(vec![2], None);
+        assert_eq!(cache.get(&[1]), Some(Some(vec![10])));
+
+        cache.insert(vec![3], Some(vec![30]));
+        assert_eq!(cache.get(&[2]), None);
+        assert_eq!(cache.get(&[1]), Some(Some(vec![10])));
+        assert_eq!(cache.get(&[3]), Some(Some(vec![30])));
+
+        cache.update(&[3], None);
+        cache.update(&[4], Some(&[40]));
+        assert_eq!(cache.get(&[3]), Some(None));
+        assert_eq!(cache.get(&[4]), None);
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 4);
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.entries, 2);
+
+        cache.set_capacity(1);
+        assert_eq!(cache.stats().entries, 1);
+        assert_eq!(cache.get(&[3]), Some(None));
+
+        cache.set_capacity(0);
+        cache.insert(vec![5], None);
+        assert_eq!(cache.stats().entries, 0);
+    }
+}
@@ -1,3 +1,7 @@
+#[cfg(feature = "merk-verify")]
+pub mod batchproof;
+#[cfg(feature = "merk-full")]
+pub mod cache;
 mod client;
 #[cfg(feature = "merk-full")]
 pub mod ics23;
@@ -12,10 +16,12 @@ pub mod snapshot;
 #[cfg(feature = "merk-full")]
 pub mod store;
 
+#[cfg(feature = "merk-verify")]
+pub use batchproof::{verify_batch, BatchQuery};
 pub use client::Client;
 pub use merk;
 #[cfg(feature = "merk-full")]
-pub use proofbuilder::ProofBuilder;
+pub use proofbuilder::{ProofBuilder, Prove};
 #[cfg(feature = "merk-verify")]
 pub use proofstore::ProofStore;
 #[cfg(feature = "merk-full")]
@@ -1,13 +1,16 @@
 use crate::store::Read;
-use crate::Result;
-use merk::{Hash, Merk};
+use crate::{Error, Result};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use merk::{restore::Restorer, Hash, Merk};
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Read as _, Write};
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use tendermint_proto::v0_34::abci::{RequestLoadSnapshotChunk, Snapshot as AbciSnapshot};
 
-use super::store::{FIRST_SNAPSHOT_HEIGHT, SNAPSHOT_INTERVAL};
+use super::store::{calc_app_hash, FIRST_SNAPSHOT_HEIGHT, SNAPSHOT_INTERVAL};
 
 #[derive(Clone)]
 pub struct Snapshot {
@@ -42,6 +45,125 @@ impl Snapshot {
     }
 }
 
+/// The path of the manifest within a snapshot archive. The manifest is always
+/// the first entry, followed by the chunks in order.
+const MANIFEST_PATH: &str = "manifest.json";
+
+fn chunk_path(index: usize) -> PathBuf {
+    Path::new("chunks").join(index.to_string())
+}
+
+/// Describes the snapshot contained in an exported archive.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub height: u64,
+    /// The hex-encoded Merk root hash of the snapshot, which the restored
+    /// chunks are verified against.
+    pub root_hash: String,
+    pub chunks: u32,
+}
+
+impl Snapshot {
+    /// Writes the snapshot to a gzipped tar archive, containing a manifest
+    /// and the same chunks that are served over state sync.
+    pub fn export<W: Write>(&self, height: u64, writer: W) -> Result<SnapshotManifest> {
+        let manifest = SnapshotManifest {
+            height,
+            root_hash: hex::encode(self.hash),
+            chunks: self.length,
+        };
+
+        let mut builder = tar::Builder::new(GzEncoder::new(writer, Compression::default()));
+        append_entry(
+            &mut builder,
+            Path::new(MANIFEST_PATH),
+            &serde_json::to_vec_pretty(&manifest)?,
+        )?;
+        for index in 0..self.length as usize {
+            append_entry(&mut builder, &chunk_path(index), &self.chunk(index)?)?;
+        }
+        builder.into_inner()?.finish()?;
+
+        Ok(manifest)
+    }
+}
+
+fn append_entry<W: Write>(builder: &mut tar::Builder<W>, path: &Path, data: &[u8]) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    builder.append_data(&mut header, path, data)?;
+
+    Ok(())
+}
+
+/// Restores a snapshot archive written by [Snapshot::export] into a new Merk
+/// database at `path`.
+///
+/// The archive itself is untrusted: its manifest must match the given
+/// `height` and `app_hash`, which the caller must take from a trusted source
+/// such as a verified block header, and every chunk is then verified against
+/// the manifest's root hash.
+pub fn import<R: std::io::Read>(
+    reader: R,
+    path: &Path,
+    height: u64,
+    app_hash: &[u8],
+) -> Result<SnapshotManifest> {
+    let mut archive = tar::Archive::new(GzDecoder::new(reader));
+    let mut entries = archive.entries()?;
+
+    let manifest: SnapshotManifest = match entries.next() {
+        Some(entry) => {
+            let entry = entry?;
+            if entry.path()?.as_ref() != Path::new(MANIFEST_PATH) {
+                return Err(Error::Store(
+                    "Snapshot archive does not start with a manifest".into(),
+                ));
+            }
+            serde_json::from_reader(entry)?
+        }
+        None => return Err(Error::Store("Snapshot archive is empty".into())),
+    };
+
+    let expected_hash: Hash = hex::decode(&manifest.root_hash)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| Error::Store("Invalid root hash in snapshot manifest".into()))?;
+
+    if manifest.height != height || calc_app_hash(&expected_hash) != app_hash {
+        return Err(Error::Store(
+            "Snapshot archive does not match the trusted height and app hash".into(),
+        ));
+    }
+
+    let mut restorer = Restorer::new(path, expected_hash, manifest.chunks as usize)?;
+    let mut chunks_remaining = manifest.chunks as usize;
+    for (index, entry) in entries.enumerate() {
+        let mut entry = entry?;
+        if entry.path()?.as_ref() != chunk_path(index).as_path() {
+            return Err(Error::Store(format!(
+                "Unexpected entry in snapshot archive: {}",
+                entry.path()?.display()
+            )));
+        }
+
+        let mut chunk = vec![];
+        entry.read_to_end(&mut chunk)?;
+        chunks_remaining = restorer.process_chunk(chunk.as_slice())?;
+    }
+
+    if chunks_remaining != 0 {
+        return Err(Error::Store(format!(
+            "Snapshot archive is missing {} chunks",
+            chunks_remaining
+        )));
+    }
+    restorer.finalize()?;
+
+    Ok(manifest)
+}
+
 impl Read for Snapshot {
     fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
         Ok(self.checkpoint.borrow().get(key)?)
@@ -60,6 +182,82 @@ impl Read for Snapshot {
     }
 }
 
+/// Configures which heights state sync snapshots are created at and how long
+/// they are kept. Snapshots at heights other than the canonical ones are kept
+/// locally, e.g. for exporting, but are not served to peers.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnapshotPolicy {
+    /// Whether snapshots are created automatically when committing. Existing
+    /// snapshots are still served and pruned when this is disabled.
+    pub enabled: bool,
+    /// Creates a snapshot at every multiple of this height, or never if 0.
+    pub interval: u64,
+    /// The number of interval snapshots to keep.
+    pub keep_recent: u64,
+    /// Specific heights to create snapshots at, which are never pruned.
+    pub heights: Vec<u64>,
+}
+
+impl Default for SnapshotPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: cfg!(feature = "state-sync"),
+            interval: SNAPSHOT_INTERVAL,
+            keep_recent: 4,
+            heights: vec![FIRST_SNAPSHOT_HEIGHT],
+        }
+    }
+}
+
+impl SnapshotPolicy {
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Default::default()
+        }
+    }
+
+    pub fn filters(&self) -> Vec<SnapshotFilter> {
+        let mut filters: Vec<_> = self
+            .heights
+            .iter()
+            .map(|height| SnapshotFilter::specific_height(*height, None))
+            .collect();
+
+        if self.interval > 0 {
+            filters.push(SnapshotFilter::interval(self.interval, self.keep_recent));
+        }
+
+        filters
+    }
+
+    /// Returns true if the policy creates snapshots at the given height,
+    /// regardless of whether automatic creation is enabled.
+    pub fn matches(&self, height: u64) -> bool {
+        height > 0 && self.filters().iter().any(|f| f.should_create(height))
+    }
+}
+
+/// Returns true if the given height is one every node on the network creates
+/// snapshots at by default. Only snapshots at these heights are advertised to
+/// or accepted from peers, so that nodes with differing local policies agree
+/// on which snapshots are used for state sync.
+pub fn is_canonical_height(height: u64) -> bool {
+    height > 0 && (height % SNAPSHOT_INTERVAL == 0 || height == FIRST_SNAPSHOT_HEIGHT)
+}
+
+/// Describes a snapshot which is available on disk.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub height: u64,
+    pub chunks: u32,
+    /// The hex-encoded root hash of the snapshot.
+    pub hash: String,
+    /// The total size of the snapshot's files, in bytes.
+    pub size: u64,
+}
+
 pub enum SnapshotFilter {
     Interval {
         interval: u64,
@@ -103,6 +301,8 @@ impl SnapshotFilter {
 pub struct Snapshots {
     snapshots: BTreeMap<u64, Snapshot>,
     filters: Vec<SnapshotFilter>,
+    auto_create: bool,
+    pinned: BTreeSet<u64>,
     path: PathBuf,
 }
 
@@ -115,6 +315,8 @@ impl Snapshots {
         Ok(Self {
             snapshots: BTreeMap::new(),
             filters: vec![],
+            auto_create: true,
+            pinned: BTreeSet::new(),
             path: path.to_path_buf(),
         })
     }
@@ -144,6 +346,22 @@ impl Snapshots {
         self
     }
 
+    pub fn with_policy(mut self, policy: &SnapshotPolicy) -> Self {
+        self.set_policy(policy);
+        self
+    }
+
+    pub fn set_policy(&mut self, policy: &SnapshotPolicy) {
+        self.filters = policy.filters();
+        self.auto_create = policy.enabled;
+    }
+
+    /// Keeps the snapshot at the given height from being pruned, until the
+    /// snapshots are reloaded.
+    pub fn pin(&mut self, height: u64) {
+        self.pinned.insert(height);
+    }
+
     pub fn get(&self, height: u64) -> Option<&Snapshot> {
         self.snapshots.get(&height)
     }
@@ -153,13 +371,15 @@ impl Snapshots {
     }
 
     pub fn should_create(&self, height: u64) -> bool {
-        height > 0 && self.filters.iter().any(|f| f.should_create(height))
+        self.auto_create && height > 0 && self.filters.iter().any(|f| f.should_create(height))
     }
 
     pub fn should_keep(&self, ss_height: u64, cur_height: u64) -> bool {
-        self.filters
-            .iter()
-            .any(|f| f.should_keep(ss_height, cur_height))
+        self.pinned.contains(&ss_height)
+            || self
+                .filters
+                .iter()
+                .any(|f| f.should_keep(ss_height, cur_height))
     }
 
     pub fn create(&mut self, height: u64, checkpoint: Merk) -> Result<()> {
@@ -202,12 +422,12 @@ impl Snapshots {
         self.path.join(height.to_string())
     }
 
+    /// Lists the snapshots to advertise to state syncing peers, which are only
+    /// those at canonical heights (see [is_canonical_height]).
     pub fn abci_snapshots(&self) -> Result<Vec<AbciSnapshot>> {
         self.snapshots
             .iter()
-            .filter(|(height, _)| {
-                *height % SNAPSHOT_INTERVAL == 0 || **height == FIRST_SNAPSHOT_HEIGHT
-            })
+            .filter(|(height, _)| is_canonical_height(**height))
             .map(|(height, snapshot)| {
                 Ok(AbciSnapshot {
                     chunks: snapshot.length,
@@ -219,6 +439,26 @@ impl Snapshots {
             .collect()
     }
 
+    pub fn export<W: Write>(&self, height: u64, writer: W) -> Result<SnapshotManifest> {
+        self.get(height)
+            .ok_or_else(|| Error::Store(format!("No snapshot at height {}", height)))?
+            .export(height, writer)
+    }
+
+    pub fn info(&self) -> Result<Vec<SnapshotInfo>> {
+        self.snapshots
+            .iter()
+            .map(|(height, snapshot)| {
+                Ok(SnapshotInfo {
+                    height: *height,
+                    chunks: snapshot.length,
+                    hash: hex::encode(snapshot.hash),
+                    size: dir_size(&self.path(*height))?,
+                })
+            })
+            .collect()
+    }
+
     pub fn abci_load_chunk(&self, req: RequestLoadSnapshotChunk) -> Result<Vec<u8>> {
         match self.snapshots.get(&req.height) {
             Some(snapshot) => snapshot.chunk(req.chunk as usize),
@@ -229,3 +469,63 @@ impl Snapshots {
         }
     }
 }
+
+fn dir_size(path: &Path) -> Result<u64> {
+    if !path.exists() {
+        return Ok(0);
+    }
+
+    let mut size = 0;
+    for entry in path.read_dir()? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_filters() {
+        let policy = SnapshotPolicy {
+            enabled: true,
+            interval: 100,
+            keep_recent: 2,
+            heights: vec![5],
+        };
+        assert!(policy.matches(5));
+        assert!(policy.matches(200));
+        assert!(!policy.matches(150));
+        assert!(!policy.matches(0));
+
+        assert!(is_canonical_height(FIRST_SNAPSHOT_HEIGHT));
+        assert!(is_canonical_height(SNAPSHOT_INTERVAL * 3));
+        assert!(!is_canonical_height(0));
+        assert!(!is_canonical_height(5));
+
+        let mut snapshots = Snapshots::default().with_policy(&policy);
+        assert!(snapshots.should_create(300));
+        assert!(snapshots.should_keep(5, 1000));
+        assert!(snapshots.should_keep(900, 1000));
+        assert!(!snapshots.should_keep(800, 1000));
+        assert!(!snapshots.should_keep(777, 1000));
+
+        snapshots.pin(777);
+        assert!(snapshots.should_keep(777, 1000));
+
+        snapshots.set_policy(&SnapshotPolicy {
+            interval: 0,
+            ..SnapshotPolicy::disabled()
+        });
+        assert!(!snapshots.should_create(2));
+        assert!(!snapshots.should_keep(900, 1000));
+    }
+}
@@ -3,15 +3,19 @@ use crate::error::{Error, Result};
 use crate::store::*;
 use merk::snapshot::StaticSnapshot;
 use merk::{restore::Restorer, tree::Tree, BatchEntry, Merk, Op};
+use std::cell::RefCell;
 use std::path::{Path, PathBuf};
 use std::{collections::BTreeMap, convert::TryInto};
 use tendermint_proto::v0_34::abci::{self, *};
 
-use super::snapshot;
+use super::cache::{CacheStats, ReadCache, DEFAULT_CACHE_CAPACITY};
+use super::snapshot::{self, SnapshotInfo, SnapshotManifest, SnapshotPolicy};
 type Map = BTreeMap<Vec<u8>, Option<Vec<u8>>>;
 
 pub const SNAPSHOT_INTERVAL: u64 = 1000;
 pub const FIRST_SNAPSHOT_HEIGHT: u64 = 2;
+/// The default number of recent heights kept in memory for queries.
+pub const DEFAULT_QUERY_HEIGHTS: usize = 20;
 
 /// A [`store::Store`] implementation backed by a [`merk`](https://docs.rs/merk)
 /// Merkle key/value store.
@@ -20,9 +24,12 @@ pub struct MerkStore {
     home: PathBuf,
     map: Option<Map>,
     snapshots: snapshot::Snapshots,
+    snapshot_policy: SnapshotPolicy,
     restorer: Option<Restorer>,
     target_snapshot: Option<Snapshot>,
     mem_snapshots: BTreeMap<u64, StaticSnapshot>,
+    query_heights: usize,
+    cache: RefCell<ReadCache>,
 }
 
 impl MerkStore {
@@ -30,6 +37,9 @@ impl MerkStore {
     /// [`Merk`](https://docs.rs/merk/latest/merk/struct.Merk.html) inside the
     /// `merk_home` directory. Initializes a new Merk instance if the directory
     /// is empty
+    ///
+    /// Snapshots are created according to the default [SnapshotPolicy], which
+    /// can be changed with [MerkStore::set_snapshot_policy].
     pub fn new<P: AsRef<Path>>(home: P) -> Self {
         let home = home.as_ref().to_path_buf();
         let merk = Merk::open(home.join("db")).unwrap();
@@ -41,10 +51,13 @@ impl MerkStore {
             map: Some(Map::new()),
             merk: Some(merk),
             snapshots: Self::load_snapshots(home.join("snapshots")),
+            snapshot_policy: SnapshotPolicy::default(),
             home,
             target_snapshot: None,
             restorer: None,
             mem_snapshots: BTreeMap::new(),
+            query_heights: DEFAULT_QUERY_HEIGHTS,
+            cache: RefCell::new(ReadCache::new(DEFAULT_CACHE_CAPACITY)),
         }
     }
 
@@ -59,23 +72,158 @@ impl MerkStore {
             map: Some(Default::default()),
             merk: Some(merk),
             snapshots: snapshot::Snapshots::default(),
+            snapshot_policy: SnapshotPolicy::disabled(),
             home,
             target_snapshot: None,
             restorer: None,
             mem_snapshots: BTreeMap::new(),
+            query_heights: DEFAULT_QUERY_HEIGHTS,
+            cache: RefCell::new(ReadCache::new(DEFAULT_CACHE_CAPACITY)),
         }
     }
 
     fn load_snapshots<P: AsRef<Path>>(path: P) -> snapshot::Snapshots {
         snapshot::Snapshots::load(path.as_ref())
             .expect("Failed to load snapshots")
-            // TODO: make configurable
-            .with_filters(vec![
-                #[cfg(feature = "state-sync")]
-                snapshot::SnapshotFilter::specific_height(2, None),
-                #[cfg(feature = "state-sync")]
-                snapshot::SnapshotFilter::interval(1000, 4),
-            ])
+            .with_policy(&SnapshotPolicy::default())
+    }
+
+    /// Sets the maximum number of committed values kept in the in-memory read
+    /// cache. A capacity of 0 disables the cache.
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.cache.get_mut().set_capacity(capacity);
+    }
+
+    #[must_use]
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.set_cache_capacity(capacity);
+        self
+    }
+
+    /// Sets the number of recent heights kept in memory which can be queried.
+    pub fn set_query_heights(&mut self, heights: usize) {
+        self.query_heights = heights.max(1);
+    }
+
+    /// Returns the hit and miss counts of the read cache.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.borrow().stats()
+    }
+
+    /// Sets the policy which determines the heights snapshots are created at
+    /// when committing, how long they are kept, and which snapshot heights are
+    /// accepted for state sync.
+    pub fn set_snapshot_policy(&mut self, policy: SnapshotPolicy) {
+        self.snapshots.set_policy(&policy);
+        self.snapshot_policy = policy;
+    }
+
+    #[must_use]
+    pub fn with_snapshot_policy(mut self, policy: SnapshotPolicy) -> Self {
+        self.set_snapshot_policy(policy);
+        self
+    }
+
+    pub fn snapshot_policy(&self) -> &SnapshotPolicy {
+        &self.snapshot_policy
+    }
+
+    /// Creates a snapshot of the committed state at the current height,
+    /// regardless of the snapshot policy, and returns its height. The snapshot
+    /// is not pruned until the store is reopened.
+    pub fn create_snapshot(&mut self) -> Result<u64> {
+        let height = self.height()?;
+        if height == 0 {
+            return Err(Error::Store(
+                "Cannot create a snapshot before the first commit".into(),
+            ));
+        }
+
+        self.snapshots.pin(height);
+        if self.snapshots.get(height).is_none() {
+            let path = self.snapshots.path(height);
+            let checkpoint = self.merk().checkpoint(path)?;
+            self.snapshots.create(height, checkpoint)?;
+        }
+
+        Ok(height)
+    }
+
+    /// Exports the snapshot at the given height to an archive file, which can
+    /// be restored with [MerkStore::import_snapshot].
+    pub fn export_snapshot<P: AsRef<Path>>(
+        &self,
+        height: u64,
+        path: P,
+    ) -> Result<SnapshotManifest> {
+        let file = std::fs::File::create(path)?;
+        self.snapshots.export(height, file)
+    }
+
+    /// Restores an archive written by [MerkStore::export_snapshot] into a new
+    /// store in the `home` directory, verifying the restored state against
+    /// the trusted `height` and `app_hash`, e.g. from a verified block header.
+    ///
+    /// Only the app state is restored: Tendermint refuses to start when the
+    /// app is ahead of its block store, so the node's Tendermint data has to
+    /// be brought to the same height separately, e.g. by copying it from a
+    /// node which was stopped at that height.
+    pub fn import_snapshot(
+        archive: impl AsRef<Path>,
+        home: impl AsRef<Path>,
+        height: u64,
+        app_hash: &[u8],
+    ) -> Result<Self> {
+        let home = home.as_ref();
+        if home.join("db").exists() {
+            return Err(Error::Store(
+                "Cannot import a snapshot into an existing store".into(),
+            ));
+        }
+        std::fs::create_dir_all(home)?;
+        maybe_remove_restore(home)?;
+
+        let restore_path = home.join("restore");
+        let file = std::fs::File::open(archive)?;
+        let manifest = match snapshot::import(file, &restore_path, height, app_hash) {
+            Ok(manifest) => manifest,
+            Err(err) => {
+                maybe_remove_restore(home)?;
+                return Err(err);
+            }
+        };
+        std::fs::rename(&restore_path, home.join("db"))?;
+
+        let mut store = Self::new(home);
+        let height_bytes = manifest.height.to_be_bytes().to_vec();
+        store.write(vec![(b"height".to_vec(), Some(height_bytes))])?;
+        store.merk.as_mut().unwrap().flush()?;
+
+        if hex::encode(store.merk().root_hash()) != manifest.root_hash {
+            return Err(Error::Store(
+                "Imported state does not match the snapshot root hash".into(),
+            ));
+        }
+
+        Ok(store)
+    }
+
+    /// Lists the snapshots which are available to serve to state syncing
+    /// nodes.
+    pub fn snapshot_info(&self) -> Result<Vec<SnapshotInfo>> {
+        self.snapshots.info()
+    }
+
+    /// Compares the state of two on-disk snapshots, returning the keys which
+    /// were added, removed or modified between the two heights.
+    pub fn diff_snapshots(&self, old_height: u64, new_height: u64) -> Result<Vec<Change>> {
+        let get = |height| {
+            self.snapshots
+                .get(height)
+                .ok_or_else(|| Error::Store(format!("No snapshot at height {}", height)))
+        };
+
+        diff(get(old_height)?, get(new_height)?).collect()
     }
 
     pub fn init_from(
@@ -122,11 +270,20 @@ impl MerkStore {
         let batch = to_batch(map);
         let aux_batch = to_batch(aux);
 
-        Ok(self
-            .merk
+        self.merk
             .as_mut()
             .unwrap()
-            .apply(batch.as_ref(), aux_batch.as_ref())?)
+            .apply(batch.as_ref(), aux_batch.as_ref())?;
+
+        let cache = self.cache.get_mut();
+        for (key, op) in batch.iter() {
+            match op {
+                Op::Put(value) => cache.update(key, Some(value)),
+                Op::Delete => cache.update(key, None),
+            }
+        }
+
+        Ok(())
     }
 
     pub fn merk(&self) -> &Merk {
@@ -160,7 +317,15 @@ impl Read for MerkStore {
         match self.map.as_ref().unwrap().get(key) {
             Some(Some(value)) => Ok(Some(value.clone())),
             Some(None) => Ok(None),
-            None => Ok(self.merk.as_ref().unwrap().get(key)?),
+            None => {
+                if let Some(value) = self.cache.borrow_mut().get(key) {
+                    return Ok(value);
+                }
+
+                let value = self.merk.as_ref().unwrap().get(key)?;
+                self.cache.borrow_mut().insert(key.to_vec(), value.clone());
+                Ok(value)
+            }
         }
     }
 
@@ -251,7 +416,7 @@ impl Write for MerkStore {
     }
 }
 
-fn calc_app_hash(merk_root: &[u8]) -> Vec<u8> {
+pub(crate) fn calc_app_hash(merk_root: &[u8]) -> Vec<u8> {
     use sha2::{Digest, Sha512_256};
 
     let mut hasher = Sha512_256::new();
@@ -292,7 +457,6 @@ impl ABCIStore for MerkStore {
             - header.time.unwrap().seconds
             < 10;
 
-        #[cfg(feature = "state-sync")]
         if recent && self.snapshots.should_create(height) {
             let path = self.snapshots.path(height);
             let checkpoint = self.merk().checkpoint(path)?;
@@ -302,8 +466,7 @@ impl ABCIStore for MerkStore {
         let snapshot = self.merk().snapshot()?.staticize();
         self.mem_snapshots.insert(height, snapshot);
 
-        // TODO: parameterize
-        while self.mem_snapshots.len() > 20 {
+        while self.mem_snapshots.len() > self.query_heights {
             let ss = self.mem_snapshots.pop_first().unwrap();
             let db = self.merk().db();
             unsafe { ss.1.drop(db) };
@@ -348,6 +511,7 @@ impl ABCIStore for MerkStore {
         if chunks_remaining == 0 {
             let restored = self.restorer.take().unwrap().finalize()?;
             self.merk.take().unwrap().destroy()?;
+            self.cache.get_mut().clear();
             let db_path = self.path("db");
             drop(restored);
 
@@ -370,9 +534,7 @@ impl ABCIStore for MerkStore {
         res.set_result(abci::response_offer_snapshot::Result::Reject);
 
         if let Some(snapshot) = req.snapshot {
-            let is_canonical_height = snapshot.height % SNAPSHOT_INTERVAL == 0
-                || snapshot.height == FIRST_SNAPSHOT_HEIGHT;
-            if is_canonical_height
+            if snapshot::is_canonical_height(snapshot.height)
                 && calc_app_hash(snapshot.hash.to_vec().as_slice()) == req.app_hash
             {
                 self.target_snapshot = Some(snapshot);
@@ -398,3 +560,85 @@ fn read_u64(bytes: &[u8]) -> u64 {
     array.copy_from_slice(bytes);
     u64::from_be_bytes(array)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn export_import_snapshot() -> Result<()> {
+        let dir = TempDir::new("orga-snapshot-export").unwrap();
+        let mut store = MerkStore::new(dir.path().join("source"));
+        for i in 0..1_000u32 {
+            store.put(i.to_be_bytes().to_vec(), vec![123; 16])?;
+        }
+        store.write(vec![(
+            b"height".to_vec(),
+            Some(5u64.to_be_bytes().to_vec()),
+        )])?;
+
+        assert_eq!(store.create_snapshot()?, 5);
+        let info = store.snapshot_info()?;
+        assert_eq!(info.len(), 1);
+        assert_eq!(info[0].height, 5);
+        assert!(info[0].size > 0);
+
+        let archive = dir.path().join("snapshot.tar.gz");
+        let manifest = store.export_snapshot(5, &archive)?;
+        assert_eq!(manifest.height, 5);
+        assert_eq!(manifest.root_hash, hex::encode(store.merk().root_hash()));
+        assert!(store
+            .export_snapshot(6, dir.path().join("missing"))
+            .is_err());
+
+        let app_hash = store.root_hash()?;
+        assert!(
+            MerkStore::import_snapshot(&archive, dir.path().join("dest"), 6, &app_hash).is_err()
+        );
+        assert!(
+            MerkStore::import_snapshot(&archive, dir.path().join("dest"), 5, &[0; 32]).is_err()
+        );
+
+        let imported = MerkStore::import_snapshot(&archive, dir.path().join("dest"), 5, &app_hash)?;
+        assert_eq!(imported.height()?, 5);
+        assert_eq!(imported.root_hash()?, app_hash);
+        assert_eq!(imported.get(&7u32.to_be_bytes())?, Some(vec![123; 16]));
+
+        assert!(
+            MerkStore::import_snapshot(&archive, dir.path().join("dest"), 5, &app_hash).is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_cache() -> Result<()> {
+        let dir = TempDir::new("orga-read-cache").unwrap();
+        let mut store = MerkStore::new(dir.path()).with_cache_capacity(2);
+        store.put(vec![1], vec![10])?;
+        store.put(vec![2], vec![20])?;
+        store.write(vec![])?;
+
+        assert_eq!(store.get(&[1])?, Some(vec![10]));
+        assert_eq!(store.get(&[1])?, Some(vec![10]));
+        assert_eq!(store.get(&[3])?, None);
+        assert_eq!(store.get(&[3])?, None);
+        let stats = store.cache_stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (2, 2, 2));
+
+        store.put(vec![1], vec![11])?;
+        store.put(vec![3], vec![30])?;
+        assert_eq!(store.get(&[1])?, Some(vec![11]));
+        store.write(vec![])?;
+        assert_eq!(store.get(&[1])?, Some(vec![11]));
+        assert_eq!(store.get(&[3])?, Some(vec![30]));
+
+        store.delete(&[1])?;
+        store.write(vec![])?;
+        assert_eq!(store.get(&[1])?, None);
+        assert_eq!(store.cache_stats().hits, 5);
+
+        Ok(())
+    }
+}
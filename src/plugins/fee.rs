@@ -3,7 +3,7 @@ use orga_macros::orga;
 use super::sdk_compat::{sdk::Tx as SdkTx, ConvertSdkTx};
 use super::Paid;
 use crate::call::Call;
-use crate::coins::{Coin, Symbol};
+use crate::coins::{Coin, InvariantRegistry, RegisterInvariants, Supply, Symbol};
 use crate::context::{Context, GetContext};
 
 use crate::query::Query;
@@ -61,6 +61,14 @@ impl<S, T: ConvertSdkTx> ConvertSdkTx for FeePlugin<S, T> {
     }
 }
 
+impl<S, T> RegisterInvariants for FeePlugin<S, T> {
+    fn register_invariants(_registry: &mut InvariantRegistry<Self>) {}
+
+    fn check_invariants(&self, supply: &Supply) -> Result<()> {
+        self.inner.check_invariants(supply)
+    }
+}
+
 impl<S, T> Deref for FeePlugin<S, T> {
     type Target = T;
 
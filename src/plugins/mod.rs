@@ -22,6 +22,9 @@ pub use sdk_compat::{ConvertSdkTx, SdkCompatPlugin};
 pub mod query;
 pub use query::QueryPlugin;
 
+pub mod supply;
+pub use supply::SupplyPlugin;
+
 macro_rules! type_chain {
     ($name:tt<$($pfx_params:ident,)* _ $(,$sfx_params:ident)*>, $($tail:tt)*) => {
         $name<$($pfx_params,)* type_chain!($($tail)*), $($sfx_params),*>
@@ -39,6 +42,7 @@ pub type DefaultPlugins<S, T> = type_chain! {
     ChainCommitmentPlugin<_>,
     NoncePlugin<_>,
     PayablePlugin<_>,
+    SupplyPlugin<_>,
     FeePlugin<S, _>,
     T
 };
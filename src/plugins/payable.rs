@@ -43,7 +43,7 @@ impl Paid {
         let amount = amount.into();
         self.take_denom(amount, S::INDEX)?;
 
-        Ok(S::mint(amount))
+        Ok(Coin::from_existing(amount))
     }
 
     pub fn take_denom<A: Into<Amount>>(&mut self, amount: A, denom: u8) -> Result<()> {
@@ -163,6 +163,10 @@ where
     type Output = PayableCall<T::Call>;
 
     fn convert(&self, sdk_tx: &SdkTx) -> Result<PayableCall<T::Call>> {
+        if let Some(paid_call) = sdk_tx.native_call() {
+            return Ok(PayableCall::Paid(paid_call?));
+        }
+
         let paid_call = self.inner.convert(sdk_tx)?;
         Ok(PayableCall::Paid(paid_call))
     }
@@ -75,6 +75,7 @@ impl<T: Decode> Decode for Call<T> {
 
 pub mod sdk {
     use super::{Address, Decode, Encode, Error, Result, MAX_CALL_SIZE};
+    use crate::plugins::PaidCall;
     use cosmrs::proto::cosmos::tx::v1beta1::Tx as ProtoTx;
     use prost::Message;
     use serde::{Deserialize, Serialize};
@@ -248,6 +249,16 @@ pub mod sdk {
             Ok(sig_arr)
         }
 
+        /// Decodes the calls of an amino transaction holding a single
+        /// [NATIVE_CALL_MSG_TYPE] message. Returns `None` for any other
+        /// transaction.
+        pub fn native_call<T: Decode + std::fmt::Debug>(&self) -> Option<Result<PaidCall<T>>> {
+            match self {
+                Tx::Amino(tx) if tx.msg.len() == 1 => tx.msg[0].native_call(),
+                _ => None,
+            }
+        }
+
         pub fn sig_type(&self) -> Result<Option<&str>> {
             Ok(match self {
                 Tx::Amino(tx) => tx
@@ -305,6 +316,51 @@ pub mod sdk {
         pub value: String,
     }
 
+    /// The type of amino messages carrying encoded native calls, so wallets
+    /// which can only sign amino transactions, e.g. MetaMask through
+    /// `EthPersonalSign`, can sign any call. The message value holds the
+    /// base64-encoded `payer` and `paid` calls.
+    ///
+    /// [PayablePlugin](crate::plugins::PayablePlugin) converts transactions
+    /// holding one of these messages itself, before the app's
+    /// [ConvertSdkTx](super::ConvertSdkTx) implementation is reached.
+    pub const NATIVE_CALL_MSG_TYPE: &str = "orga/NativeCall";
+
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    struct NativeCallValue {
+        payer: String,
+        paid: String,
+    }
+
+    impl Msg {
+        /// Decodes the calls of a [NATIVE_CALL_MSG_TYPE] message. Returns
+        /// `None` for other message types.
+        pub fn native_call<T: Decode + std::fmt::Debug>(&self) -> Option<Result<PaidCall<T>>> {
+            if self.type_ != NATIVE_CALL_MSG_TYPE {
+                return None;
+            }
+
+            let decode = |value: &str| -> Result<T> {
+                use base64::Engine;
+                let bytes = base64::prelude::BASE64_STANDARD
+                    .decode(value)
+                    .map_err(|e| Error::App(e.to_string()))?;
+                Ok(T::decode(bytes.as_slice())?)
+            };
+
+            Some(
+                serde_json::from_value(self.value.clone())
+                    .map_err(|e| Error::App(e.to_string()))
+                    .and_then(|value: NativeCallValue| {
+                        Ok(PaidCall {
+                            payer: decode(&value.payer)?,
+                            paid: decode(&value.paid)?,
+                        })
+                    }),
+            )
+        }
+    }
+
     #[derive(Deserialize, Debug, Clone)]
     pub struct MsgSend {
         pub from_address: String,
@@ -312,6 +368,86 @@ pub mod sdk {
         pub amount: Vec<Coin>,
     }
 
+    #[derive(Deserialize, Debug, Clone)]
+    pub struct MsgMultiSend {
+        pub inputs: Vec<MultiSendIo>,
+        pub outputs: Vec<MultiSendIo>,
+    }
+
+    /// An input or output of a [MsgMultiSend].
+    #[derive(Deserialize, Debug, Clone)]
+    pub struct MultiSendIo {
+        pub address: String,
+        pub coins: Vec<Coin>,
+    }
+
+    impl MsgMultiSend {
+        /// Checks that the message has a single input from `sender` whose
+        /// coins equal the sum of the outputs, then returns the outputs paid
+        /// in `denom`.
+        pub fn outputs_for_denom(
+            &self,
+            sender: Address,
+            denom: &str,
+        ) -> Result<Vec<(Address, crate::coins::Amount)>> {
+            use std::collections::BTreeMap;
+
+            if self.inputs.len() != 1 {
+                return Err(Error::App(
+                    "MsgMultiSend must have exactly one input".into(),
+                ));
+            }
+            let input = &self.inputs[0];
+            let input_address: Address = input
+                .address
+                .parse()
+                .map_err(|_| Error::App("Invalid input address".into()))?;
+            if input_address != sender {
+                return Err(Error::App("MsgMultiSend input must be the signer".into()));
+            }
+
+            fn add_coins(totals: &mut BTreeMap<String, u64>, coins: &[Coin]) -> Result<()> {
+                for coin in coins {
+                    let amount: u64 = coin
+                        .amount
+                        .parse()
+                        .map_err(|_| Error::App("Invalid amount".into()))?;
+                    let total = totals.entry(coin.denom.clone()).or_default();
+                    *total = total
+                        .checked_add(amount)
+                        .ok_or_else(|| Error::App("Amount overflow".into()))?;
+                }
+                Ok(())
+            }
+
+            let mut input_totals = BTreeMap::new();
+            add_coins(&mut input_totals, &input.coins)?;
+            let mut output_totals = BTreeMap::new();
+            let mut outputs = vec![];
+            for output in self.outputs.iter() {
+                add_coins(&mut output_totals, &output.coins)?;
+                let address: Address = output
+                    .address
+                    .parse()
+                    .map_err(|_| Error::App("Invalid output address".into()))?;
+                for coin in output.coins.iter().filter(|coin| coin.denom == denom) {
+                    let amount: u64 = coin
+                        .amount
+                        .parse()
+                        .map_err(|_| Error::App("Invalid amount".into()))?;
+                    outputs.push((address, amount.into()));
+                }
+            }
+            if input_totals != output_totals {
+                return Err(Error::App(
+                    "MsgMultiSend inputs and outputs must be equal".into(),
+                ));
+            }
+
+            Ok(outputs)
+        }
+    }
+
     #[derive(Deserialize, Debug, Clone)]
     pub struct MsgDelegate {
         pub delegator_address: String,
@@ -408,3 +544,144 @@ mod abci {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::sdk::{Coin, MsgMultiSend, MultiSendIo};
+    use super::*;
+
+    fn coin(amount: &str, denom: &str) -> Coin {
+        Coin {
+            amount: amount.to_string(),
+            denom: denom.to_string(),
+        }
+    }
+
+    fn io(address: Address, coins: Vec<Coin>) -> MultiSendIo {
+        MultiSendIo {
+            address: address.to_string(),
+            coins,
+        }
+    }
+
+    #[test]
+    fn multi_send_outputs_for_denom() -> Result<()> {
+        let alice = Address::from_pubkey([2; 33]);
+        let bob = Address::from_pubkey([3; 33]);
+        let carol = Address::from_pubkey([4; 33]);
+
+        let msg: MsgMultiSend = serde_json::from_value(serde_json::json!({
+            "inputs": [{
+                "address": alice.to_string(),
+                "coins": [
+                    { "amount": "30", "denom": "uoraibtc" },
+                    { "amount": "5", "denom": "usat" },
+                ],
+            }],
+            "outputs": [
+                {
+                    "address": bob.to_string(),
+                    "coins": [
+                        { "amount": "10", "denom": "uoraibtc" },
+                        { "amount": "5", "denom": "usat" },
+                    ],
+                },
+                {
+                    "address": carol.to_string(),
+                    "coins": [{ "amount": "20", "denom": "uoraibtc" }],
+                },
+            ],
+        }))?;
+
+        assert_eq!(
+            msg.outputs_for_denom(alice, "uoraibtc")?,
+            vec![(bob, 10.into()), (carol, 20.into())]
+        );
+        assert_eq!(msg.outputs_for_denom(alice, "usat")?, vec![(bob, 5.into())]);
+        assert!(msg.outputs_for_denom(alice, "uatom")?.is_empty());
+        assert!(msg.outputs_for_denom(bob, "uoraibtc").is_err());
+
+        // totals must match per denom, not just in sum
+        let mismatched = MsgMultiSend {
+            inputs: vec![io(alice, vec![coin("30", "uoraibtc"), coin("5", "usat")])],
+            outputs: vec![io(bob, vec![coin("35", "uoraibtc")])],
+        };
+        assert!(mismatched.outputs_for_denom(alice, "uoraibtc").is_err());
+
+        let short = MsgMultiSend {
+            inputs: vec![io(alice, vec![coin("30", "uoraibtc")])],
+            outputs: vec![io(bob, vec![coin("29", "uoraibtc")])],
+        };
+        assert!(short.outputs_for_denom(alice, "uoraibtc").is_err());
+
+        let malformed_amount = MsgMultiSend {
+            inputs: vec![io(alice, vec![coin("1.5", "uoraibtc")])],
+            outputs: vec![io(bob, vec![coin("1.5", "uoraibtc")])],
+        };
+        assert!(malformed_amount
+            .outputs_for_denom(alice, "uoraibtc")
+            .is_err());
+
+        let malformed_address = MsgMultiSend {
+            inputs: vec![io(alice, vec![coin("10", "uoraibtc")])],
+            outputs: vec![MultiSendIo {
+                address: "notanaddress".to_string(),
+                coins: vec![coin("10", "uoraibtc")],
+            }],
+        };
+        assert!(malformed_address
+            .outputs_for_denom(alice, "uoraibtc")
+            .is_err());
+
+        let two_inputs = MsgMultiSend {
+            inputs: vec![
+                io(alice, vec![coin("10", "uoraibtc")]),
+                io(bob, vec![coin("10", "uoraibtc")]),
+            ],
+            outputs: vec![io(carol, vec![coin("20", "uoraibtc")])],
+        };
+        assert!(two_inputs.outputs_for_denom(alice, "uoraibtc").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn native_call_msg() -> Result<()> {
+        use base64::Engine;
+        let b64 = |n: u32| base64::prelude::BASE64_STANDARD.encode(n.encode().unwrap());
+        let tx = |msgs: Vec<sdk::Msg>| {
+            sdk::Tx::Amino(sdk::AminoTx {
+                msg: msgs,
+                fee: sdk::Fee {
+                    amount: vec![],
+                    gas: "0".to_string(),
+                },
+                memo: String::new(),
+                signatures: vec![],
+            })
+        };
+        let msg = sdk::Msg {
+            type_: sdk::NATIVE_CALL_MSG_TYPE.to_string(),
+            value: serde_json::json!({ "payer": b64(1), "paid": b64(2) }),
+        };
+
+        let call = tx(vec![msg.clone()]).native_call::<u32>().unwrap()?;
+        assert_eq!((call.payer, call.paid), (1, 2));
+
+        assert!(tx(vec![msg.clone(), msg.clone()])
+            .native_call::<u32>()
+            .is_none());
+        let other = sdk::Msg {
+            type_: "cosmos-sdk/MsgSend".to_string(),
+            ..msg.clone()
+        };
+        assert!(tx(vec![other]).native_call::<u32>().is_none());
+        let malformed = sdk::Msg {
+            value: serde_json::json!({ "payer": "!", "paid": b64(2) }),
+            ..msg
+        };
+        assert!(tx(vec![malformed]).native_call::<u32>().unwrap().is_err());
+
+        Ok(())
+    }
+}
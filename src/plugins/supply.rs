@@ -0,0 +1,224 @@
+use orga_macros::orga;
+
+use super::sdk_compat::{sdk::Tx as SdkTx, ConvertSdkTx};
+use crate::call::Call;
+use crate::coins::{RegisterInvariants, Supply, SupplyChanges};
+use crate::context::Context;
+use crate::query::Query;
+use crate::state::State;
+use crate::Result;
+use std::ops::{Deref, DerefMut};
+
+/// Keeps a per-denom total supply ledger for the inner app and periodically
+/// checks the invariants it declares through [RegisterInvariants].
+///
+/// Every call and block step runs with a fresh [SupplyChanges] context, and
+/// the mints and burns recorded in it are applied to `supply` once the inner
+/// operation succeeds.
+#[orga(skip(Call, Query))]
+pub struct SupplyPlugin<T> {
+    pub supply: Supply,
+    /// Check invariants at the end of every block whose height is a multiple
+    /// of this interval. Zero disables the checks.
+    pub invariant_check_interval: u64,
+    pub inner: T,
+}
+
+impl<T: State> SupplyPlugin<T> {
+    fn track_supply<R, F>(&mut self, op: F) -> Result<R>
+    where
+        F: FnOnce(&mut T) -> Result<R>,
+    {
+        Context::add(SupplyChanges::default());
+        let res = op(&mut self.inner);
+        let changes = Context::resolve::<SupplyChanges>()
+            .map(std::mem::take)
+            .unwrap_or_default();
+        Context::remove::<SupplyChanges>();
+
+        let res = res?;
+        self.supply.apply(changes)?;
+
+        Ok(res)
+    }
+
+    /// Runs every invariant registered by the inner app.
+    pub fn check_invariants(&self) -> Result<()> {
+        self.inner.check_invariants(&self.supply)
+    }
+}
+
+impl<T: Query> Query for SupplyPlugin<T> {
+    type Query = T::Query;
+
+    fn query(&self, query: Self::Query) -> Result<()> {
+        self.inner.query(query)
+    }
+}
+
+impl<T: Call + State> Call for SupplyPlugin<T> {
+    type Call = T::Call;
+
+    fn call(&mut self, call: Self::Call) -> Result<()> {
+        self.track_supply(|inner| inner.call(call))
+    }
+}
+
+impl<T: ConvertSdkTx> ConvertSdkTx for SupplyPlugin<T> {
+    type Output = T::Output;
+
+    fn convert(&self, sdk_tx: &SdkTx) -> Result<T::Output> {
+        self.inner.convert(sdk_tx)
+    }
+}
+
+impl<T> Deref for SupplyPlugin<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for SupplyPlugin<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+#[cfg(feature = "abci")]
+mod abci {
+    use super::super::{BeginBlockCtx, EndBlockCtx, InitChainCtx};
+    use super::*;
+    use crate::abci::{BeginBlock, EndBlock, InitChain};
+
+    impl<T> BeginBlock for SupplyPlugin<T>
+    where
+        T: BeginBlock + State,
+    {
+        fn begin_block(&mut self, ctx: &BeginBlockCtx) -> Result<()> {
+            self.track_supply(|inner| inner.begin_block(ctx))
+        }
+    }
+
+    impl<T> EndBlock for SupplyPlugin<T>
+    where
+        T: EndBlock + State,
+    {
+        fn end_block(&mut self, ctx: &EndBlockCtx) -> Result<()> {
+            self.track_supply(|inner| inner.end_block(ctx))?;
+
+            let interval = self.invariant_check_interval;
+            if interval > 0 && ctx.height % interval == 0 {
+                self.check_invariants()?;
+            }
+
+            Ok(())
+        }
+    }
+
+    impl<T> InitChain for SupplyPlugin<T>
+    where
+        T: InitChain + State + Call,
+    {
+        fn init_chain(&mut self, ctx: &InitChainCtx) -> Result<()> {
+            self.track_supply(|inner| inner.init_chain(ctx))
+        }
+    }
+
+    impl<T> crate::abci::AbciQuery for SupplyPlugin<T>
+    where
+        T: crate::abci::AbciQuery + State + Call,
+    {
+        fn abci_query(
+            &self,
+            request: &tendermint_proto::v0_34::abci::RequestQuery,
+        ) -> Result<tendermint_proto::v0_34::abci::ResponseQuery> {
+            self.inner.abci_query(request)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "abci"))]
+mod tests {
+    use super::*;
+    use crate::abci::EndBlock;
+    use crate::coins::{Amount, Coin, InvariantRegistry, Symbol};
+    use crate::plugins::EndBlockCtx;
+    use crate::{Error, Result};
+
+    #[orga]
+    #[derive(Clone, Debug)]
+    pub struct Simp;
+    impl Symbol for Simp {
+        const INDEX: u8 = 0;
+        const NAME: &'static str = "SIMP";
+    }
+
+    #[orga]
+    pub struct Bank {
+        balance: Amount,
+    }
+
+    #[orga]
+    impl Bank {
+        #[call]
+        pub fn mint(&mut self, amount: Amount) -> Result<()> {
+            let coins = Coin::<Simp>::mint(amount);
+            self.balance = (self.balance + coins.amount)?;
+            Ok(())
+        }
+
+        #[call]
+        pub fn burn(&mut self, amount: Amount) -> Result<()> {
+            self.balance = (self.balance - amount)?;
+            Coin::<Simp>::from_existing(amount).burn();
+            Ok(())
+        }
+
+        #[call]
+        pub fn leak(&mut self, amount: Amount) -> Result<()> {
+            self.balance = (self.balance - amount)?;
+            Ok(())
+        }
+    }
+
+    impl RegisterInvariants for Bank {
+        fn register_invariants(registry: &mut InvariantRegistry<Self>) {
+            registry.register("bank holds all supply", |bank, supply| {
+                if bank.balance != supply.total_of::<Simp>()? {
+                    return Err(Error::Coins("Bank balance does not match supply".into()));
+                }
+                Ok(())
+            });
+        }
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn tracks_supply_and_checks_invariants() -> Result<()> {
+        let mut plugin = SupplyPlugin::<Bank> {
+            invariant_check_interval: 2,
+            ..Default::default()
+        };
+
+        plugin.call(<Bank as Call>::Call::Method(BankMethodCall::Mint(
+            100.into(),
+        )))?;
+        plugin.call(<Bank as Call>::Call::Method(BankMethodCall::Burn(
+            30.into(),
+        )))?;
+        assert_eq!(plugin.supply.total_of::<Simp>()?, 70);
+        plugin.check_invariants()?;
+
+        plugin.call(<Bank as Call>::Call::Method(BankMethodCall::Leak(
+            10.into(),
+        )))?;
+        plugin.end_block(&EndBlockCtx { height: 1 })?;
+        plugin
+            .end_block(&EndBlockCtx { height: 2 })
+            .expect_err("Invariant should be violated");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+use super::{Iter, Read, KV};
+use crate::describe::Descriptor;
+use crate::Result;
+use serde::Serialize;
+use std::cmp::Ordering;
+
+/// A difference in a single key between two stores.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum Change {
+    Added {
+        key: Vec<u8>,
+        value: Vec<u8>,
+    },
+    Removed {
+        key: Vec<u8>,
+        value: Vec<u8>,
+    },
+    Modified {
+        key: Vec<u8>,
+        old: Vec<u8>,
+        new: Vec<u8>,
+    },
+}
+
+impl Change {
+    pub fn key(&self) -> &[u8] {
+        match self {
+            Change::Added { key, .. } => key,
+            Change::Removed { key, .. } => key,
+            Change::Modified { key, .. } => key,
+        }
+    }
+
+    /// Resolves the key and values of the change using the descriptor of the
+    /// state type at the root of the stores.
+    pub fn describe(&self, desc: &Descriptor) -> DescribedChange {
+        let (path, value_desc) = desc.resolve_key(self.key());
+        let format = |value: &[u8]| {
+            value_desc
+                .and_then(|desc| desc.format_bytes(&mut &value[..]).ok())
+                .unwrap_or_else(|| hex::encode(value))
+        };
+
+        let (kind, old, new) = match self {
+            Change::Added { value, .. } => (ChangeKind::Added, None, Some(format(value))),
+            Change::Removed { value, .. } => (ChangeKind::Removed, Some(format(value)), None),
+            Change::Modified { old, new, .. } => {
+                (ChangeKind::Modified, Some(format(old)), Some(format(new)))
+            }
+        };
+
+        DescribedChange {
+            path,
+            kind,
+            old,
+            new,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// A [Change] with its key resolved to a typed path and its values formatted.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DescribedChange {
+    pub path: String,
+    pub kind: ChangeKind,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// Compares the entries of two stores, yielding the added, removed and
+/// modified keys in ascending key order.
+pub fn diff<A: Read, B: Read>(old: A, new: B) -> Diff<A, B> {
+    Diff {
+        old: old.into_iter(..),
+        new: new.into_iter(..),
+        old_next: None,
+        new_next: None,
+    }
+}
+
+/// An iterator over the changes between two stores, created by [diff].
+pub struct Diff<A, B> {
+    old: Iter<A>,
+    new: Iter<B>,
+    old_next: Option<KV>,
+    new_next: Option<KV>,
+}
+
+impl<A: Read, B: Read> Diff<A, B> {
+    fn next_change(&mut self) -> Result<Option<Change>> {
+        loop {
+            if self.old_next.is_none() {
+                self.old_next = self.old.next().transpose()?;
+            }
+            if self.new_next.is_none() {
+                self.new_next = self.new.next().transpose()?;
+            }
+
+            let change = match (self.old_next.take(), self.new_next.take()) {
+                (None, None) => return Ok(None),
+                (Some((key, value)), None) => Change::Removed { key, value },
+                (None, Some((key, value))) => Change::Added { key, value },
+                (Some(old), Some(new)) => match old.0.cmp(&new.0) {
+                    Ordering::Less => {
+                        self.new_next = Some(new);
+                        Change::Removed {
+                            key: old.0,
+                            value: old.1,
+                        }
+                    }
+                    Ordering::Greater => {
+                        self.old_next = Some(old);
+                        Change::Added {
+                            key: new.0,
+                            value: new.1,
+                        }
+                    }
+                    Ordering::Equal if old.1 == new.1 => continue,
+                    Ordering::Equal => Change::Modified {
+                        key: old.0,
+                        old: old.1,
+                        new: new.1,
+                    },
+                },
+            };
+
+            return Ok(Some(change));
+        }
+    }
+}
+
+impl<A: Read, B: Read> Iterator for Diff<A, B> {
+    type Item = Result<Change>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_change().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::Map;
+    use crate::describe::Describe;
+    use crate::orga;
+    use crate::state::State;
+    use crate::store::{MapStore, Shared, Store, Write};
+
+    #[orga]
+    struct Foo {
+        count: u32,
+        balances: Map<u32, u64>,
+    }
+
+    fn write_foo(count: u32, balances: &[(u32, u64)]) -> Result<Shared<MapStore>> {
+        let backing = Shared::new(MapStore::new());
+        let mut store = Store::new(backing.clone().into());
+
+        let mut foo = Foo::default();
+        foo.attach(store.clone())?;
+        foo.count = count;
+        for (key, value) in balances {
+            foo.balances.insert(*key, *value)?;
+        }
+
+        let mut bytes = vec![];
+        foo.flush(&mut bytes)?;
+        store.put(vec![], bytes)?;
+
+        Ok(backing)
+    }
+
+    #[test]
+    fn diff_and_describe() -> Result<()> {
+        let old = write_foo(1, &[(1, 10), (2, 20), (3, 30)])?;
+        let new = write_foo(2, &[(1, 10), (2, 25), (4, 40)])?;
+
+        let changes: Vec<_> = diff(old.borrow(), new.borrow()).collect::<Result<_>>()?;
+        assert_eq!(changes.len(), 4);
+
+        let desc = Foo::describe();
+        let described: Vec<_> = changes.iter().map(|c| c.describe(&desc)).collect();
+        assert_eq!(described[0].kind, ChangeKind::Modified);
+        assert_eq!(described[0].path, "");
+        assert_eq!(described[1].path, "balances[2]");
+        assert_eq!(described[1].old.as_deref(), Some("20"));
+        assert_eq!(described[1].new.as_deref(), Some("25"));
+        assert_eq!(described[2].path, "balances[3]");
+        assert_eq!(described[2].kind, ChangeKind::Removed);
+        assert_eq!(described[3].path, "balances[4]");
+        assert_eq!(described[3].kind, ChangeKind::Added);
+        assert_eq!(described[3].new.as_deref(), Some("40"));
+
+        assert_eq!(diff(old.borrow(), old.borrow()).count(), 0);
+
+        Ok(())
+    }
+}
@@ -7,19 +7,23 @@ use thiserror::Error;
 
 pub mod backingstore;
 pub mod bufstore;
+pub mod diff;
 pub mod iter;
 pub mod log;
 pub mod null;
 pub mod partialmap;
+pub mod savepoint;
 pub mod share;
 #[allow(clippy::module_inception)]
 pub mod store;
 
 pub use backingstore::BackingStore;
 pub use bufstore::{BufStore, Map as BufStoreMap, MapStore};
+pub use diff::{diff, Change, Diff};
 pub use iter::Iter;
 pub use null::Empty;
 pub use partialmap::PartialMapStore;
+pub use savepoint::{with_savepoint, AttachedStore, Savepoint};
 pub use share::Shared;
 pub use store::{DefaultBackingStore, Store};
 
@@ -0,0 +1,185 @@
+use super::{BackingStore, BufStore, Shared, Store};
+use crate::state::State;
+use crate::Result;
+
+/// A layer of buffered writes on top of a store, which can either be
+/// committed to the store or discarded.
+///
+/// Savepoints can be nested by opening a savepoint on the store of another
+/// savepoint.
+pub struct Savepoint {
+    layer: Shared<BufStore<Store>>,
+}
+
+impl Savepoint {
+    /// Opens a savepoint on top of the given store.
+    pub fn open(store: &Store) -> Self {
+        Self {
+            layer: Shared::new(BufStore::wrap(store.clone())),
+        }
+    }
+
+    /// Returns a store which reads through the savepoint and buffers its
+    /// writes in it.
+    pub fn store(&self) -> Store {
+        let layer: Box<dyn super::ReadWrite> = Box::new(self.layer.clone());
+        Store::new(BackingStore::Other(Shared::new(layer)))
+    }
+
+    /// Writes all buffered writes to the underlying store.
+    pub fn commit(mut self) -> Result<()> {
+        self.layer.borrow_mut().flush()
+    }
+
+    /// Discards all buffered writes.
+    pub fn revert(self) {}
+}
+
+/// Implemented by stateful types which can return the store they are
+/// attached to, so that a savepoint can be opened on it by
+/// [with_savepoint].
+pub trait AttachedStore {
+    fn attached_store(&self) -> Store;
+}
+
+impl AttachedStore for Store {
+    fn attached_store(&self) -> Store {
+        self.clone()
+    }
+}
+
+/// Runs `op` on `value` within a savepoint on the store `value` is attached
+/// to.
+///
+/// If `op` succeeds, its writes are kept. If it fails, `value` is restored to
+/// its state from before `op` ran and the error is returned in the inner
+/// result, allowing the caller to fall back or continue with other work. The
+/// outer result is an error if the state itself could not be saved or loaded,
+/// in which case `value` is also restored unless it could not be saved in the
+/// first place.
+pub fn with_savepoint<T, R, F>(value: &mut T, op: F) -> Result<Result<R>>
+where
+    T: State + AttachedStore + Default,
+    F: FnOnce(&mut T) -> Result<R>,
+{
+    let store = value.attached_store();
+    let mut original = vec![];
+    std::mem::take(value).flush(&mut original)?;
+
+    let savepoint = Savepoint::open(&store);
+    let outcome = (|| {
+        let mut inner = T::load(savepoint.store(), &mut original.as_slice())?;
+        let res = op(&mut inner);
+
+        let mut bytes = vec![];
+        if res.is_ok() {
+            inner.flush(&mut bytes)?;
+        }
+
+        Ok((res, bytes))
+    })();
+
+    let (res, bytes) = match outcome {
+        Ok((Ok(res), bytes)) => match savepoint.commit() {
+            Ok(()) => (Ok(Ok(res)), bytes),
+            Err(err) => (Err(err), original),
+        },
+        Ok((Err(err), _)) => {
+            savepoint.revert();
+            (Ok(Err(err)), original)
+        }
+        Err(err) => {
+            savepoint.revert();
+            (Err(err), original)
+        }
+    };
+
+    *value = T::load(store, &mut bytes.as_slice())?;
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::Map;
+    use crate::orga;
+    use crate::Error;
+
+    #[orga]
+    struct Counter {
+        count: u32,
+        #[state(prefix(b""))]
+        store: Store,
+        entries: Map<u32, u32>,
+    }
+
+    impl AttachedStore for Counter {
+        fn attached_store(&self) -> Store {
+            self.store.clone()
+        }
+    }
+
+    impl Counter {
+        fn add(&mut self, key: u32, fail: bool) -> Result<()> {
+            self.count += 1;
+            self.entries.insert(key, self.count)?;
+            if fail {
+                return Err(Error::App("Failed".into()));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn nested_savepoints() -> Result<()> {
+        let store = Store::with_map_store();
+        let mut counter = Counter::default();
+        counter.attach(store.clone())?;
+
+        counter.add(1, false)?;
+
+        let res = with_savepoint(&mut counter, |counter| counter.add(2, true))?;
+        assert!(res.is_err());
+        assert_eq!(counter.count, 1);
+        assert!(counter.entries.get(2)?.is_none());
+
+        with_savepoint(&mut counter, |counter| {
+            counter.add(3, false)?;
+
+            let res = with_savepoint(counter, |counter| counter.add(4, true))?;
+            assert!(res.is_err());
+
+            with_savepoint(counter, |counter| counter.add(5, false))?
+        })??;
+
+        assert_eq!(counter.count, 3);
+        assert_eq!(*counter.entries.get(1)?.unwrap(), 1);
+        assert_eq!(*counter.entries.get(3)?.unwrap(), 2);
+        assert!(counter.entries.get(4)?.is_none());
+        assert_eq!(*counter.entries.get(5)?.unwrap(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn collection_savepoint() -> Result<()> {
+        let store = Store::with_map_store();
+        let mut entries: Map<u32, u32> = Map::with_store(store.sub(&[1]))?;
+        entries.insert(1, 1)?;
+
+        let res = with_savepoint(&mut entries, |entries| {
+            entries.insert(2, 2)?;
+            entries.remove(1)?;
+            Err::<(), _>(Error::App("Failed".into()))
+        })?;
+        assert!(res.is_err());
+        assert_eq!(*entries.get(1)?.unwrap(), 1);
+        assert!(entries.get(2)?.is_none());
+
+        with_savepoint(&mut entries, |entries| entries.insert(3, 3))??;
+        assert_eq!(*entries.get(3)?.unwrap(), 3);
+
+        Ok(())
+    }
+}
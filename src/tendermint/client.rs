@@ -1,22 +1,51 @@
+#[cfg(feature = "merk-verify")]
 use crate::{
     abci::App,
     call::Call,
     client::{sync::Transport as SyncTransport, Transport},
     encoding::Encode,
-    merk::ProofStore,
+    merk::{verify_batch, BatchQuery, ProofStore},
     plugins::{ABCICall, ABCIPlugin},
     query::Query,
     state::State,
-    store::{BackingStore, Shared, Store},
+    store::{BackingStore, PartialMapStore, Shared, Store},
+};
+use crate::{
+    client::{tx::TxEvent, TxResponse},
     Error, Result,
 };
+#[cfg(feature = "merk-verify")]
 use futures_lite::future::block_on;
+use std::time::Duration;
+use tendermint::{abci::Event, hash::Algorithm, Hash};
 use tendermint_rpc::{self as tm, Client as _};
 use tokio::sync::Mutex;
 
+/// How long to wait between polls for a transaction's inclusion.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Determines when [HttpClient] returns after broadcasting a transaction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BroadcastMode {
+    /// Returns as soon as the transaction is received by the node, without
+    /// checking it. The response only contains the transaction hash.
+    Async,
+    /// Returns once the transaction has passed `CheckTx` and entered the
+    /// mempool. The response has a height of 0.
+    Sync,
+    /// Waits for the transaction to be committed using Tendermint's
+    /// `broadcast_tx_commit`, which may time out under load.
+    #[default]
+    Commit,
+    /// Broadcasts like [BroadcastMode::Sync], then polls for the transaction
+    /// until it is included in a block or the timeout elapses.
+    Confirm(Duration),
+}
+
 pub struct HttpClient {
     client: tm::HttpClient,
     height: Mutex<Option<u32>>,
+    mode: BroadcastMode,
 }
 
 impl HttpClient {
@@ -24,6 +53,7 @@ impl HttpClient {
         Ok(Self {
             client: tm::HttpClient::new(url)?,
             height: Mutex::new(None),
+            mode: BroadcastMode::default(),
         })
     }
 
@@ -31,26 +61,75 @@ impl HttpClient {
         Ok(Self {
             client: tm::HttpClient::new(url)?,
             height: Mutex::new(Some(height)),
+            mode: BroadcastMode::default(),
         })
     }
+
+    #[must_use]
+    pub fn with_broadcast_mode(mut self, mode: BroadcastMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Polls the node for the transaction with the given hash until it is
+    /// included in a block, returning its result. Errors if the transaction is
+    /// not found before the timeout elapses.
+    pub async fn wait_for_tx(&self, hash: &str, timeout: Duration) -> Result<TxResponse> {
+        let tm_hash = Hash::from_hex_upper(Algorithm::Sha256, hash)
+            .map_err(|err| Error::Tendermint(err.to_string()))?;
+
+        poll_tx(&self.client, tm_hash, timeout).await?.into_result()
+    }
+
+    /// Broadcasts an encoded transaction using the client's
+    /// [BroadcastMode].
+    pub async fn broadcast(&self, tx: Vec<u8>) -> Result<TxResponse> {
+        broadcast(&self.client, self.mode, tx).await
+    }
+
+    /// Queries a single proof for all the keys and ranges in the batch,
+    /// returning the verified entries.
+    #[cfg(feature = "merk-verify")]
+    pub async fn prove_batch(&self, query: &BatchQuery) -> Result<PartialMapStore> {
+        let maybe_height = self.height.lock().await.map(Into::into);
+        let res = self
+            .client
+            .abci_query(
+                Some("/batch_proof".to_string()),
+                query.encode()?,
+                maybe_height,
+                true,
+            )
+            .await?;
+
+        if let tendermint::abci::Code::Err(code) = res.code {
+            let msg = format!("code {}: {}", code, res.log);
+            return Err(Error::Query(msg));
+        }
+
+        // TODO: the root hash should come from a trusted source
+        let root_hash = match res.value.get(0..32).map(TryInto::try_into) {
+            Some(Ok(inner)) => inner,
+            _ => {
+                return Err(Error::Tendermint(
+                    "Cannot convert result to fixed size array".into(),
+                ));
+            }
+        };
+
+        verify_batch(&res.value[32..], root_hash, query)
+    }
 }
 
+#[cfg(feature = "merk-verify")]
 impl<T: App + Call + Query + State + Default> Transport<ABCIPlugin<T>> for HttpClient {
-    async fn call(&self, call: <ABCIPlugin<T> as Call>::Call) -> Result<()> {
+    async fn call(&self, call: <ABCIPlugin<T> as Call>::Call) -> Result<TxResponse> {
         // TODO: shouldn't need to deal with ABCIPlugin at this level
         let call = match call {
             ABCICall::DeliverTx(call) => call,
             _ => return Err(Error::Client("Unexpected call type".into())),
         };
-        let call_bytes = call.encode()?;
-        let res = self.client.broadcast_tx_commit(call_bytes).await?;
-
-        if let tendermint::abci::Code::Err(code) = res.check_tx.code {
-            let msg = format!("code {}: {}", code, res.check_tx.log);
-            return Err(Error::Call(msg));
-        }
-
-        Ok(())
+        self.broadcast(call.encode()?).await
     }
 
     async fn query(&self, query: T::Query) -> Result<Store> {
@@ -89,8 +168,121 @@ impl<T: App + Call + Query + State + Default> Transport<ABCIPlugin<T>> for HttpC
     }
 }
 
+/// Broadcasts a transaction, returning once it has progressed as far as the
+/// given mode requires.
+async fn broadcast<C: tm::Client + Sync>(
+    client: &C,
+    mode: BroadcastMode,
+    tx: Vec<u8>,
+) -> Result<TxResponse> {
+    match mode {
+        BroadcastMode::Async => {
+            let res = client.broadcast_tx_async(tx).await?;
+            Ok(TxResponse {
+                hash: res.hash.to_string(),
+                ..Default::default()
+            })
+        }
+        BroadcastMode::Sync | BroadcastMode::Confirm(_) => {
+            let res = client.broadcast_tx_sync(tx).await?;
+            if res.code.is_err() {
+                return Err(Error::CheckTx {
+                    code: res.code.value(),
+                    codespace: res.codespace,
+                    log: res.log,
+                });
+            }
+
+            match mode {
+                BroadcastMode::Confirm(timeout) => {
+                    poll_tx(client, res.hash, timeout).await?.into_result()
+                }
+                _ => Ok(TxResponse {
+                    hash: res.hash.to_string(),
+                    log: res.log,
+                    ..Default::default()
+                }),
+            }
+        }
+        BroadcastMode::Commit => {
+            let res = client.broadcast_tx_commit(tx).await?;
+            if res.check_tx.code.is_err() {
+                return Err(Error::CheckTx {
+                    code: res.check_tx.code.value(),
+                    codespace: res.check_tx.codespace,
+                    log: res.check_tx.log,
+                });
+            }
+
+            TxResponse {
+                hash: res.hash.to_string(),
+                height: res.height.value(),
+                check_code: res.check_tx.code.value(),
+                deliver_code: res.deliver_tx.code.value(),
+                codespace: res.deliver_tx.codespace,
+                log: res.deliver_tx.log,
+                gas_wanted: res.deliver_tx.gas_wanted,
+                gas_used: res.deliver_tx.gas_used,
+                events: tx_events(res.deliver_tx.events),
+            }
+            .into_result()
+        }
+    }
+}
+
+/// Polls the node until the transaction with the given hash has been indexed,
+/// returning its result even if it failed.
+pub(crate) async fn poll_tx<C: tm::Client + Sync>(
+    client: &C,
+    hash: Hash,
+    timeout: Duration,
+) -> Result<TxResponse> {
+    let wait = async {
+        loop {
+            // the node returns an error until the tx has been indexed
+            if let Ok(res) = client.tx(hash, false).await {
+                return tx_response(res);
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    };
+
+    tokio::time::timeout(timeout, wait)
+        .await
+        .map_err(|_| Error::Client(format!("Timed out waiting for tx {} to be included", hash)))
+}
+
+pub(crate) fn tx_response(res: tm::endpoint::tx::Response) -> TxResponse {
+    TxResponse {
+        hash: res.hash.to_string(),
+        height: res.height.value(),
+        check_code: 0,
+        deliver_code: res.tx_result.code.value(),
+        codespace: res.tx_result.codespace,
+        log: res.tx_result.log,
+        gas_wanted: res.tx_result.gas_wanted,
+        gas_used: res.tx_result.gas_used,
+        events: tx_events(res.tx_result.events),
+    }
+}
+
+pub(crate) fn tx_events(events: Vec<Event>) -> Vec<TxEvent> {
+    events
+        .into_iter()
+        .map(|event| TxEvent {
+            kind: event.kind,
+            attributes: event
+                .attributes
+                .into_iter()
+                .map(|attr| (attr.key, attr.value))
+                .collect(),
+        })
+        .collect()
+}
+
+#[cfg(feature = "merk-verify")]
 impl<T: App + Call + Query + State + Default> SyncTransport<ABCIPlugin<T>> for HttpClient {
-    fn call_sync(&self, call: <ABCIPlugin<T> as Call>::Call) -> Result<()> {
+    fn call_sync(&self, call: <ABCIPlugin<T> as Call>::Call) -> Result<TxResponse> {
         block_on(Transport::<ABCIPlugin<T>>::call(self, call))
     }
 
@@ -101,9 +293,11 @@ impl<T: App + Call + Query + State + Default> SyncTransport<ABCIPlugin<T>> for H
 
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "merk-verify")]
+    use crate::client::AppClient;
     use crate::{
         abci::InitChain,
-        client::{wallet::DerivedKey, AppClient},
+        client::{tx::tx_hash, wallet::DerivedKey},
         coins::{Accounts, Symbol},
         collections::Map,
         context::Context,
@@ -112,7 +306,9 @@ mod tests {
 
     use super::*;
     use orga::orga;
+    #[cfg(feature = "merk-verify")]
     use orga_macros::build_call;
+    use tendermint_rpc::{Method, MockClient, MockRequestMethodMatcher};
 
     #[orga]
     #[derive(Debug, Clone, Copy)]
@@ -148,7 +344,7 @@ mod tests {
         }
     }
 
-    #[cfg(feature = "tokio")]
+    #[cfg(all(feature = "tokio", feature = "merk-full"))]
     #[tokio::test]
     #[serial_test::serial]
     pub async fn spawn_node() {
@@ -190,7 +386,7 @@ mod tests {
     }
 
     #[ignore]
-    #[cfg(feature = "tokio")]
+    #[cfg(all(feature = "tokio", feature = "merk-verify", feature = "merk-full"))]
     #[tokio::test]
     #[serial_test::serial]
     async fn basic_async() -> Result<()> {
@@ -214,15 +410,17 @@ mod tests {
             .unwrap();
         assert_eq!(res.value, 100_000);
 
-        client
+        let tx = client
             .call(
                 |app| build_call!(app.accounts.take_as_funding(50_000.into())),
                 |app| build_call!(app.increment_foo()),
             )
             .await
             .unwrap();
+        assert_eq!(tx.deliver_code, 0);
+        assert_eq!(tx.hash.len(), 64);
 
-        let old_height = 2; // TODO: get from call response
+        let old_height = tx.height as u32 - 1;
         let client = HttpClient::with_height("http://localhost:26657", old_height).unwrap();
         let client =
             AppClient::<App, App, _, FooCoin, _>::new(client, DerivedKey::new(b"alice").unwrap());
@@ -253,7 +451,7 @@ mod tests {
     }
 
     #[ignore]
-    #[cfg(feature = "tokio")]
+    #[cfg(all(feature = "tokio", feature = "merk-verify", feature = "merk-full"))]
     #[tokio::test]
     #[serial_test::serial]
     async fn basic_sync() -> Result<()> {
@@ -280,14 +478,15 @@ mod tests {
                 .unwrap();
             assert_eq!(res.value, 100_000);
 
-            client
+            let tx = client
                 .call_sync(
                     |app| build_call!(app.accounts.take_as_funding(50_000.into())),
                     |app| build_call!(app.increment_foo()),
                 )
                 .unwrap();
+            assert_eq!(tx.deliver_code, 0);
 
-            let old_height = 2; // TODO: get from call response
+            let old_height = tx.height as u32 - 1;
             let client = HttpClient::with_height("http://localhost:26657", old_height).unwrap();
             let client = AppClient::<App, App, _, FooCoin, _>::new(
                 client,
@@ -321,4 +520,117 @@ mod tests {
         .await
         .unwrap()
     }
+
+    fn rpc_response(result: &str) -> std::result::Result<String, tm::Error> {
+        Ok(format!(
+            r#"{{"jsonrpc":"2.0","id":"","result":{}}}"#,
+            result
+        ))
+    }
+
+    fn tx_result(code: u32, log: &str) -> String {
+        format!(
+            r#"{{"code":{},"data":"","log":"{}","info":"","gas_wanted":"100","gas_used":"50","events":[],"codespace":"app"}}"#,
+            code, log
+        )
+    }
+
+    fn mock_client(check_code: u32, deliver_code: u32) -> MockClient<MockRequestMethodMatcher> {
+        let hash = tx_hash(b"tx");
+        let sync = rpc_response(&format!(
+            r#"{{"code":{},"data":"","log":"checked","codespace":"app","hash":"{}"}}"#,
+            check_code, hash
+        ));
+        let commit = rpc_response(&format!(
+            r#"{{"check_tx":{},"deliver_tx":{},"hash":"{}","height":"5"}}"#,
+            tx_result(check_code, "checked"),
+            tx_result(deliver_code, "delivered"),
+            hash
+        ));
+        let tx = rpc_response(&format!(
+            r#"{{"hash":"{}","height":"5","index":0,"tx_result":{},"tx":"dHg=","proof":null}}"#,
+            hash,
+            tx_result(deliver_code, "delivered")
+        ));
+
+        let matcher = MockRequestMethodMatcher::default()
+            .map(Method::BroadcastTxAsync, sync.clone())
+            .map(Method::BroadcastTxSync, sync)
+            .map(Method::BroadcastTxCommit, commit)
+            .map(Method::Tx, tx);
+        MockClient::new(matcher).0
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn broadcast_modes() -> Result<()> {
+        let client = mock_client(0, 0);
+        let hash = tx_hash(b"tx");
+
+        let res = broadcast(&client, BroadcastMode::Async, b"tx".to_vec()).await?;
+        assert_eq!(res.hash, hash);
+        assert_eq!(res.height, 0);
+        assert_eq!(res.log, "");
+
+        let res = broadcast(&client, BroadcastMode::Sync, b"tx".to_vec()).await?;
+        assert_eq!(res.hash, hash);
+        assert_eq!(res.height, 0);
+        assert_eq!(res.log, "checked");
+
+        let res = broadcast(&client, BroadcastMode::Commit, b"tx".to_vec()).await?;
+        assert_eq!(res.hash, hash);
+        assert_eq!(res.height, 5);
+        assert_eq!(res.log, "delivered");
+        assert_eq!(res.gas_used, 50);
+
+        let timeout = Duration::from_secs(1);
+        let res = broadcast(&client, BroadcastMode::Confirm(timeout), b"tx".to_vec()).await?;
+        assert_eq!(res.hash, hash);
+        assert_eq!(res.height, 5);
+        assert_eq!(res.log, "delivered");
+
+        Ok(())
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn broadcast_modes_failed_tx() {
+        let client = mock_client(1, 0);
+        for mode in [
+            BroadcastMode::Sync,
+            BroadcastMode::Commit,
+            BroadcastMode::Confirm(Duration::from_secs(1)),
+        ] {
+            let err = broadcast(&client, mode, b"tx".to_vec()).await.unwrap_err();
+            assert!(
+                matches!(err, Error::CheckTx { code: 1, ref codespace, .. } if codespace == "app"),
+                "{:?}",
+                mode
+            );
+        }
+
+        let client = mock_client(0, 2);
+        for mode in [
+            BroadcastMode::Commit,
+            BroadcastMode::Confirm(Duration::from_secs(1)),
+        ] {
+            let err = broadcast(&client, mode, b"tx".to_vec()).await.unwrap_err();
+            assert!(
+                matches!(
+                    err,
+                    Error::DeliverTx {
+                        code: 2,
+                        height: 5,
+                        ..
+                    }
+                ),
+                "{:?}",
+                mode
+            );
+        }
+
+        // async broadcasts return before the tx is checked
+        let res = broadcast(&client, BroadcastMode::Async, b"tx".to_vec()).await;
+        assert!(res.is_ok());
+    }
 }
@@ -1,4 +1,5 @@
 pub mod client;
+pub mod subscription;
 
 use crate::error::{Error, Result};
 use flate2::read::GzDecoder;
@@ -0,0 +1,369 @@
+use super::client::{poll_tx, tx_response};
+use crate::client::{tx::tx_hash, TxResponse};
+use crate::{Error, Result};
+use futures_lite::{Stream, StreamExt};
+use std::pin::Pin;
+use std::str::FromStr;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tendermint::block::Height;
+use tendermint::{hash::Algorithm, Hash};
+use tendermint_rpc::event::{EventData, TxInfo};
+use tendermint_rpc::query::{EventType, Query};
+use tendermint_rpc::{Client as _, Order, SubscriptionClient, WebSocketClient};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// The number of transactions fetched per page when catching up on missed
+/// heights.
+const TX_SEARCH_PAGE_SIZE: u8 = 100;
+
+/// How long to wait for the node to index a transaction received from the
+/// event stream before reconnecting.
+const TX_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An event received from a [Subscription].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChainEvent {
+    NewBlock { height: u64, time: String },
+    Tx(TxResponse),
+}
+
+impl ChainEvent {
+    pub fn height(&self) -> u64 {
+        match self {
+            ChainEvent::NewBlock { height, .. } => *height,
+            ChainEvent::Tx(tx) => tx.height,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Kind {
+    Blocks,
+    Txs(Option<Query>),
+}
+
+/// Configures a subscription to a node's websocket RPC endpoint, e.g.
+/// `ws://localhost:26657/websocket`.
+///
+/// The subscription reconnects automatically if the connection is lost. After
+/// reconnecting, or when started with [Subscriber::from_height], blocks and
+/// transactions from heights which were missed are fetched before streaming
+/// new events, so no events are skipped. Transactions from the last height
+/// seen before a reconnect may be delivered again.
+///
+/// Transaction events do not include the result code, so each transaction's
+/// result is fetched from the node by its hash before it is streamed.
+#[derive(Clone, Debug)]
+pub struct Subscriber {
+    url: String,
+    kind: Kind,
+    from_height: Option<u64>,
+    reconnect_delay: Duration,
+}
+
+impl Subscriber {
+    /// Subscribes to every new block.
+    pub fn blocks(url: &str) -> Self {
+        Self::new(url, Kind::Blocks)
+    }
+
+    /// Subscribes to the results of transactions included in blocks.
+    pub fn txs(url: &str) -> Self {
+        Self::new(url, Kind::Txs(None))
+    }
+
+    fn new(url: &str, kind: Kind) -> Self {
+        Self {
+            url: url.to_string(),
+            kind,
+            from_height: None,
+            reconnect_delay: Duration::from_secs(5),
+        }
+    }
+
+    /// Only streams transactions with events matching the query, e.g.
+    /// `Query::eq("transfer.recipient", address)`.
+    ///
+    /// The query should only contain conditions on event attributes, since it
+    /// is also used to search for transactions from missed heights.
+    #[must_use]
+    pub fn filter(mut self, query: Query) -> Self {
+        if let Kind::Txs(ref mut filter) = self.kind {
+            filter.replace(query);
+        }
+        self
+    }
+
+    /// Starts streaming from the given height, rather than from the next block.
+    #[must_use]
+    pub fn from_height(mut self, height: u64) -> Self {
+        self.from_height = Some(height);
+        self
+    }
+
+    #[must_use]
+    pub fn reconnect_delay(mut self, delay: Duration) -> Self {
+        self.reconnect_delay = delay;
+        self
+    }
+
+    /// Spawns a task which maintains the websocket connection, returning a
+    /// stream of its events. The task stops when the [Subscription] is
+    /// dropped.
+    pub fn subscribe(self) -> Result<Subscription> {
+        // validate queries before spawning so errors are returned to the
+        // caller rather than retried
+        self.query()?;
+        self.search_query(0, 0)?;
+
+        let (sender, receiver) = mpsc::channel(256);
+        let task = tokio::spawn(async move {
+            let mut next_height = self.from_height;
+            loop {
+                match self.stream(&mut next_height, &sender).await {
+                    Ok(()) => return,
+                    Err(err) => log::warn!("Subscription error: {}. Reconnecting...", err),
+                }
+
+                if sender.is_closed() {
+                    return;
+                }
+                tokio::time::sleep(self.reconnect_delay).await;
+            }
+        });
+
+        Ok(Subscription { receiver, task })
+    }
+
+    fn query(&self) -> Result<Query> {
+        match &self.kind {
+            Kind::Blocks => Ok(EventType::NewBlock.into()),
+            Kind::Txs(None) => Ok(EventType::Tx.into()),
+            Kind::Txs(Some(filter)) => parse_query(&format!("tm.event = 'Tx' AND {}", filter)),
+        }
+    }
+
+    fn search_query(&self, start: u64, end: u64) -> Result<Query> {
+        let range = format!("tx.height >= {} AND tx.height <= {}", start, end);
+        match &self.kind {
+            Kind::Txs(Some(filter)) => parse_query(&format!("{} AND {}", filter, range)),
+            _ => parse_query(&range),
+        }
+    }
+
+    /// Connects and forwards events until the connection fails, returning
+    /// `Ok` once the receiver has been dropped.
+    async fn stream(
+        &self,
+        next_height: &mut Option<u64>,
+        sender: &mpsc::Sender<Result<ChainEvent>>,
+    ) -> Result<()> {
+        let (client, driver) = WebSocketClient::new(self.url.as_str()).await?;
+        let driver = tokio::spawn(async move { driver.run().await });
+
+        let res = self.stream_with(&client, next_height, sender).await;
+
+        let _ = client.close();
+        driver.abort();
+
+        res
+    }
+
+    async fn stream_with(
+        &self,
+        client: &WebSocketClient,
+        next_height: &mut Option<u64>,
+        sender: &mpsc::Sender<Result<ChainEvent>>,
+    ) -> Result<()> {
+        // subscribe before catching up so no heights are missed in between
+        let mut subscription = client.subscribe(self.query()?).await?;
+
+        if let Some(start) = *next_height {
+            let latest = client.latest_block().await?.block.header.height.value();
+            if start <= latest {
+                if !self
+                    .catch_up(client, start, latest, next_height, sender)
+                    .await?
+                {
+                    return Ok(());
+                }
+                next_height.replace(latest + 1);
+            }
+        }
+
+        while let Some(event) = subscription.next().await {
+            let event = match event?.data {
+                EventData::NewBlock {
+                    block: Some(block), ..
+                } => ChainEvent::NewBlock {
+                    height: block.header.height.value(),
+                    time: block.header.time.to_rfc3339(),
+                },
+                EventData::Tx { tx_result } => {
+                    if next_height.map_or(false, |next| (tx_result.height as u64) < next) {
+                        continue;
+                    }
+                    // resume from this height if the result can't be fetched
+                    next_height.get_or_insert(tx_result.height as u64);
+                    ChainEvent::Tx(fetch_tx(client, &tx_result).await?)
+                }
+                _ => continue,
+            };
+
+            if next_height.map_or(false, |next| event.height() < next) {
+                continue;
+            }
+            if !send(event, next_height, sender).await {
+                return Ok(());
+            }
+        }
+
+        Err(Error::Tendermint("Event stream closed".into()))
+    }
+
+    /// Fetches the events for the inclusive range of heights, sending each one
+    /// as soon as it has been fetched. Returns `false` if the receiver has been
+    /// dropped.
+    async fn catch_up(
+        &self,
+        client: &WebSocketClient,
+        start: u64,
+        end: u64,
+        next_height: &mut Option<u64>,
+        sender: &mpsc::Sender<Result<ChainEvent>>,
+    ) -> Result<bool> {
+        match self.kind {
+            Kind::Blocks => {
+                for height in start..=end {
+                    let height = Height::try_from(height)
+                        .map_err(|err| Error::Tendermint(err.to_string()))?;
+                    let block = client.block(height).await?.block;
+                    let event = ChainEvent::NewBlock {
+                        height: block.header.height.value(),
+                        time: block.header.time.to_rfc3339(),
+                    };
+                    if !send(event, next_height, sender).await {
+                        return Ok(false);
+                    }
+                }
+            }
+            Kind::Txs(_) => {
+                let query = self.search_query(start, end)?;
+                let mut page = 1;
+                let mut fetched = 0;
+                loop {
+                    let res = client
+                        .tx_search(
+                            query.clone(),
+                            false,
+                            page,
+                            TX_SEARCH_PAGE_SIZE,
+                            Order::Ascending,
+                        )
+                        .await?;
+                    let done = res.txs.len() < TX_SEARCH_PAGE_SIZE as usize;
+                    fetched += res.txs.len() as u32;
+                    for tx in res.txs {
+                        if !send(ChainEvent::Tx(tx_response(tx)), next_height, sender).await {
+                            return Ok(false);
+                        }
+                    }
+                    if done || fetched >= res.total_count {
+                        break;
+                    }
+                    page += 1;
+                }
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// Sends an event to the subscription and advances the height to resume from
+/// after a reconnect. Returns `false` if the receiver has been dropped.
+async fn send(
+    event: ChainEvent,
+    next_height: &mut Option<u64>,
+    sender: &mpsc::Sender<Result<ChainEvent>>,
+) -> bool {
+    // txs share a height, so only blocks advance past their height
+    let next = match event {
+        ChainEvent::NewBlock { height, .. } => height + 1,
+        ChainEvent::Tx(ref tx) => tx.height,
+    };
+    if sender.send(Ok(event)).await.is_err() {
+        return false;
+    }
+    next_height.replace(next);
+
+    true
+}
+
+/// Fetches the full result of a transaction received from the event stream,
+/// which does not include its result code.
+async fn fetch_tx(client: &WebSocketClient, info: &TxInfo) -> Result<TxResponse> {
+    let hash = Hash::from_hex_upper(Algorithm::Sha256, &tx_hash(&info.tx))
+        .map_err(|err| Error::Tendermint(err.to_string()))?;
+
+    poll_tx(client, hash, TX_FETCH_TIMEOUT).await
+}
+
+fn parse_query(query: &str) -> Result<Query> {
+    Query::from_str(query).map_err(|err| Error::Tendermint(err.to_string()))
+}
+
+/// A stream of [ChainEvent]s, created by [Subscriber::subscribe].
+pub struct Subscription {
+    receiver: mpsc::Receiver<Result<ChainEvent>>,
+    task: JoinHandle<()>,
+}
+
+impl Subscription {
+    /// Waits for the next event. Returns `None` if the subscription task has
+    /// stopped.
+    pub async fn next_event(&mut self) -> Option<Result<ChainEvent>> {
+        self.receiver.recv().await
+    }
+}
+
+impl Stream for Subscription {
+    type Item = Result<ChainEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subscription_queries() -> Result<()> {
+        let subscriber = Subscriber::txs("ws://localhost:26657/websocket")
+            .filter(Query::eq("transfer.recipient", "foo"));
+        assert_eq!(
+            subscriber.query()?.to_string(),
+            "tm.event = 'Tx' AND transfer.recipient = 'foo'"
+        );
+        assert_eq!(
+            subscriber.search_query(5, 10)?.to_string(),
+            "transfer.recipient = 'foo' AND tx.height >= 5 AND tx.height <= 10"
+        );
+
+        let subscriber = Subscriber::blocks("ws://localhost:26657/websocket")
+            .filter(Query::eq("transfer.recipient", "foo"));
+        assert_eq!(subscriber.query()?.to_string(), "tm.event = 'NewBlock'");
+
+        Ok(())
+    }
+}